@@ -0,0 +1,250 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+struct Column {
+    ident: Ident,
+    ty: Type,
+    name: String,
+    primary_key: bool,
+    skip: bool,
+}
+
+impl Column {
+    fn field_name(&self) -> String {
+        unraw(&self.ident)
+    }
+
+    fn constant(&self) -> Ident {
+        format_ident!("{}", self.field_name().to_uppercase())
+    }
+}
+
+fn unraw(ident: &Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#")
+        .map(|n| n.to_owned())
+        .unwrap_or(name)
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn table_name(input: &DeriveInput) -> syn::Result<String> {
+    let mut table = snake_case(&input.ident.to_string());
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("qrex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `table = \"...\"`"))
+            }
+        })?;
+    }
+
+    Ok(table)
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Column>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "qrex derives need a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "qrex derives need a struct with named fields",
+            ))
+        }
+    };
+
+    let mut columns = vec![];
+
+    for field in fields.iter() {
+        let ident = field.ident.clone().expect("named field");
+        let mut name = unraw(&ident);
+        let mut skip = false;
+        let mut primary_key = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("qrex")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("primary_key") {
+                    primary_key = true;
+                } else {
+                    return Err(meta.error("expected `rename = \"...\"`, `skip` or `primary_key`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        columns.push(Column {
+            ident,
+            ty: field.ty.clone(),
+            name,
+            primary_key,
+            skip,
+        });
+    }
+
+    Ok(columns)
+}
+
+fn columns(input: &DeriveInput) -> syn::Result<Vec<Column>> {
+    Ok(fields(input)?.into_iter().filter(|c| !c.skip).collect())
+}
+
+fn expand_table(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let table = table_name(input)?;
+    let columns = columns(input)?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let constants = columns.iter().map(|c| {
+        let constant = c.constant();
+        let ty = &c.ty;
+        let name = &c.name;
+        quote! {
+            pub const #constant: ::qrex::query_builder::schema::Column<#ty> =
+                ::qrex::query_builder::schema::Column::new(#table, #name);
+        }
+    });
+
+    let names = columns.iter().map(|c| &c.name);
+    let primary_key = columns
+        .iter()
+        .filter(|c| c.primary_key)
+        .map(|c| &c.name)
+        .collect::<Vec<&String>>();
+    let definitions = columns.iter().map(|c| {
+        let constant = c.constant();
+        quote! { .column(Self::#constant.def()) }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#constants)*
+        }
+
+        impl #impl_generics ::qrex::query_builder::schema::Table for #ident #ty_generics #where_clause {
+            const TABLE_NAME: &'static str = #table;
+
+            fn columns() -> Vec<&'static str> {
+                vec![#(#names),*]
+            }
+
+            fn primary_key() -> Vec<&'static str> {
+                vec![#(#primary_key),*]
+            }
+
+            fn table() -> ::qrex::query_builder::schema::TableDef {
+                ::qrex::query_builder::schema::TableDef::new(#table)
+                    #(#definitions)*
+                    .primary_key(&[#(#primary_key),*])
+            }
+        }
+    })
+}
+
+fn expand_selectable(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let columns = columns(input)?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = columns.iter().map(|c| {
+        let name = &c.name;
+        let field = c.field_name();
+        quote! { (#name, #field) }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::qrex::query_builder::schema::Selectable for #ident #ty_generics #where_clause {
+            fn select_fields() -> Vec<(&'static str, &'static str)> {
+                vec![#(#fields),*]
+            }
+        }
+    })
+}
+
+fn expand_from_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(input)?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // read by field name, the alias Selectable gives each column
+    let values = fields.iter().map(|c| {
+        let ident = &c.ident;
+        let field = c.field_name();
+        if c.skip {
+            quote! { #ident: ::std::default::Default::default() }
+        } else {
+            quote! { #ident: ::qrex::query_builder::row::Row::try_get(row, #field)? }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::qrex::query_builder::row::FromRow for #ident #ty_generics #where_clause {
+            fn from_row(
+                row: &impl ::qrex::query_builder::row::Row,
+            ) -> ::std::result::Result<Self, ::qrex::query_builder::error::QueryError> {
+                ::std::result::Result::Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+    })
+}
+
+/// Implements `Table` and adds one typed column constant per field.
+#[proc_macro_derive(Table, attributes(qrex))]
+pub fn derive_table(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_table(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implements `Selectable`, pairing every column with the field it is read into.
+#[proc_macro_derive(Selectable, attributes(qrex))]
+pub fn derive_selectable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_selectable(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implements `FromRow`, reading every field from the column aliased to its name.
+#[proc_macro_derive(FromRow, attributes(qrex))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_row(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -1,4 +1,9 @@
 #![allow(unused)]
-mod query_builder;
+extern crate self as qrex;
+
+pub mod query_builder;
+
+#[cfg(feature = "derive")]
+pub use qrex_derive::{FromRow, Selectable, Table};
 
 pub mod tuto;
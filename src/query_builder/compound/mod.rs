@@ -0,0 +1,383 @@
+use super::{
+    dialect::Dialect,
+    error::QueryError,
+    order_by::{Order, OrderBy, OrderEntry},
+    query::{BuildQuery, Query},
+    value::SqlValue,
+};
+
+pub enum SetOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    fn keyword(&self) -> &'static str {
+        match self {
+            SetOperator::Union => "UNION",
+            SetOperator::UnionAll => "UNION ALL",
+            SetOperator::Intersect => "INTERSECT",
+            SetOperator::Except => "EXCEPT",
+        }
+    }
+}
+
+pub struct CompoundQuery {
+    first: Query,
+    rest: Vec<(SetOperator, Query)>,
+    order: Vec<OrderEntry>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+    dialect: Dialect,
+}
+
+impl CompoundQuery {
+    pub fn new(query: Query) -> Self {
+        Self {
+            first: query,
+            rest: vec![],
+            order: vec![],
+            limit: None,
+            offset: None,
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn combine(&mut self, operator: SetOperator, query: Query) -> &mut Self {
+        self.rest.push((operator, query));
+        self
+    }
+
+    pub fn union(&mut self, query: Query) -> &mut Self {
+        self.combine(SetOperator::Union, query)
+    }
+
+    pub fn union_all(&mut self, query: Query) -> &mut Self {
+        self.combine(SetOperator::UnionAll, query)
+    }
+
+    pub fn intersect(&mut self, query: Query) -> &mut Self {
+        self.combine(SetOperator::Intersect, query)
+    }
+
+    pub fn except(&mut self, query: Query) -> &mut Self {
+        self.combine(SetOperator::Except, query)
+    }
+
+    pub fn limit(&mut self, limit: u64) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    // members built from raw sql or `*` carry no fields and are not checked
+    fn check_columns(&self) -> Result<(), QueryError> {
+        let mut expected: Option<usize> = None;
+
+        for query in std::iter::once(&self.first).chain(self.rest.iter().map(|(_, q)| q)) {
+            let found = query.fields().len();
+            if found == 0 {
+                continue;
+            }
+            match expected {
+                Some(expected) if expected != found => {
+                    return Err(QueryError::ColumnCountMismatch(expected, found))
+                }
+                _ => expected = Some(found),
+            }
+        }
+
+        Ok(())
+    }
+
+    // sqlite takes no parentheses around members, so their own ORDER BY, LIMIT or lock cannot be kept
+    fn member(&self, query: &Query, params: &mut Vec<SqlValue>) -> Result<String, QueryError> {
+        if self.dialect != Dialect::Sqlite {
+            return Ok(format!("({})", query.embed(self.dialect, params)));
+        }
+
+        let words = top_level_words(query.sql());
+        let ordered = words.windows(2).any(|w| w[0] == "ORDER" && w[1] == "BY");
+
+        if ordered
+            || words
+                .iter()
+                .any(|w| ["LIMIT", "OFFSET", "FOR"].contains(&w.as_str()))
+        {
+            return Err(QueryError::Unsupported(
+                "ORDER BY, LIMIT or locking in a compound member",
+                self.dialect,
+            ));
+        }
+
+        Ok(query.embed(self.dialect, params))
+    }
+
+    fn build_limit(&self) -> Result<Option<String>, QueryError> {
+        if self.limit.is_none() && self.offset.is_none() {
+            return Ok(None);
+        }
+
+        let offset = self.offset.unwrap_or(0);
+
+        let limit = match (self.dialect, self.limit) {
+            (Dialect::SqlServer, _) if self.order.is_empty() => {
+                return Err(QueryError::Unsupported(
+                    "OFFSET without ORDER BY",
+                    self.dialect,
+                ))
+            }
+            (Dialect::SqlServer, Some(limit)) => {
+                format!("OFFSET {} ROWS FETCH NEXT {} ROWS ONLY", offset, limit)
+            }
+            (Dialect::SqlServer, None) => format!("OFFSET {} ROWS", offset),
+            (_, Some(limit)) if offset > 0 => format!("LIMIT {} OFFSET {}", limit, offset),
+            (_, Some(limit)) => format!("LIMIT {}", limit),
+            (Dialect::Postgres, None) => format!("OFFSET {}", offset),
+            (Dialect::Sqlite, None) => format!("LIMIT -1 OFFSET {}", offset),
+            (_, None) => format!("LIMIT {} OFFSET {}", u64::MAX, offset),
+        };
+
+        Ok(Some(limit))
+    }
+
+    pub fn build_compound(&self) -> Result<Query, QueryError> {
+        self.check_columns()?;
+
+        let mut params = vec![];
+        let mut sql = self.member(&self.first, &mut params)?;
+
+        for (operator, query) in self.rest.iter() {
+            let member = self.member(query, &mut params)?;
+            sql.push_str(&format!(" {} {}", operator.keyword(), member));
+        }
+
+        if let Some(order) = self.build_order_by(self.dialect) {
+            sql.push_str(&format!(" {}", order));
+        }
+
+        if let Some(limit) = self.build_limit()? {
+            sql.push_str(&format!(" {}", limit));
+        }
+
+        Ok(Query::new(sql)
+            .with_fields(self.first.fields().to_vec())
+            .with_params(params))
+    }
+}
+
+// upper-cased words outside quotes and parentheses
+fn top_level_words(sql: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    for c in sql.chars() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '\'' | '"' | '`' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => {
+                word.push(c.to_ascii_uppercase());
+                continue;
+            }
+            _ => {}
+        }
+
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+impl OrderBy for CompoundQuery {
+    fn get_order(&self) -> Vec<&OrderEntry> {
+        self.order.iter().collect()
+    }
+
+    fn set_order(&mut self, f: impl FnOnce(&mut Vec<OrderEntry>)) {
+        f(&mut self.order)
+    }
+}
+
+impl BuildQuery for CompoundQuery {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_compound()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(sql: &str, fields: &[&str]) -> Query {
+        Query::new(sql).with_fields(fields.iter().map(|&f| f.to_owned()).collect())
+    }
+
+    #[test]
+    fn test_union_all() {
+        let mut compound =
+            CompoundQuery::new(select("SELECT id, total FROM orders", &["id", "total"]));
+        compound.union_all(select(
+            "SELECT id, total FROM orders_archive",
+            &["id", "total"],
+        ));
+        assert_eq!(
+            compound.build().unwrap().sql(),
+            "(SELECT id, total FROM orders) UNION ALL (SELECT id, total FROM orders_archive)"
+        );
+    }
+
+    #[test]
+    fn test_member_params() {
+        let mut compound = CompoundQuery::new(
+            Query::new("SELECT id FROM a WHERE x = $1 AND y = $2")
+                .bind(1)
+                .bind(2),
+        );
+        compound.union(Query::new("SELECT id FROM b WHERE x = $1").bind(3));
+        let query = compound.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "(SELECT id FROM a WHERE x = $1 AND y = $2) UNION (SELECT id FROM b WHERE x = $3)"
+        );
+        assert_eq!(
+            query.params(),
+            &[SqlValue::I32(1), SqlValue::I32(2), SqlValue::I32(3)]
+        );
+
+        let mut compound = CompoundQuery::new(Query::new("SELECT id FROM a WHERE x = ?").bind(1));
+        compound
+            .dialect(Dialect::Sqlite)
+            .union_all(Query::new("SELECT id FROM b WHERE x = ?").bind(2));
+        let query = compound.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM a WHERE x = ? UNION ALL SELECT id FROM b WHERE x = ?"
+        );
+        assert_eq!(query.params(), &[SqlValue::I32(1), SqlValue::I32(2)]);
+    }
+
+    #[test]
+    fn test_sqlite_members_without_parentheses() {
+        let mut compound = CompoundQuery::new(Query::new("SELECT id FROM a"));
+        compound
+            .dialect(Dialect::Sqlite)
+            .intersect(Query::new("SELECT id FROM b"))
+            .except(Query::new("SELECT id FROM c"));
+        assert_eq!(
+            compound.build().unwrap().sql(),
+            "SELECT id FROM a INTERSECT SELECT id FROM b EXCEPT SELECT id FROM c"
+        );
+    }
+
+    #[test]
+    fn test_sqlite_rejects_ordered_members() {
+        let mut compound = CompoundQuery::new(Query::new(
+            "SELECT id FROM a WHERE id IN (SELECT id FROM c ORDER BY id LIMIT 5)",
+        ));
+        compound
+            .dialect(Dialect::Sqlite)
+            .union(Query::new("SELECT 'order by' FROM b"));
+        assert!(compound.build().is_ok());
+
+        for member in [
+            "SELECT id FROM b ORDER BY id",
+            "SELECT id FROM b LIMIT 1",
+            "SELECT id FROM b FOR UPDATE",
+        ] {
+            let mut compound = CompoundQuery::new(Query::new("SELECT id FROM a"));
+            compound.dialect(Dialect::Sqlite).union(Query::new(member));
+            assert_eq!(
+                compound.build(),
+                Err(QueryError::Unsupported(
+                    "ORDER BY, LIMIT or locking in a compound member",
+                    Dialect::Sqlite
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_outer_order_and_limit() {
+        let mut compound = CompoundQuery::new(select("SELECT id FROM a", &["id"]));
+        compound
+            .union(select("SELECT id FROM b", &["id"]))
+            .order_by_field("id", Order::Desc)
+            .limit(10)
+            .offset(20);
+        let query = compound.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "(SELECT id FROM a) UNION (SELECT id FROM b) ORDER BY id DESC LIMIT 10 OFFSET 20"
+        );
+        assert_eq!(query.fields(), &["id".to_owned()]);
+
+        compound.dialect(Dialect::SqlServer);
+        assert_eq!(
+            compound.build().unwrap().sql(),
+            "(SELECT id FROM a) UNION (SELECT id FROM b) ORDER BY id DESC OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn test_offset_without_limit() {
+        let mut compound = CompoundQuery::new(Query::new("SELECT id FROM a"));
+        compound.union(Query::new("SELECT id FROM b")).offset(5);
+        compound.dialect(Dialect::Sqlite);
+        assert_eq!(
+            compound.build().unwrap().sql(),
+            "SELECT id FROM a UNION SELECT id FROM b LIMIT -1 OFFSET 5"
+        );
+    }
+
+    #[test]
+    fn test_sql_server_limit_requires_order() {
+        let mut compound = CompoundQuery::new(Query::new("SELECT id FROM a"));
+        compound
+            .dialect(Dialect::SqlServer)
+            .union(Query::new("SELECT id FROM b"))
+            .limit(1);
+        assert_eq!(
+            compound.build(),
+            Err(QueryError::Unsupported(
+                "OFFSET without ORDER BY",
+                Dialect::SqlServer
+            ))
+        );
+    }
+
+    #[test]
+    fn test_column_count_mismatch() {
+        let mut compound = CompoundQuery::new(select("SELECT id, name FROM a", &["id", "name"]));
+        compound
+            .union(Query::new("SELECT * FROM b"))
+            .union(select("SELECT id FROM c", &["id"]));
+        assert_eq!(compound.build(), Err(QueryError::ColumnCountMismatch(2, 1)));
+    }
+}
@@ -12,9 +12,9 @@
 
 #![allow(unused)]
 
-use super::value::SqlValue;
+use super::{dialect::Dialect, error::QueryError, parser, select::Agregate, value::SqlValue};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Condition {
     Native(String),
     Null(String),
@@ -31,6 +31,7 @@ pub enum Condition {
     Lt(String, SqlValue),
     Lte(String, SqlValue),
     Like(String, SqlValue),
+    Agregate(Box<Agregate>, Box<Self>), // The aggregate takes the place of the inner condition's column
 }
 impl Condition {
     pub fn build_conditions(&self) -> Option<String> {
@@ -67,11 +68,59 @@ impl Condition {
                     format!("{} BETWEEN {} AND {}", f, a.to_sql(), b.to_sql())
                 }
                 Condition::Native(f) => f.clone(),
+                Condition::Agregate(a, c) => gc(&c.on(a.to_string())),
             }
         }
         Some(gc(self))
     }
 
+    // same as build_conditions with every value pushed to params and replaced by a placeholder
+    pub fn build_parameterized(&self, dialect: Dialect, params: &mut Vec<SqlValue>) -> String {
+        render(self, &mut |value: &SqlValue| {
+            params.push(value.clone());
+            dialect.placeholder(params.len())
+        })
+    }
+
+    // for ddl, values are inlined as literals of the dialect
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        Ok(render(
+            &self.for_dialect(dialect)?,
+            &mut |value: &SqlValue| value.to_literal(dialect),
+        ))
+    }
+
+    // renders the aggregates for the dialect, build_conditions would use the postgres syntax
+    pub fn for_dialect(&self, dialect: Dialect) -> Result<Self, QueryError> {
+        match self {
+            Condition::And(lhs, rhs) => {
+                Ok(lhs.for_dialect(dialect)?.and(rhs.for_dialect(dialect)?))
+            }
+            Condition::Or(lhs, rhs) => Ok(lhs.for_dialect(dialect)?.or(rhs.for_dialect(dialect)?)),
+            Condition::Agregate(agregate, condition) => Ok(condition.on(agregate.to_sql(dialect)?)),
+            condition => Ok(condition.clone()),
+        }
+    }
+
+    // the same comparison on another column
+    fn on(&self, column: String) -> Self {
+        match self.clone() {
+            Condition::Null(_) => Condition::Null(column),
+            Condition::NotNull(_) => Condition::NotNull(column),
+            Condition::In(_, values) => Condition::In(column, values),
+            Condition::NotIn(_, values) => Condition::NotIn(column, values),
+            Condition::Eq(_, value) => Condition::Eq(column, value),
+            Condition::Neq(_, value) => Condition::Neq(column, value),
+            Condition::Between(_, low, high) => Condition::Between(column, low, high),
+            Condition::Gt(_, value) => Condition::Gt(column, value),
+            Condition::Gte(_, value) => Condition::Gte(column, value),
+            Condition::Lt(_, value) => Condition::Lt(column, value),
+            Condition::Lte(_, value) => Condition::Lte(column, value),
+            Condition::Like(_, value) => Condition::Like(column, value),
+            condition => condition,
+        }
+    }
+
     pub fn or(mut self, condition: Self) -> Self {
         self = Self::Or(Box::new(self.clone()), Box::new(condition));
 
@@ -82,6 +131,47 @@ impl Condition {
         self = Self::And(Box::new(self.clone()), Box::new(condition));
         self
     }
+
+    // a filter stored as text, it never produces Condition::Native
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        parser::parse_filter(text, None)
+    }
+
+    // like parse but only the allowed columns may appear
+    pub fn parse_allowed(text: &str, allowed: &[&str]) -> Result<Self, QueryError> {
+        parser::parse_filter(text, Some(allowed))
+    }
+}
+
+// renders the condition with bind writing each value
+fn render(c: &Condition, bind: &mut impl FnMut(&SqlValue) -> String) -> String {
+    match c {
+        Condition::Or(lhs, rhs) => format!("({} OR {})", render(lhs, bind), render(rhs, bind)),
+        Condition::And(lhs, rhs) => format!("({} AND {})", render(lhs, bind), render(rhs, bind)),
+        Condition::Null(f) => format!("{} IS NULL", f),
+        Condition::NotNull(f) => format!("{} IS NOT NULL", f),
+        Condition::In(f, d) => {
+            let values = d.iter().map(&mut *bind).collect::<Vec<String>>().join(", ");
+            format!("{} IN ({})", f, values)
+        }
+        Condition::NotIn(f, d) => {
+            let values = d.iter().map(&mut *bind).collect::<Vec<String>>().join(", ");
+            format!("{} NOT IN ({})", f, values)
+        }
+        Condition::Eq(f, d) => format!("{} = {}", f, bind(d)),
+        Condition::Neq(f, d) => format!("{} != {}", f, bind(d)),
+        Condition::Lt(f, d) => format!("{} < {}", f, bind(d)),
+        Condition::Lte(f, d) => format!("{} <= {}", f, bind(d)),
+        Condition::Gt(f, d) => format!("{} > {}", f, bind(d)),
+        Condition::Gte(f, d) => format!("{} >= {}", f, bind(d)),
+        Condition::Like(f, d) => format!("{} Like {}", f, bind(d)),
+        Condition::Between(f, a, b) => {
+            let low = bind(a);
+            format!("{} BETWEEN {} AND {}", f, low, bind(b))
+        }
+        Condition::Native(f) => f.clone(),
+        Condition::Agregate(a, c) => render(&c.on(a.to_string()), bind),
+    }
 }
 
 #[cfg(test)]
@@ -165,6 +255,42 @@ mod tests {
         let condition = Condition::Lte("age".to_string(), SqlValue::Int(30));
         assert_eq!(condition.build_conditions().unwrap(), "age <= 30");
     }
+    #[test]
+    fn test_parameterized_condition() {
+        let condition = Condition::Eq("name".to_string(), SqlValue::from_string_slice("bob"))
+            .and(Condition::Between(
+                "age".to_string(),
+                SqlValue::Int(18),
+                SqlValue::Int(30),
+            ))
+            .or(Condition::In(
+                "id".to_string(),
+                vec![SqlValue::Int(1), SqlValue::Int(2)],
+            ));
+        let mut params = vec![];
+        assert_eq!(
+            condition.build_parameterized(Dialect::Postgres, &mut params),
+            "((name = $1 AND age BETWEEN $2 AND $3) OR id IN ($4, $5))"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlValue::from_string_slice("bob"),
+                SqlValue::Int(18),
+                SqlValue::Int(30),
+                SqlValue::Int(1),
+                SqlValue::Int(2)
+            ]
+        );
+
+        let mut params = vec![];
+        assert_eq!(
+            Condition::Gt("age".to_string(), SqlValue::Int(3))
+                .build_parameterized(Dialect::Sqlite, &mut params),
+            "age > ?"
+        );
+    }
+
     #[test]
     fn test_and_condition() {
         let condition = Condition::Eq("age".to_string(), SqlValue::Int(30))
@@ -460,4 +586,100 @@ mod tests {
             "((status = 'active' OR last_login IS NOT NULL) AND ((salary >= 7000 AND position IS NOT NULL) OR (age < 30 OR department IS NOT NULL)))"
         );
     }
+
+    #[test]
+    fn test_parse_condition() {
+        let condition =
+            Condition::parse("age > 30 and (status = 'active' or last_login is not null)").unwrap();
+        assert_eq!(
+            condition.build_conditions().unwrap(),
+            "(age > 30 AND (status = 'active' OR last_login IS NOT NULL))"
+        );
+
+        let mut params = vec![];
+        let sql =
+            Condition::parse("name like 'a%' and id not in (1, 2) and score between 1.5 and 3")
+                .unwrap()
+                .build_parameterized(Dialect::Postgres, &mut params);
+        assert_eq!(
+            sql,
+            "((name Like $1 AND id NOT IN ($2, $3)) AND score BETWEEN $4 AND $5)"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlValue::from("a%"),
+                SqlValue::I32(1),
+                SqlValue::I32(2),
+                SqlValue::F64(1.5),
+                SqlValue::I32(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_condition_allowed_columns() {
+        let allowed = ["age", "status"];
+        assert!(Condition::parse_allowed("age >= 18 OR status = 'new'", &allowed).is_ok());
+        assert_eq!(
+            Condition::parse_allowed("age >= 18 OR password = 'x'", &allowed).map(|_| ()),
+            Err(QueryError::Syntax(
+                "unknown column password".to_owned(),
+                1,
+                14
+            ))
+        );
+        assert_eq!(
+            Condition::parse_allowed("18 <= secret", &allowed).map(|_| ()),
+            Err(QueryError::Syntax("unknown column secret".to_owned(), 1, 7))
+        );
+    }
+
+    #[test]
+    fn test_parse_condition_never_native() {
+        for (text, message, column) in [
+            (
+                "a = b",
+                "comparing two columns is not supported in a filter",
+                1,
+            ),
+            (
+                "x = 1 and not y = 2",
+                "NOT is not supported in a filter",
+                11,
+            ),
+            (
+                "name not like 'a%'",
+                "NOT LIKE is not supported in a filter",
+                1,
+            ),
+            (
+                "lower(name) = 'a'",
+                "expected a column, found an expression",
+                1,
+            ),
+            (
+                "1 = 1",
+                "comparing two values is not supported in a filter",
+                1,
+            ),
+            (
+                "deleted_at = null",
+                "comparing with NULL is not supported in a filter",
+                1,
+            ),
+            (
+                "id = 1; drop table users",
+                "expected end of input, found drop",
+                9,
+            ),
+        ] {
+            assert_eq!(
+                Condition::parse(text).map(|_| ()),
+                Err(QueryError::Syntax(message.to_owned(), 1, column)),
+                "{}",
+                text
+            );
+        }
+    }
 }
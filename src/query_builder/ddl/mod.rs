@@ -0,0 +1,1063 @@
+use super::{
+    condition::Condition,
+    dialect::Dialect,
+    error::QueryError,
+    query::{BuildQuery, Query},
+    schema::{ColumnDef, TableDef},
+    value::SqlValue,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForeignKeyAction {
+    NoAction,
+    Restrict,
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+impl ForeignKeyAction {
+    fn to_sql(self, dialect: Dialect) -> Result<&'static str, QueryError> {
+        match (self, dialect) {
+            (ForeignKeyAction::NoAction, _) => Ok("NO ACTION"),
+            (ForeignKeyAction::Restrict, Dialect::SqlServer) => {
+                Err(QueryError::Unsupported("ON DELETE RESTRICT", dialect))
+            }
+            (ForeignKeyAction::Restrict, _) => Ok("RESTRICT"),
+            (ForeignKeyAction::Cascade, _) => Ok("CASCADE"),
+            (ForeignKeyAction::SetNull, _) => Ok("SET NULL"),
+            // innodb parses SET DEFAULT but rejects the table
+            (ForeignKeyAction::SetDefault, Dialect::MySql) => {
+                Err(QueryError::Unsupported("ON DELETE SET DEFAULT", dialect))
+            }
+            (ForeignKeyAction::SetDefault, _) => Ok("SET DEFAULT"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForeignKey {
+    columns: Vec<String>,
+    table: String,
+    references: Vec<String>,
+    on_delete: Option<ForeignKeyAction>,
+}
+
+impl ForeignKey {
+    pub fn new(columns: &[&str], table: &str, references: &[&str]) -> Self {
+        Self {
+            columns: columns.iter().map(|&c| c.to_owned()).collect(),
+            table: table.to_owned(),
+            references: references.iter().map(|&c| c.to_owned()).collect(),
+            on_delete: None,
+        }
+    }
+
+    pub fn on_delete(mut self, action: ForeignKeyAction) -> Self {
+        self.on_delete = Some(action);
+        self
+    }
+
+    pub fn get_columns(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.as_str()).collect()
+    }
+
+    pub fn get_table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn get_references(&self) -> Vec<&str> {
+        self.references.iter().map(|c| c.as_str()).collect()
+    }
+
+    pub fn get_on_delete(&self) -> Option<ForeignKeyAction> {
+        self.on_delete
+    }
+
+    fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        let mut sql = format!(
+            "FOREIGN KEY ({}) REFERENCES {} ({})",
+            self.columns.join(", "),
+            self.table,
+            self.references.join(", ")
+        );
+
+        if let Some(action) = self.on_delete {
+            sql.push_str(&format!(" ON DELETE {}", action.to_sql(dialect)?));
+        }
+
+        Ok(sql)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Constraint {
+    Unique(Vec<String>),
+    Check(Condition),
+    ForeignKey(ForeignKey),
+}
+
+impl Constraint {
+    fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        match self {
+            Constraint::Unique(columns) => Ok(format!("UNIQUE ({})", columns.join(", "))),
+            Constraint::Check(condition) => Ok(format!("CHECK ({})", condition.to_sql(dialect)?)),
+            Constraint::ForeignKey(foreign_key) => foreign_key.to_sql(dialect),
+        }
+    }
+}
+
+// ddl cannot bind params, defaults are rendered as literals
+fn column_sql(column: &ColumnDef, dialect: Dialect) -> String {
+    let mut sql = format!(
+        "{} {}",
+        column.get_name(),
+        column.get_sql_type().to_sql(dialect)
+    );
+
+    if !column.is_nullable() {
+        sql.push_str(" NOT NULL");
+    }
+
+    if let Some(default) = column.get_default() {
+        sql.push_str(&format!(" DEFAULT {}", default.to_literal(dialect)));
+    }
+
+    sql
+}
+
+// sql server keeps defaults in named constraints, sqlite can only rebuild the table
+fn alter_column_sql(
+    alter: &str,
+    column: &ColumnDef,
+    dialect: Dialect,
+) -> Result<String, QueryError> {
+    let name = column.get_name();
+    let sql_type = column.get_sql_type().to_sql(dialect);
+
+    match dialect {
+        Dialect::Postgres => {
+            let nullable = match column.is_nullable() {
+                true => "DROP NOT NULL",
+                false => "SET NOT NULL",
+            };
+            let default = match column.get_default() {
+                Some(default) => format!("SET DEFAULT {}", default.to_literal(dialect)),
+                None => "DROP DEFAULT".to_owned(),
+            };
+            let column = format!("ALTER COLUMN {}", name);
+            Ok(format!(
+                "{} {} TYPE {}, {} {}, {} {}",
+                alter, column, sql_type, column, nullable, column, default
+            ))
+        }
+        Dialect::MySql => Ok(format!(
+            "{} MODIFY COLUMN {}",
+            alter,
+            column_sql(column, dialect)
+        )),
+        Dialect::SqlServer if column.get_default().is_some() => {
+            Err(QueryError::Unsupported("ALTER COLUMN DEFAULT", dialect))
+        }
+        Dialect::SqlServer => Ok(format!(
+            "{} ALTER COLUMN {}",
+            alter,
+            column_sql(column, dialect)
+        )),
+        Dialect::Sqlite => Err(QueryError::Unsupported("ALTER COLUMN", dialect)),
+    }
+}
+
+pub struct CreateTable {
+    table: TableDef,
+    if_not_exists: bool,
+    constraints: Vec<Constraint>,
+    dialect: Dialect,
+}
+
+impl CreateTable {
+    pub fn new(table: &str) -> Self {
+        Self::from_table(TableDef::new(table))
+    }
+
+    // the definition a table! module or #[derive(Table)] struct returns from table()
+    pub fn from_table(table: TableDef) -> Self {
+        Self {
+            table,
+            if_not_exists: false,
+            constraints: vec![],
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn if_not_exists(&mut self) -> &mut Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    pub fn column(&mut self, column: ColumnDef) -> &mut Self {
+        self.table = self.table.clone().column(column);
+        self
+    }
+
+    pub fn primary_key(&mut self, columns: &[&str]) -> &mut Self {
+        self.table = self.table.clone().primary_key(columns);
+        self
+    }
+
+    pub fn unique(&mut self, columns: &[&str]) -> &mut Self {
+        self.constraints.push(Constraint::Unique(
+            columns.iter().map(|&c| c.to_owned()).collect(),
+        ));
+        self
+    }
+
+    pub fn check(&mut self, condition: Condition) -> &mut Self {
+        self.constraints.push(Constraint::Check(condition));
+        self
+    }
+
+    pub fn foreign_key(&mut self, foreign_key: ForeignKey) -> &mut Self {
+        self.constraints.push(Constraint::ForeignKey(foreign_key));
+        self
+    }
+
+    pub fn build_create(&self) -> Result<Query, QueryError> {
+        if self.table.get_columns().is_empty() {
+            return Err(QueryError::NoFields);
+        }
+
+        let mut definitions = self
+            .table
+            .get_columns()
+            .iter()
+            .map(|c| column_sql(c, self.dialect))
+            .collect::<Vec<String>>();
+
+        let primary_key = self.table.get_primary_key();
+        if !primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", primary_key.join(", ")));
+        }
+
+        for constraint in self.constraints.iter() {
+            definitions.push(constraint.to_sql(self.dialect)?);
+        }
+
+        let create = match (self.if_not_exists, self.dialect) {
+            (true, Dialect::SqlServer) => {
+                return Err(QueryError::Unsupported(
+                    "CREATE TABLE IF NOT EXISTS",
+                    self.dialect,
+                ))
+            }
+            (true, _) => "CREATE TABLE IF NOT EXISTS",
+            (false, _) => "CREATE TABLE",
+        };
+
+        Ok(Query::new(format!(
+            "{} {} ({})",
+            create,
+            self.table.get_name(),
+            definitions.join(", ")
+        )))
+    }
+}
+
+impl BuildQuery for CreateTable {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_create()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AlterAction {
+    AddColumn(ColumnDef),
+    DropColumn(String),
+    RenameColumn(String, String), // Takes the current and the new name
+    AlterColumn(ColumnDef),       // Takes the column as it should end up
+    AddPrimaryKey(Vec<String>),
+    DropPrimaryKey,
+}
+
+pub struct AlterTable {
+    table: String,
+    actions: Vec<AlterAction>,
+    dialect: Dialect,
+}
+
+impl AlterTable {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_owned(),
+            actions: vec![],
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn add_column(&mut self, column: ColumnDef) -> &mut Self {
+        self.actions.push(AlterAction::AddColumn(column));
+        self
+    }
+
+    pub fn drop_column(&mut self, column: &str) -> &mut Self {
+        self.actions
+            .push(AlterAction::DropColumn(column.to_owned()));
+        self
+    }
+
+    // sets the type, nullability and default of an existing column
+    pub fn alter_column(&mut self, column: ColumnDef) -> &mut Self {
+        self.actions.push(AlterAction::AlterColumn(column));
+        self
+    }
+
+    pub fn rename_column(&mut self, from: &str, to: &str) -> &mut Self {
+        self.actions
+            .push(AlterAction::RenameColumn(from.to_owned(), to.to_owned()));
+        self
+    }
+
+    pub fn add_primary_key(&mut self, columns: &[&str]) -> &mut Self {
+        self.actions.push(AlterAction::AddPrimaryKey(
+            columns.iter().map(|&c| c.to_owned()).collect(),
+        ));
+        self
+    }
+
+    // postgres drops the key by the name it gives one declared without a name, table_pkey,
+    // sql server names it at random
+    pub fn drop_primary_key(&mut self) -> &mut Self {
+        self.actions.push(AlterAction::DropPrimaryKey);
+        self
+    }
+
+    // one statement per action, sqlite and sql server cannot combine them
+    pub fn build_alter(&self) -> Result<Vec<Query>, QueryError> {
+        if self.actions.is_empty() {
+            return Err(QueryError::NoFields);
+        }
+
+        let alter = format!("ALTER TABLE {}", self.table);
+        let dialect = self.dialect;
+
+        self.actions
+            .iter()
+            .map(|action| match (action, dialect) {
+                (AlterAction::AddColumn(column), Dialect::SqlServer) => {
+                    Ok(format!("{} ADD {}", alter, column_sql(column, dialect)))
+                }
+                (AlterAction::AddColumn(column), _) => Ok(format!(
+                    "{} ADD COLUMN {}",
+                    alter,
+                    column_sql(column, dialect)
+                )),
+                (AlterAction::DropColumn(column), _) => {
+                    Ok(format!("{} DROP COLUMN {}", alter, column))
+                }
+                (AlterAction::RenameColumn(from, to), Dialect::SqlServer) => Ok(format!(
+                    "EXEC sp_rename '{}.{}', '{}', 'COLUMN'",
+                    self.table, from, to
+                )),
+                (AlterAction::RenameColumn(from, to), _) => {
+                    Ok(format!("{} RENAME COLUMN {} TO {}", alter, from, to))
+                }
+                (AlterAction::AlterColumn(column), _) => alter_column_sql(&alter, column, dialect),
+                (AlterAction::AddPrimaryKey(_) | AlterAction::DropPrimaryKey, Dialect::Sqlite) => {
+                    Err(QueryError::Unsupported("ALTER PRIMARY KEY", dialect))
+                }
+                (AlterAction::AddPrimaryKey(columns), _) => Ok(format!(
+                    "{} ADD PRIMARY KEY ({})",
+                    alter,
+                    columns.join(", ")
+                )),
+                (AlterAction::DropPrimaryKey, Dialect::Postgres) => {
+                    let table = self.table.rsplit('.').next().unwrap_or(&self.table);
+                    Ok(format!("{} DROP CONSTRAINT {}_pkey", alter, table))
+                }
+                (AlterAction::DropPrimaryKey, Dialect::MySql) => {
+                    Ok(format!("{} DROP PRIMARY KEY", alter))
+                }
+                (AlterAction::DropPrimaryKey, _) => {
+                    Err(QueryError::Unsupported("DROP PRIMARY KEY", dialect))
+                }
+            })
+            .map(|sql| sql.map(Query::new))
+            .collect()
+    }
+}
+
+pub struct DropTable {
+    table: String,
+    if_exists: bool,
+    cascade: bool,
+    dialect: Dialect,
+}
+
+impl DropTable {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_owned(),
+            if_exists: false,
+            cascade: false,
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn if_exists(&mut self) -> &mut Self {
+        self.if_exists = true;
+        self
+    }
+
+    pub fn cascade(&mut self) -> &mut Self {
+        self.cascade = true;
+        self
+    }
+
+    pub fn build_drop(&self) -> Result<Query, QueryError> {
+        let mut sql = match self.if_exists {
+            true => format!("DROP TABLE IF EXISTS {}", self.table),
+            false => format!("DROP TABLE {}", self.table),
+        };
+
+        if self.cascade {
+            match self.dialect {
+                Dialect::Sqlite | Dialect::SqlServer => {
+                    return Err(QueryError::Unsupported("DROP TABLE CASCADE", self.dialect))
+                }
+                _ => sql.push_str(" CASCADE"),
+            }
+        }
+
+        Ok(Query::new(sql))
+    }
+}
+
+impl BuildQuery for DropTable {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_drop()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexMethod {
+    BTree,
+    Hash,
+    Gin,
+    Gist,
+}
+
+impl IndexMethod {
+    fn to_sql(self, dialect: Dialect) -> Result<&'static str, QueryError> {
+        match (self, dialect) {
+            (IndexMethod::BTree, Dialect::Postgres) => Ok("btree"),
+            (IndexMethod::Hash, Dialect::Postgres) => Ok("hash"),
+            (IndexMethod::Gin, Dialect::Postgres) => Ok("gin"),
+            (IndexMethod::Gist, Dialect::Postgres) => Ok("gist"),
+            (IndexMethod::BTree, Dialect::MySql) => Ok("BTREE"),
+            (IndexMethod::Hash, Dialect::MySql) => Ok("HASH"),
+            _ => Err(QueryError::Unsupported("index method", dialect)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum IndexPart {
+    Column(String),
+    Expression(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateIndex {
+    name: String,
+    table: String,
+    unique: bool,
+    parts: Vec<IndexPart>,
+    include: Vec<String>,
+    method: Option<IndexMethod>,
+    condition: Option<Condition>,
+    concurrently: bool,
+    dialect: Dialect,
+}
+
+impl CreateIndex {
+    pub fn new(name: &str, table: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            table: table.to_owned(),
+            unique: false,
+            parts: vec![],
+            include: vec![],
+            method: None,
+            condition: None,
+            concurrently: false,
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn unique(&mut self) -> &mut Self {
+        self.unique = true;
+        self
+    }
+
+    pub fn columns(&mut self, columns: &[&str]) -> &mut Self {
+        self.parts
+            .extend(columns.iter().map(|&c| IndexPart::Column(c.to_owned())));
+        self
+    }
+
+    // e.g. lower(email), rendered between parentheses
+    pub fn expression(&mut self, expression: &str) -> &mut Self {
+        self.parts
+            .push(IndexPart::Expression(expression.to_owned()));
+        self
+    }
+
+    // non key columns stored in the index, for index only scans
+    pub fn include(&mut self, columns: &[&str]) -> &mut Self {
+        self.include.extend(columns.iter().map(|&c| c.to_owned()));
+        self
+    }
+
+    pub fn method(&mut self, method: IndexMethod) -> &mut Self {
+        self.method = Some(method);
+        self
+    }
+
+    // partial index, e.g. a unique email among the rows that are not soft deleted
+    pub fn r#where(&mut self, condition: Condition) -> &mut Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    pub fn concurrently(&mut self) -> &mut Self {
+        self.concurrently = true;
+        self
+    }
+
+    pub fn build_create_index(&self) -> Result<Query, QueryError> {
+        if self.parts.is_empty() {
+            return Err(QueryError::NoFields);
+        }
+
+        let dialect = self.dialect;
+
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| match (part, dialect) {
+                (IndexPart::Column(column), _) => Ok(column.clone()),
+                (IndexPart::Expression(_), Dialect::SqlServer) => {
+                    Err(QueryError::Unsupported("expression index", dialect))
+                }
+                (IndexPart::Expression(expression), _) => Ok(format!("({})", expression)),
+            })
+            .collect::<Result<Vec<String>, QueryError>>()?
+            .join(", ");
+
+        let mut sql = match self.unique {
+            true => "CREATE UNIQUE INDEX".to_owned(),
+            false => "CREATE INDEX".to_owned(),
+        };
+
+        if self.concurrently {
+            match dialect {
+                Dialect::Postgres => sql.push_str(" CONCURRENTLY"),
+                _ => return Err(QueryError::Unsupported("CONCURRENTLY", dialect)),
+            }
+        }
+
+        sql.push_str(&format!(" {} ON {}", self.name, self.table));
+
+        // postgres names the method before the columns, mysql after them
+        let method = self.method.map(|m| m.to_sql(dialect)).transpose()?;
+        match (method, dialect) {
+            (Some(method), Dialect::Postgres) => {
+                sql.push_str(&format!(" USING {} ({})", method, parts))
+            }
+            (Some(method), _) => sql.push_str(&format!(" ({}) USING {}", parts, method)),
+            (None, _) => sql.push_str(&format!(" ({})", parts)),
+        }
+
+        if !self.include.is_empty() {
+            match dialect {
+                Dialect::Postgres | Dialect::SqlServer => {
+                    sql.push_str(&format!(" INCLUDE ({})", self.include.join(", ")))
+                }
+                _ => return Err(QueryError::Unsupported("INCLUDE", dialect)),
+            }
+        }
+
+        if let Some(condition) = &self.condition {
+            match dialect {
+                Dialect::MySql => return Err(QueryError::Unsupported("partial index", dialect)),
+                _ => sql.push_str(&format!(" WHERE {}", condition.to_sql(dialect)?)),
+            }
+        }
+
+        Ok(Query::new(sql))
+    }
+}
+
+impl BuildQuery for CreateIndex {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_create_index()
+    }
+}
+
+pub struct DropIndex {
+    name: String,
+    table: Option<String>,
+    if_exists: bool,
+    concurrently: bool,
+    dialect: Dialect,
+}
+
+impl DropIndex {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            table: None,
+            if_exists: false,
+            concurrently: false,
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // mysql and sql server scope index names to their table
+    pub fn on(&mut self, table: &str) -> &mut Self {
+        self.table = Some(table.to_owned());
+        self
+    }
+
+    pub fn if_exists(&mut self) -> &mut Self {
+        self.if_exists = true;
+        self
+    }
+
+    pub fn concurrently(&mut self) -> &mut Self {
+        self.concurrently = true;
+        self
+    }
+
+    pub fn build_drop_index(&self) -> Result<Query, QueryError> {
+        let dialect = self.dialect;
+        let mut sql = "DROP INDEX".to_owned();
+
+        if self.concurrently {
+            match dialect {
+                Dialect::Postgres => sql.push_str(" CONCURRENTLY"),
+                _ => return Err(QueryError::Unsupported("CONCURRENTLY", dialect)),
+            }
+        }
+
+        if self.if_exists {
+            match dialect {
+                Dialect::MySql => {
+                    return Err(QueryError::Unsupported("DROP INDEX IF EXISTS", dialect))
+                }
+                _ => sql.push_str(" IF EXISTS"),
+            }
+        }
+
+        sql.push_str(&format!(" {}", self.name));
+
+        match (&self.table, dialect) {
+            (Some(table), Dialect::MySql | Dialect::SqlServer) => {
+                sql.push_str(&format!(" ON {}", table))
+            }
+            (None, Dialect::MySql | Dialect::SqlServer) => return Err(QueryError::NoTable),
+            _ => {}
+        }
+
+        Ok(Query::new(sql))
+    }
+}
+
+impl BuildQuery for DropIndex {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_drop_index()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::schema::SqlType;
+
+    fn orders() -> CreateTable {
+        let mut create = CreateTable::new("orders");
+        create
+            .column(ColumnDef::new("id", SqlType::BigInt))
+            .column(ColumnDef::new("customer_id", SqlType::BigInt).nullable(true))
+            .column(ColumnDef::new("reference", SqlType::Varchar(32)))
+            .column(ColumnDef::new("total", SqlType::Numeric(10, 2)).default(0))
+            .column(ColumnDef::new("paid", SqlType::Boolean).default(false))
+            .primary_key(&["id"])
+            .unique(&["reference"])
+            .check(Condition::Gte("total".to_owned(), SqlValue::Int(0)))
+            .foreign_key(
+                ForeignKey::new(&["customer_id"], "customers", &["id"])
+                    .on_delete(ForeignKeyAction::SetNull),
+            );
+        create
+    }
+
+    #[test]
+    fn test_create_table() {
+        let mut create = orders();
+        assert_eq!(
+            create.if_not_exists().build().unwrap().sql(),
+            "CREATE TABLE IF NOT EXISTS orders (id BIGINT NOT NULL, customer_id BIGINT, reference VARCHAR(32) NOT NULL, total NUMERIC(10, 2) NOT NULL DEFAULT 0, paid BOOLEAN NOT NULL DEFAULT false, PRIMARY KEY (id), UNIQUE (reference), CHECK (total >= 0), FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE SET NULL)"
+        );
+    }
+
+    #[test]
+    fn test_create_table_per_dialect() {
+        let mut create = orders();
+        assert_eq!(
+            create.dialect(Dialect::SqlServer).build().unwrap().sql(),
+            "CREATE TABLE orders (id BIGINT NOT NULL, customer_id BIGINT, reference NVARCHAR(32) NOT NULL, total NUMERIC(10, 2) NOT NULL DEFAULT 0, paid BIT NOT NULL DEFAULT 0, PRIMARY KEY (id), UNIQUE (reference), CHECK (total >= 0), FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE SET NULL)"
+        );
+        assert_eq!(
+            create.if_not_exists().build(),
+            Err(QueryError::Unsupported(
+                "CREATE TABLE IF NOT EXISTS",
+                Dialect::SqlServer
+            ))
+        );
+
+        let mut create = CreateTable::new("lines");
+        create
+            .dialect(Dialect::MySql)
+            .column(ColumnDef::new("order_id", SqlType::BigInt))
+            .foreign_key(
+                ForeignKey::new(&["order_id"], "orders", &["id"])
+                    .on_delete(ForeignKeyAction::SetDefault),
+            );
+        assert_eq!(
+            create.build(),
+            Err(QueryError::Unsupported(
+                "ON DELETE SET DEFAULT",
+                Dialect::MySql
+            ))
+        );
+    }
+
+    #[test]
+    fn test_literals_per_dialect() {
+        let mut create = CreateTable::new("files");
+        create
+            .column(ColumnDef::new("magic", SqlType::Blob).default(vec![0x89, 0x50]))
+            .column(ColumnDef::new("public", SqlType::Boolean))
+            .check(Condition::Eq("public".to_owned(), SqlValue::Bool(true)));
+        assert_eq!(
+            create.build().unwrap().sql(),
+            "CREATE TABLE files (magic BYTEA NOT NULL DEFAULT '\\x8950', public BOOLEAN NOT NULL, CHECK (public = true))"
+        );
+        assert_eq!(
+            create.dialect(Dialect::SqlServer).build().unwrap().sql(),
+            "CREATE TABLE files (magic VARBINARY(MAX) NOT NULL DEFAULT 0x8950, public BIT NOT NULL, CHECK (public = 1))"
+        );
+
+        let mut index = CreateIndex::new("files_public_idx", "files");
+        index
+            .dialect(Dialect::SqlServer)
+            .columns(&["magic"])
+            .r#where(Condition::Eq("public".to_owned(), SqlValue::Bool(false)));
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE INDEX files_public_idx ON files (magic) WHERE public = 0"
+        );
+    }
+
+    #[test]
+    fn test_create_table_from_definition() {
+        let table = TableDef::new("tags")
+            .column(ColumnDef::new("id", SqlType::Integer))
+            .column(ColumnDef::new("label", SqlType::Text).nullable(true))
+            .primary_key(&["id"]);
+        let mut create = CreateTable::from_table(table);
+        create.dialect(Dialect::Sqlite);
+        assert_eq!(
+            create.build().unwrap().sql(),
+            "CREATE TABLE tags (id INTEGER NOT NULL, label TEXT, PRIMARY KEY (id))"
+        );
+        assert_eq!(CreateTable::new("empty").build(), Err(QueryError::NoFields));
+    }
+
+    #[test]
+    fn test_alter_table() {
+        let mut alter = AlterTable::new("users");
+        alter
+            .add_column(ColumnDef::new("bio", SqlType::Text).nullable(true))
+            .drop_column("legacy")
+            .rename_column("mail", "email");
+        let sql = |alter: &AlterTable| {
+            alter
+                .build_alter()
+                .unwrap()
+                .iter()
+                .map(|q| q.sql().to_owned())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            sql(&alter),
+            vec![
+                "ALTER TABLE users ADD COLUMN bio TEXT",
+                "ALTER TABLE users DROP COLUMN legacy",
+                "ALTER TABLE users RENAME COLUMN mail TO email"
+            ]
+        );
+        alter.dialect(Dialect::SqlServer);
+        assert_eq!(
+            sql(&alter),
+            vec![
+                "ALTER TABLE users ADD bio NVARCHAR(MAX)",
+                "ALTER TABLE users DROP COLUMN legacy",
+                "EXEC sp_rename 'users.mail', 'email', 'COLUMN'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alter_primary_key() {
+        let mut alter = AlterTable::new("app.memberships");
+        alter
+            .drop_primary_key()
+            .add_primary_key(&["user_id", "team_id"]);
+        let sql = |alter: &AlterTable| {
+            alter.build_alter().map(|queries| {
+                queries
+                    .iter()
+                    .map(|q| q.sql().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(
+            sql(&alter),
+            Ok(vec![
+                "ALTER TABLE app.memberships DROP CONSTRAINT memberships_pkey".to_owned(),
+                "ALTER TABLE app.memberships ADD PRIMARY KEY (user_id, team_id)".to_owned(),
+            ])
+        );
+        assert_eq!(
+            sql(alter.dialect(Dialect::MySql)),
+            Ok(vec![
+                "ALTER TABLE app.memberships DROP PRIMARY KEY".to_owned(),
+                "ALTER TABLE app.memberships ADD PRIMARY KEY (user_id, team_id)".to_owned(),
+            ])
+        );
+        assert_eq!(
+            sql(alter.dialect(Dialect::SqlServer)),
+            Err(QueryError::Unsupported(
+                "DROP PRIMARY KEY",
+                Dialect::SqlServer
+            ))
+        );
+        assert_eq!(
+            sql(alter.dialect(Dialect::Sqlite)),
+            Err(QueryError::Unsupported(
+                "ALTER PRIMARY KEY",
+                Dialect::Sqlite
+            ))
+        );
+    }
+
+    #[test]
+    fn test_alter_column() {
+        let column = ColumnDef::new("score", SqlType::BigInt).default(0);
+        let mut alter = AlterTable::new("users");
+        alter.alter_column(column.clone());
+        assert_eq!(
+            alter.build_alter().unwrap()[0].sql(),
+            "ALTER TABLE users ALTER COLUMN score TYPE BIGINT, ALTER COLUMN score SET NOT NULL, ALTER COLUMN score SET DEFAULT 0"
+        );
+        assert_eq!(
+            alter.dialect(Dialect::MySql).build_alter().unwrap()[0].sql(),
+            "ALTER TABLE users MODIFY COLUMN score BIGINT NOT NULL DEFAULT 0"
+        );
+        assert_eq!(
+            alter.dialect(Dialect::SqlServer).build_alter(),
+            Err(QueryError::Unsupported(
+                "ALTER COLUMN DEFAULT",
+                Dialect::SqlServer
+            ))
+        );
+        assert_eq!(
+            alter.dialect(Dialect::Sqlite).build_alter(),
+            Err(QueryError::Unsupported("ALTER COLUMN", Dialect::Sqlite))
+        );
+
+        let mut alter = AlterTable::new("users");
+        alter
+            .dialect(Dialect::SqlServer)
+            .alter_column(ColumnDef::new("bio", SqlType::Varchar(500)).nullable(true));
+        assert_eq!(
+            alter.build_alter().unwrap()[0].sql(),
+            "ALTER TABLE users ALTER COLUMN bio NVARCHAR(500)"
+        );
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let mut drop = DropTable::new("users");
+        assert_eq!(drop.build().unwrap().sql(), "DROP TABLE users");
+        drop.if_exists().cascade();
+        assert_eq!(
+            drop.build().unwrap().sql(),
+            "DROP TABLE IF EXISTS users CASCADE"
+        );
+        drop.dialect(Dialect::Sqlite);
+        assert_eq!(
+            drop.build(),
+            Err(QueryError::Unsupported(
+                "DROP TABLE CASCADE",
+                Dialect::Sqlite
+            ))
+        );
+    }
+
+    #[test]
+    fn test_create_partial_unique_index() {
+        let mut index = CreateIndex::new("users_email_key", "users");
+        index
+            .unique()
+            .expression("lower(email)")
+            .r#where(Condition::Null("deleted_at".to_owned()));
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE UNIQUE INDEX users_email_key ON users ((lower(email))) WHERE deleted_at IS NULL"
+        );
+        assert_eq!(
+            index.dialect(Dialect::Sqlite).build().unwrap().sql(),
+            "CREATE UNIQUE INDEX users_email_key ON users ((lower(email))) WHERE deleted_at IS NULL"
+        );
+        assert_eq!(
+            index.dialect(Dialect::MySql).build(),
+            Err(QueryError::Unsupported("partial index", Dialect::MySql))
+        );
+        assert_eq!(
+            index.dialect(Dialect::SqlServer).build(),
+            Err(QueryError::Unsupported(
+                "expression index",
+                Dialect::SqlServer
+            ))
+        );
+    }
+
+    #[test]
+    fn test_create_index_options() {
+        let mut index = CreateIndex::new("orders_customer_idx", "orders");
+        index
+            .columns(&["customer_id", "created_at"])
+            .include(&["total"])
+            .method(IndexMethod::BTree)
+            .concurrently();
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE INDEX CONCURRENTLY orders_customer_idx ON orders USING btree (customer_id, created_at) INCLUDE (total)"
+        );
+        assert_eq!(
+            index.dialect(Dialect::MySql).build(),
+            Err(QueryError::Unsupported("CONCURRENTLY", Dialect::MySql))
+        );
+
+        let mut index = CreateIndex::new("docs_body_idx", "docs");
+        index.columns(&["body"]).method(IndexMethod::Gin);
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE INDEX docs_body_idx ON docs USING gin (body)"
+        );
+        assert_eq!(
+            index.dialect(Dialect::MySql).build(),
+            Err(QueryError::Unsupported("index method", Dialect::MySql))
+        );
+        assert_eq!(
+            CreateIndex::new("empty_idx", "docs").build(),
+            Err(QueryError::NoFields)
+        );
+    }
+
+    #[test]
+    fn test_create_index_per_dialect() {
+        let mut index = CreateIndex::new("orders_customer_idx", "orders");
+        index
+            .columns(&["customer_id"])
+            .method(IndexMethod::Hash)
+            .dialect(Dialect::MySql);
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE INDEX orders_customer_idx ON orders (customer_id) USING HASH"
+        );
+
+        let mut index = CreateIndex::new("orders_open_idx", "orders");
+        index
+            .columns(&["customer_id"])
+            .include(&["total"])
+            .r#where(Condition::NotNull("shipped_at".to_owned()))
+            .dialect(Dialect::SqlServer);
+        assert_eq!(
+            index.build().unwrap().sql(),
+            "CREATE INDEX orders_open_idx ON orders (customer_id) INCLUDE (total) WHERE shipped_at IS NOT NULL"
+        );
+        assert_eq!(
+            index.dialect(Dialect::Sqlite).build(),
+            Err(QueryError::Unsupported("INCLUDE", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_drop_index() {
+        let mut drop = DropIndex::new("users_email_key");
+        drop.concurrently().if_exists();
+        assert_eq!(
+            drop.build().unwrap().sql(),
+            "DROP INDEX CONCURRENTLY IF EXISTS users_email_key"
+        );
+
+        let mut drop = DropIndex::new("users_email_key");
+        drop.dialect(Dialect::MySql);
+        assert_eq!(drop.build(), Err(QueryError::NoTable));
+        assert_eq!(
+            drop.on("users").build().unwrap().sql(),
+            "DROP INDEX users_email_key ON users"
+        );
+        assert_eq!(
+            drop.if_exists().build(),
+            Err(QueryError::Unsupported(
+                "DROP INDEX IF EXISTS",
+                Dialect::MySql
+            ))
+        );
+        drop.dialect(Dialect::SqlServer);
+        assert_eq!(
+            drop.build().unwrap().sql(),
+            "DROP INDEX IF EXISTS users_email_key ON users"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use super::{
+    condition::Condition,
+    dialect::Dialect,
+    error::QueryError,
+    query::{BuildQuery, Query},
+    returning::Returning,
+    select::Field,
+    with::{CteEntry, With},
+};
+
+pub struct Delete {
+    table: String,
+    condition: Option<Condition>,
+    returning: Vec<(Field, String)>,
+    ctes: Vec<CteEntry>,
+    dialect: Dialect,
+}
+
+impl Delete {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_owned(),
+            condition: None,
+            returning: vec![],
+            ctes: vec![],
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn r#where(&mut self, condition: Condition) -> &mut Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    // the WHERE values are bound as params after the params of the ctes
+    pub fn build_delete(&self) -> Result<Query, QueryError> {
+        let mut params = vec![];
+        let mut sql = match self.build_with(self.dialect, &mut params) {
+            Some(ctes) => format!("{} DELETE FROM {}", ctes, self.table),
+            None => format!("DELETE FROM {}", self.table),
+        };
+
+        let returning = self.build_returning(self.dialect, "DELETED")?;
+
+        if let (Some(output), Dialect::SqlServer) = (&returning, self.dialect) {
+            sql.push_str(&format!(" {}", output));
+        }
+
+        if let Some(condition) = &self.condition {
+            let condition = condition
+                .for_dialect(self.dialect)?
+                .build_parameterized(self.dialect, &mut params);
+            sql.push_str(&format!(" WHERE {}", condition));
+        }
+
+        if let (Some(returning), false) = (&returning, self.dialect == Dialect::SqlServer) {
+            sql.push_str(&format!(" {}", returning));
+        }
+
+        Ok(Query::new(sql)
+            .with_fields(self.returning_fields())
+            .with_params(params))
+    }
+}
+
+impl Returning for Delete {
+    fn get_returning(&self) -> Vec<(&Field, &str)> {
+        self.returning
+            .iter()
+            .map(|(f, a)| (f, a.as_str()))
+            .collect()
+    }
+
+    fn set_returning(&mut self, f: impl FnOnce(&mut Vec<(Field, String)>)) {
+        f(&mut self.returning)
+    }
+}
+
+impl With for Delete {
+    fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>)) {
+        f(&mut self.ctes)
+    }
+
+    fn get_with(&self) -> Vec<&CteEntry> {
+        self.ctes.iter().collect()
+    }
+}
+
+impl BuildQuery for Delete {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_delete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::{select::Agregate, value::SqlValue};
+
+    #[test]
+    fn test_delete_returning() {
+        let mut delete = Delete::new("sessions");
+        delete
+            .r#where(Condition::Lt(
+                "expires_at".to_owned(),
+                SqlValue::StringDate("2024-01-01".to_owned()),
+            ))
+            .returning_all();
+        let query = delete.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "DELETE FROM sessions WHERE expires_at < $1 RETURNING *"
+        );
+        assert!(query.fields().is_empty());
+
+        delete.dialect(Dialect::SqlServer);
+        assert_eq!(
+            delete.build().unwrap().sql(),
+            "DELETE FROM sessions OUTPUT DELETED.* WHERE expires_at < @p1"
+        );
+    }
+
+    #[test]
+    fn test_delete_all_rows() {
+        let mut delete = Delete::new("sessions");
+        delete.dialect(Dialect::MySql);
+        assert_eq!(delete.build().unwrap().sql(), "DELETE FROM sessions");
+    }
+
+    #[test]
+    fn test_delete_with_cte() {
+        let mut delete = Delete::new("sessions");
+        delete
+            .dialect(Dialect::Sqlite)
+            .with(
+                "banned",
+                Query::new("SELECT id FROM users WHERE banned_at > ?").bind("2024-01-01"),
+            )
+            .r#where(
+                Condition::Native("user_id IN (SELECT id FROM banned)".to_owned())
+                    .or(Condition::Lt("expires_at".to_owned(), SqlValue::I64(0))),
+            );
+        let query = delete.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "WITH banned AS (SELECT id FROM users WHERE banned_at > ?) DELETE FROM sessions WHERE (user_id IN (SELECT id FROM banned) OR expires_at < ?)"
+        );
+        assert_eq!(
+            query.params(),
+            &[SqlValue::Text("2024-01-01".to_owned()), SqlValue::I64(0)]
+        );
+    }
+
+    #[test]
+    fn test_delete_condition_per_dialect() {
+        let mut delete = Delete::new("orders");
+        delete.dialect(Dialect::MySql).r#where(
+            Agregate::CountAll
+                .filter(Condition::Eq("paid".to_owned(), SqlValue::Bool(true)))
+                .gt(SqlValue::Int(1)),
+        );
+        assert_eq!(
+            delete.build().unwrap().sql(),
+            "DELETE FROM orders WHERE COUNT(CASE WHEN paid = true THEN 1 END) > ?"
+        );
+    }
+}
@@ -0,0 +1,19 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    #[default]
+    Postgres,
+    MySql,
+    Sqlite,
+    SqlServer,
+}
+
+impl Dialect {
+    // index is 1-based, the position of the value in Query::params
+    pub fn placeholder(&self, index: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${}", index),
+            Dialect::MySql | Dialect::Sqlite => "?".to_owned(),
+            Dialect::SqlServer => format!("@p{}", index),
+        }
+    }
+}
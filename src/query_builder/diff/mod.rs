@@ -0,0 +1,354 @@
+use super::{
+    ddl::{AlterTable, CreateTable, DropTable},
+    dialect::Dialect,
+    error::QueryError,
+    migration::Migration,
+    query::Query,
+    schema::{ColumnDef, TableDef},
+};
+
+// dropped tables and columns keep their definition so the change can be reversed
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaChange {
+    CreateTable(TableDef),
+    DropTable(TableDef),
+    AddColumn(String, ColumnDef), // Takes the table and the column
+    DropColumn(String, ColumnDef),
+    AlterColumn(String, ColumnDef, ColumnDef), // Takes the table, the live and the declared column
+    AlterPrimaryKey(String, Vec<String>, Vec<String>), // Takes the table, the live and the declared key
+}
+
+impl SchemaChange {
+    // loses data or fails on existing rows when applied, review these before running a
+    // generated migration. a NOT NULL column without a default cannot be added to a table
+    // with rows, and a new key fails on duplicates
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            SchemaChange::CreateTable(_) => false,
+            SchemaChange::AddColumn(_, column) => {
+                !column.is_nullable() && column.get_default().is_none()
+            }
+            SchemaChange::DropTable(_)
+            | SchemaChange::DropColumn(_, _)
+            | SchemaChange::AlterPrimaryKey(_, _, _) => true,
+            SchemaChange::AlterColumn(_, live, declared) => {
+                live.get_sql_type() != declared.get_sql_type()
+                    || (live.is_nullable() && !declared.is_nullable())
+            }
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        match self {
+            SchemaChange::CreateTable(table) => SchemaChange::DropTable(table.clone()),
+            SchemaChange::DropTable(table) => SchemaChange::CreateTable(table.clone()),
+            SchemaChange::AddColumn(table, column) => {
+                SchemaChange::DropColumn(table.clone(), column.clone())
+            }
+            SchemaChange::DropColumn(table, column) => {
+                SchemaChange::AddColumn(table.clone(), column.clone())
+            }
+            SchemaChange::AlterColumn(table, live, declared) => {
+                SchemaChange::AlterColumn(table.clone(), declared.clone(), live.clone())
+            }
+            SchemaChange::AlterPrimaryKey(table, live, declared) => {
+                SchemaChange::AlterPrimaryKey(table.clone(), declared.clone(), live.clone())
+            }
+        }
+    }
+
+    pub fn to_sql(&self, dialect: Dialect) -> Result<Vec<Query>, QueryError> {
+        match self {
+            SchemaChange::CreateTable(table) => Ok(vec![CreateTable::from_table(table.clone())
+                .dialect(dialect)
+                .build_create()?]),
+            SchemaChange::DropTable(table) => Ok(vec![DropTable::new(table.get_name())
+                .dialect(dialect)
+                .build_drop()?]),
+            SchemaChange::AddColumn(table, column) => AlterTable::new(table)
+                .dialect(dialect)
+                .add_column(column.clone())
+                .build_alter(),
+            SchemaChange::DropColumn(table, column) => AlterTable::new(table)
+                .dialect(dialect)
+                .drop_column(column.get_name())
+                .build_alter(),
+            SchemaChange::AlterColumn(table, _, declared) => AlterTable::new(table)
+                .dialect(dialect)
+                .alter_column(declared.clone())
+                .build_alter(),
+            SchemaChange::AlterPrimaryKey(table, live, declared) => {
+                let mut alter = AlterTable::new(table);
+                alter.dialect(dialect);
+                if !live.is_empty() {
+                    alter.drop_primary_key();
+                }
+                if !declared.is_empty() {
+                    alter.add_primary_key(&declared.iter().map(String::as_str).collect::<Vec<_>>());
+                }
+                alter.build_alter()
+            }
+        }
+    }
+}
+
+// defaults compare by the literal they render to, a 0 declared on a BIGINT reads back as an i32
+fn same_column(live: &ColumnDef, declared: &ColumnDef) -> bool {
+    let default = |column: &ColumnDef| {
+        column
+            .get_default()
+            .map(|default| default.to_literal(Dialect::Postgres))
+    };
+
+    live.get_sql_type() == declared.get_sql_type()
+        && live.is_nullable() == declared.is_nullable()
+        && default(live) == default(declared)
+}
+
+// what turns the live schema into the declared one: new tables first, then table by table the
+// added and altered columns, the primary key and the dropped columns, then dropped tables.
+// indexes and unique, check and foreign key constraints are not part of a TableDef, they are
+// never compared and are left to migrations written by hand
+pub fn diff_schema(declared: &[TableDef], live: &[TableDef]) -> Vec<SchemaChange> {
+    let find = |tables: &[TableDef], name: &str| -> Option<TableDef> {
+        tables.iter().find(|t| t.get_name() == name).cloned()
+    };
+
+    let mut changes = vec![];
+
+    for table in declared.iter() {
+        if find(live, table.get_name()).is_none() {
+            changes.push(SchemaChange::CreateTable(table.clone()));
+        }
+    }
+
+    for table in declared.iter() {
+        let Some(live_table) = find(live, table.get_name()) else {
+            continue;
+        };
+        let name = table.get_name().to_owned();
+
+        for column in table.get_columns() {
+            match live_table.get_column(column.get_name()) {
+                None => changes.push(SchemaChange::AddColumn(name.clone(), column.clone())),
+                Some(live_column) if !same_column(live_column, column) => changes.push(
+                    SchemaChange::AlterColumn(name.clone(), live_column.clone(), column.clone()),
+                ),
+                Some(_) => {}
+            }
+        }
+
+        if table.get_primary_key() != live_table.get_primary_key() {
+            let key = |table: &TableDef| {
+                table
+                    .get_primary_key()
+                    .iter()
+                    .map(|&c| c.to_owned())
+                    .collect()
+            };
+            changes.push(SchemaChange::AlterPrimaryKey(
+                name.clone(),
+                key(&live_table),
+                key(table),
+            ));
+        }
+
+        for live_column in live_table.get_columns() {
+            if table.get_column(live_column.get_name()).is_none() {
+                changes.push(SchemaChange::DropColumn(name.clone(), live_column.clone()));
+            }
+        }
+    }
+
+    for table in live.iter() {
+        if find(declared, table.get_name()).is_none() {
+            changes.push(SchemaChange::DropTable(table.clone()));
+        }
+    }
+
+    changes
+}
+
+// a draft to review, down undoes the changes in reverse order
+pub fn draft_migration(
+    version: i64,
+    name: &str,
+    changes: &[SchemaChange],
+    dialect: Dialect,
+) -> Result<Migration, QueryError> {
+    let mut migration = Migration::new(version, name);
+
+    for change in changes.iter() {
+        for query in change.to_sql(dialect)? {
+            migration = migration.up(query);
+        }
+    }
+
+    for change in changes.iter().rev() {
+        for query in change.inverse().to_sql(dialect)? {
+            migration = migration.down(query);
+        }
+    }
+
+    Ok(migration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::schema::SqlType;
+
+    fn declared() -> Vec<TableDef> {
+        vec![
+            TableDef::new("users")
+                .column(ColumnDef::new("id", SqlType::BigInt))
+                .column(ColumnDef::new("email", SqlType::Varchar(255)))
+                .column(ColumnDef::new("bio", SqlType::Text).nullable(true))
+                .primary_key(&["id"]),
+            TableDef::new("tags").column(ColumnDef::new("label", SqlType::Text)),
+        ]
+    }
+
+    fn live() -> Vec<TableDef> {
+        vec![
+            TableDef::new("users")
+                .column(ColumnDef::new("id", SqlType::BigInt))
+                .column(ColumnDef::new("email", SqlType::Varchar(100)).nullable(true))
+                .column(ColumnDef::new("legacy", SqlType::Integer).nullable(true))
+                .primary_key(&["id"]),
+            TableDef::new("sessions").column(ColumnDef::new("token", SqlType::Text)),
+        ]
+    }
+
+    #[test]
+    fn test_diff_schema() {
+        let changes = diff_schema(&declared(), &live());
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| (c.to_sql(Dialect::Postgres).unwrap(), c.is_destructive()))
+                .map(|(q, destructive)| (q[0].sql().to_owned(), destructive))
+                .collect::<Vec<(String, bool)>>(),
+            vec![
+                ("CREATE TABLE tags (label TEXT NOT NULL)".to_owned(), false),
+                ("ALTER TABLE users ALTER COLUMN email TYPE VARCHAR(255), ALTER COLUMN email SET NOT NULL, ALTER COLUMN email DROP DEFAULT".to_owned(), true),
+                ("ALTER TABLE users ADD COLUMN bio TEXT".to_owned(), false),
+                ("ALTER TABLE users DROP COLUMN legacy".to_owned(), true),
+                ("DROP TABLE sessions".to_owned(), true),
+            ]
+        );
+        assert!(diff_schema(&declared(), &declared()).is_empty());
+    }
+
+    #[test]
+    fn test_destructive_changes() {
+        let declared = vec![TableDef::new("memberships")
+            .column(ColumnDef::new("user_id", SqlType::BigInt))
+            .column(ColumnDef::new("team_id", SqlType::BigInt))
+            .column(ColumnDef::new("role", SqlType::Text).default("member"))
+            .primary_key(&["user_id", "team_id"])];
+        let live = vec![TableDef::new("memberships")
+            .column(ColumnDef::new("user_id", SqlType::BigInt))
+            .primary_key(&["user_id"])];
+        let changes = diff_schema(&declared, &live);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| (c.to_sql(Dialect::Postgres).unwrap(), c.is_destructive()))
+                .map(|(q, destructive)| {
+                    let sql = q.iter().map(|q| q.sql()).collect::<Vec<_>>().join("; ");
+                    (sql, destructive)
+                })
+                .collect::<Vec<(String, bool)>>(),
+            vec![
+                // fails on a table with rows
+                ("ALTER TABLE memberships ADD COLUMN team_id BIGINT NOT NULL".to_owned(), true),
+                ("ALTER TABLE memberships ADD COLUMN role TEXT NOT NULL DEFAULT 'member'".to_owned(), false),
+                ("ALTER TABLE memberships DROP CONSTRAINT memberships_pkey; ALTER TABLE memberships ADD PRIMARY KEY (user_id, team_id)".to_owned(), true),
+            ]
+        );
+        assert_eq!(
+            draft_migration(1, "sync", &changes, Dialect::Sqlite).map(|m| m.version()),
+            Err(QueryError::Unsupported(
+                "ALTER PRIMARY KEY",
+                Dialect::Sqlite
+            ))
+        );
+    }
+
+    #[test]
+    fn test_diff_is_reversible() {
+        let declared = vec![TableDef::new("users")
+            .column(ColumnDef::new("id", SqlType::BigInt))
+            .column(ColumnDef::new("bio", SqlType::Text).nullable(true))
+            .primary_key(&["id"])];
+        let live = vec![
+            TableDef::new("users")
+                .column(ColumnDef::new("id", SqlType::BigInt))
+                .column(ColumnDef::new("legacy", SqlType::Integer)),
+            TableDef::new("sessions").column(ColumnDef::new("token", SqlType::Text)),
+        ];
+        let changes = diff_schema(&declared, &live);
+        assert_eq!(
+            diff_schema(&live, &declared),
+            changes
+                .iter()
+                .rev()
+                .map(|c| c.inverse())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_apply_draft_migration() {
+        use std::sync::Mutex;
+
+        use crate::query_builder::{
+            executor::{Connection, Executor},
+            migration::Migrator,
+        };
+
+        let connection = Connection::new(
+            Mutex::new(rusqlite::Connection::open_in_memory().unwrap()),
+            Dialect::Sqlite,
+        );
+        for table in live() {
+            let create = CreateTable::from_table(table)
+                .dialect(Dialect::Sqlite)
+                .build_create()
+                .unwrap();
+            connection.execute(&create).await.unwrap();
+        }
+
+        // sqlite cannot alter the email column, keep it out of this draft
+        let changes = diff_schema(&declared(), &live())
+            .into_iter()
+            .filter(|c| !matches!(c, SchemaChange::AlterColumn(_, _, _)))
+            .collect::<Vec<SchemaChange>>();
+        let mut migrator = Migrator::new();
+        migrator.migration(draft_migration(1, "sync users", &changes, Dialect::Sqlite).unwrap());
+
+        migrator.apply(&connection).await.unwrap();
+        let insert = Query::new("INSERT INTO users (id, email, bio) VALUES (1, 'ann', 'hi')");
+        connection.execute(&insert).await.unwrap();
+        let session = Query::new("INSERT INTO sessions (token) VALUES ('abc')");
+        assert!(connection.execute(&session).await.is_err());
+
+        migrator.rollback(&connection).await.unwrap();
+        connection.execute(&session).await.unwrap();
+        assert!(connection
+            .execute(&Query::new("INSERT INTO tags (label) VALUES ('new')"))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_draft_migration_unsupported_change() {
+        let changes = diff_schema(&declared(), &live());
+        assert_eq!(
+            draft_migration(1, "sync", &changes, Dialect::Sqlite).map(|m| m.version()),
+            Err(QueryError::Unsupported("ALTER COLUMN", Dialect::Sqlite))
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use super::dialect::Dialect;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryError {
+    NoFields,
+    NoTable,
+    ColumnCountMismatch(usize, usize), // Takes expected and found column counts
+    UngroupedColumn(String),
+    UnknownSortField(String),
+    Unsupported(&'static str, Dialect), // Takes the feature and the dialect lacking it
+    MissingColumn(String),
+    InvalidType(String, &'static str), // Takes the column and the rust type it was read into
+    RowNotFound,
+    Driver(String), // Error reported by the database driver
+    DuplicateMigration(i64),
+    UnknownMigration(i64), // Takes an applied version missing from the migration list
+    ChecksumMismatch(i64), // Takes an applied version whose statements changed since
+    IrreversibleMigration(i64),
+    UnknownSqlType(String, String), // Takes the column and the type the database reported
+    Syntax(String, usize, usize),   // Takes the message, the line and the column, from 1
+    InvalidFrame(&'static str),     // Takes what is wrong with the window frame
+    AbandonedTransaction,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::NoFields => write!(f, "no fields selected"),
+            QueryError::NoTable => write!(f, "no table to select from"),
+            QueryError::ColumnCountMismatch(expected, found) => write!(
+                f,
+                "column count mismatch: expected {} columns, found {}",
+                expected, found
+            ),
+            QueryError::UngroupedColumn(column) => write!(
+                f,
+                "column {} must appear in GROUP BY or be used in an aggregate",
+                column
+            ),
+            QueryError::UnknownSortField(field) => write!(f, "cannot sort by {}", field),
+            QueryError::Unsupported(feature, dialect) => {
+                write!(f, "{} is not supported on {:?}", feature, dialect)
+            }
+            QueryError::MissingColumn(column) => write!(f, "no column {} in row", column),
+            QueryError::InvalidType(column, rust_type) => {
+                write!(f, "column {} cannot be read as {}", column, rust_type)
+            }
+            QueryError::RowNotFound => write!(f, "query returned no rows"),
+            QueryError::Driver(error) => write!(f, "driver error: {}", error),
+            QueryError::DuplicateMigration(version) => {
+                write!(f, "migration {} is declared more than once", version)
+            }
+            QueryError::UnknownMigration(version) => {
+                write!(
+                    f,
+                    "applied migration {} is not in the migration list",
+                    version
+                )
+            }
+            QueryError::ChecksumMismatch(version) => {
+                write!(f, "migration {} changed after it was applied", version)
+            }
+            QueryError::IrreversibleMigration(version) => {
+                write!(f, "migration {} has no down statements", version)
+            }
+            QueryError::UnknownSqlType(column, sql_type) => {
+                write!(f, "column {} has an unknown type {}", column, sql_type)
+            }
+            QueryError::Syntax(message, line, column) => write!(
+                f,
+                "syntax error at line {}, column {}: {}",
+                line, column, message
+            ),
+            QueryError::InvalidFrame(reason) => write!(f, "invalid window frame: {}", reason),
+            QueryError::AbandonedTransaction => write!(
+                f,
+                "a transaction was abandoned on this connection, recover or discard it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
@@ -0,0 +1,1301 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use super::{
+    dialect::Dialect,
+    error::QueryError,
+    query::Query,
+    row::FromRow,
+    transaction::{Begin, Transaction},
+    value::SqlValue,
+};
+
+// runs the Query returned by BuildQuery::build, binding Query::params in order
+pub trait Executor: Sync {
+    fn execute(&self, query: &Query) -> impl Future<Output = Result<u64, QueryError>> + Send;
+
+    fn fetch_all<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Vec<T>, QueryError>> + Send;
+
+    fn fetch_optional<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Option<T>, QueryError>> + Send;
+
+    fn fetch_one<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<T, QueryError>> + Send {
+        async move {
+            self.fetch_optional(query)
+                .await?
+                .ok_or(QueryError::RowNotFound)
+        }
+    }
+}
+
+impl<E: Executor + Send> Executor for Arc<E> {
+    fn execute(&self, query: &Query) -> impl Future<Output = Result<u64, QueryError>> + Send {
+        (**self).execute(query)
+    }
+
+    fn fetch_all<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Vec<T>, QueryError>> + Send {
+        (**self).fetch_all(query)
+    }
+
+    fn fetch_optional<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Option<T>, QueryError>> + Send {
+        (**self).fetch_optional(query)
+    }
+}
+
+// an executor bound to one database connection, what transactions and migrations need since
+// a pool may run each statement on a different connection
+pub trait SingleConnection: Executor {}
+
+impl<E: SingleConnection + Send> SingleConnection for Arc<E> {}
+
+// a single connection that tracks how deeply transactions are nested on it, and is poisoned
+// when a transaction future is dropped unfinished: a pool should discard a poisoned connection,
+// closing it rolls the transaction back, or recover rolls it back in place
+pub struct Connection<E> {
+    executor: E,
+    dialect: Dialect,
+    depth: AtomicUsize,
+    poisoned: AtomicBool,
+}
+
+impl<E: SingleConnection> Connection<E> {
+    pub fn new(executor: E, dialect: Dialect) -> Self {
+        Self {
+            executor,
+            dialect,
+            depth: AtomicUsize::new(0),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn into_inner(self) -> E {
+        self.executor
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Acquire)
+    }
+
+    // rolls back the transaction left open by a dropped future, the connection stays poisoned
+    // if the rollback fails
+    pub async fn recover(&self) -> Result<(), QueryError> {
+        if self.is_poisoned() {
+            let rollback = Query::new(Transaction::Rollback.to_sql(self.dialect)?);
+            self.executor.execute(&rollback).await?;
+            self.depth.store(0, Ordering::Release);
+            self.poisoned.store(false, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), QueryError> {
+        match self.is_poisoned() {
+            true => Err(QueryError::AbandonedTransaction),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<E: SingleConnection> Executor for Connection<E> {
+    async fn execute(&self, query: &Query) -> Result<u64, QueryError> {
+        self.check()?;
+        self.executor.execute(query).await
+    }
+
+    async fn fetch_all<T: FromRow + Send>(&self, query: &Query) -> Result<Vec<T>, QueryError> {
+        self.check()?;
+        self.executor.fetch_all(query).await
+    }
+
+    async fn fetch_optional<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> Result<Option<T>, QueryError> {
+        self.check()?;
+        self.executor.fetch_optional(query).await
+    }
+}
+
+impl<E: SingleConnection> SingleConnection for Connection<E> {}
+
+// one level of transaction nesting, poisons the connection when dropped before it finished
+struct Nesting<'a> {
+    depth: &'a AtomicUsize,
+    poisoned: &'a AtomicBool,
+    finished: bool,
+}
+
+impl Drop for Nesting<'_> {
+    fn drop(&mut self) {
+        // recover may have reset the depth already
+        let _ = self
+            .depth
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |d| d.checked_sub(1));
+        if !self.finished {
+            self.poisoned.store(true, Ordering::Release);
+        }
+    }
+}
+
+// runs f between BEGIN and COMMIT, rolling back when it fails. called inside another
+// transaction on the same connection it runs between SAVEPOINT and RELEASE instead, rolling
+// back to the savepoint, which cannot take an isolation level or access mode
+pub async fn transaction<E, T>(
+    connection: &Connection<E>,
+    begin: Begin,
+    f: impl AsyncFnOnce(&Connection<E>) -> Result<T, QueryError>,
+) -> Result<T, QueryError>
+where
+    E: SingleConnection,
+{
+    let dialect = connection.dialect;
+    let depth = connection.depth.load(Ordering::Acquire);
+    let savepoint = format!("qrex_savepoint_{}", depth);
+    let (start, commit, rollback) = match depth {
+        0 => (
+            begin.to_sql(dialect)?,
+            Some(Transaction::Commit),
+            Transaction::Rollback,
+        ),
+        _ if begin != Begin::default() => {
+            return Err(QueryError::Unsupported(
+                "isolation or access mode on a nested transaction",
+                dialect,
+            ))
+        }
+        // sql server has no release, its savepoints end with the outer transaction
+        _ => (
+            vec![Transaction::Savepoint(savepoint.clone()).to_sql(dialect)?],
+            (dialect != Dialect::SqlServer).then(|| Transaction::Release(savepoint.clone())),
+            Transaction::RollbackTo(savepoint),
+        ),
+    };
+    let commit = commit.map(|c| c.to_sql(dialect)).transpose()?;
+    let rollback = Query::new(rollback.to_sql(dialect)?);
+
+    connection.depth.fetch_add(1, Ordering::AcqRel);
+    let mut nesting = Nesting {
+        depth: &connection.depth,
+        poisoned: &connection.poisoned,
+        finished: false,
+    };
+    for statement in start {
+        if let Err(error) = connection.execute(&Query::new(statement)).await {
+            nesting.finished = true;
+            return Err(error);
+        }
+    }
+
+    let result = f(connection).await;
+    let ended = match (&result, commit) {
+        (Ok(_), Some(commit)) => connection.execute(&Query::new(commit)).await,
+        (Ok(_), None) => Ok(0),
+        // keep the original error even if the rollback fails too
+        (Err(_), _) => {
+            let _ = connection.execute(&rollback).await;
+            Ok(0)
+        }
+    };
+    nesting.finished = true;
+
+    ended.and(result)
+}
+
+#[cfg(feature = "tokio-postgres")]
+mod tokio_postgres_executor {
+    use std::error::Error;
+
+    use rust_decimal::Decimal;
+    use tokio_postgres::{
+        types::{to_sql_checked, IsNull, ToSql, Type, WrongType},
+        Client,
+    };
+
+    use super::*;
+
+    // the message alone says which parameter failed but not why
+    impl From<tokio_postgres::Error> for QueryError {
+        fn from(error: tokio_postgres::Error) -> Self {
+            match error.source() {
+                Some(source) => QueryError::Driver(format!("{}: {}", error, source)),
+                None => QueryError::Driver(error.to_string()),
+            }
+        }
+    }
+
+    fn checked<T: ToSql>(
+        value: &T,
+        ty: &Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        match T::accepts(ty) {
+            true => value.to_sql(ty, out),
+            false => Err(Box::new(WrongType::new::<T>(ty.clone()))),
+        }
+    }
+
+    // accepts cannot see the variant, to_sql checks it against the parameter type instead.
+    // integers fit any integer parameter they are in range of, floats any float parameter
+    impl ToSql for SqlValue {
+        fn to_sql(
+            &self,
+            ty: &Type,
+            out: &mut bytes::BytesMut,
+        ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+            let integer: i128 = match self {
+                SqlValue::Null => return Ok(IsNull::Yes),
+                SqlValue::Bool(val) => return checked(val, ty, out),
+                SqlValue::Text(val) | SqlValue::StringDate(val) => return checked(val, ty, out),
+                SqlValue::Bytes(val) => return checked(val, ty, out),
+                SqlValue::Numeric(val) if String::accepts(ty) => return checked(val, ty, out),
+                SqlValue::Numeric(val) => return checked(&val.parse::<Decimal>()?, ty, out),
+                SqlValue::F64(val) if *ty == Type::FLOAT4 => return (*val as f32).to_sql(ty, out),
+                SqlValue::F64(val) => return checked(val, ty, out),
+                SqlValue::F32(val) | SqlValue::Float(val) if *ty == Type::FLOAT8 => {
+                    return (*val as f64).to_sql(ty, out)
+                }
+                SqlValue::F32(val) | SqlValue::Float(val) => return checked(val, ty, out),
+                SqlValue::I128(val) => *val,
+                SqlValue::I64(val) => (*val).into(),
+                SqlValue::I32(val) | SqlValue::Int(val) => (*val).into(),
+                SqlValue::U128(val) => i128::try_from(*val)?,
+                SqlValue::U64(val) => (*val).into(),
+                SqlValue::U32(val) => (*val).into(),
+            };
+
+            match *ty {
+                Type::INT2 => i16::try_from(integer)?.to_sql(ty, out),
+                Type::INT4 => i32::try_from(integer)?.to_sql(ty, out),
+                Type::INT8 => i64::try_from(integer)?.to_sql(ty, out),
+                _ => Err(Box::new(WrongType::new::<i64>(ty.clone()))),
+            }
+        }
+
+        fn accepts(_: &Type) -> bool {
+            true
+        }
+
+        to_sql_checked!();
+    }
+
+    fn params(query: &Query) -> Vec<&(dyn ToSql + Sync)> {
+        query
+            .params()
+            .iter()
+            .map(|p| p as &(dyn ToSql + Sync))
+            .collect()
+    }
+
+    impl Executor for Client {
+        async fn execute(&self, query: &Query) -> Result<u64, QueryError> {
+            Ok(Client::execute(self, query.sql(), &params(query)).await?)
+        }
+
+        async fn fetch_all<T: FromRow + Send>(&self, query: &Query) -> Result<Vec<T>, QueryError> {
+            Client::query(self, query.sql(), &params(query))
+                .await?
+                .iter()
+                .map(T::from_row)
+                .collect()
+        }
+
+        async fn fetch_optional<T: FromRow + Send>(
+            &self,
+            query: &Query,
+        ) -> Result<Option<T>, QueryError> {
+            Client::query(self, query.sql(), &params(query))
+                .await?
+                .first()
+                .map(T::from_row)
+                .transpose()
+        }
+    }
+
+    impl SingleConnection for Client {}
+}
+
+#[cfg(feature = "sqlx")]
+mod sqlx_executor {
+    use sqlx::{
+        encode::IsNull,
+        error::BoxDynError,
+        postgres::{types::Oid, PgArgumentBuffer, PgArguments, PgConnection, PgPool, PgTypeInfo},
+        types::Decimal,
+        Postgres,
+    };
+
+    use super::*;
+
+    impl From<sqlx::Error> for QueryError {
+        fn from(error: sqlx::Error) -> Self {
+            QueryError::Driver(error.to_string())
+        }
+    }
+
+    type SqlxQuery<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+    // a NULL with no declared type, postgres gives it the type of where it is used
+    struct Null;
+
+    impl sqlx::Type<Postgres> for Null {
+        fn type_info() -> PgTypeInfo {
+            PgTypeInfo::with_oid(Oid(0))
+        }
+    }
+
+    impl sqlx::Encode<'_, Postgres> for Null {
+        fn encode_by_ref(&self, _: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+            Ok(IsNull::Yes)
+        }
+    }
+
+    fn bind(query: &Query) -> Result<SqlxQuery<'_>, QueryError> {
+        let mut bound = sqlx::query(query.sql());
+
+        for param in query.params().iter() {
+            let out_of_range = |_| QueryError::Driver(format!("{:?} is out of range", param));
+            bound = match param {
+                SqlValue::Null => bound.bind(Null),
+                SqlValue::Bool(val) => bound.bind(*val),
+                SqlValue::Text(val) | SqlValue::StringDate(val) => bound.bind(val.clone()),
+                SqlValue::Bytes(val) => bound.bind(val.clone()),
+                SqlValue::Numeric(val) => bound.bind(
+                    val.parse::<Decimal>()
+                        .map_err(|e| QueryError::Driver(format!("{}: {}", val, e)))?,
+                ),
+                SqlValue::F64(val) => bound.bind(*val),
+                SqlValue::F32(val) | SqlValue::Float(val) => bound.bind(*val),
+                SqlValue::I32(val) | SqlValue::Int(val) => bound.bind(*val),
+                SqlValue::I64(val) => bound.bind(*val),
+                SqlValue::U32(val) => bound.bind(i64::from(*val)),
+                SqlValue::U64(val) => bound.bind(i64::try_from(*val).map_err(out_of_range)?),
+                SqlValue::I128(val) => bound.bind(i64::try_from(*val).map_err(out_of_range)?),
+                SqlValue::U128(val) => bound.bind(i64::try_from(*val).map_err(out_of_range)?),
+            };
+        }
+
+        Ok(bound)
+    }
+
+    impl Executor for tokio::sync::Mutex<PgConnection> {
+        async fn execute(&self, query: &Query) -> Result<u64, QueryError> {
+            let mut connection = self.lock().await;
+            Ok(bind(query)?
+                .execute(&mut *connection)
+                .await?
+                .rows_affected())
+        }
+
+        async fn fetch_all<T: FromRow + Send>(&self, query: &Query) -> Result<Vec<T>, QueryError> {
+            let mut connection = self.lock().await;
+            bind(query)?
+                .fetch_all(&mut *connection)
+                .await?
+                .iter()
+                .map(T::from_row)
+                .collect()
+        }
+
+        async fn fetch_optional<T: FromRow + Send>(
+            &self,
+            query: &Query,
+        ) -> Result<Option<T>, QueryError> {
+            let mut connection = self.lock().await;
+            bind(query)?
+                .fetch_optional(&mut *connection)
+                .await?
+                .as_ref()
+                .map(T::from_row)
+                .transpose()
+        }
+    }
+
+    impl SingleConnection for tokio::sync::Mutex<PgConnection> {}
+
+    impl Executor for PgPool {
+        async fn execute(&self, query: &Query) -> Result<u64, QueryError> {
+            Ok(bind(query)?.execute(self).await?.rows_affected())
+        }
+
+        async fn fetch_all<T: FromRow + Send>(&self, query: &Query) -> Result<Vec<T>, QueryError> {
+            bind(query)?
+                .fetch_all(self)
+                .await?
+                .iter()
+                .map(T::from_row)
+                .collect()
+        }
+
+        async fn fetch_optional<T: FromRow + Send>(
+            &self,
+            query: &Query,
+        ) -> Result<Option<T>, QueryError> {
+            bind(query)?
+                .fetch_optional(self)
+                .await?
+                .as_ref()
+                .map(T::from_row)
+                .transpose()
+        }
+    }
+}
+
+// the ignored tests need a running server, run them with QREX_POSTGRES_URL set and
+// cargo test -- --ignored, the others talk to the stand_in below
+#[cfg(all(test, any(feature = "tokio-postgres", feature = "sqlx")))]
+mod tests {
+    use super::*;
+    use crate::query_builder::{
+        row::{FromSql, Row},
+        transaction::IsolationLevel,
+    };
+
+    // a stand-in postgres server speaking just enough of the protocol for the drivers.
+    // statements in the script take its parameter and column types and send its rows,
+    // anything else takes no parameters and sends nothing back
+    mod stand_in {
+        use std::{
+            collections::HashMap,
+            sync::{Arc, Mutex},
+        };
+
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::{TcpListener, TcpStream},
+        };
+
+        pub type Value = Option<Vec<u8>>;
+
+        #[derive(Clone, Default)]
+        pub struct Reply {
+            pub params: Vec<u32>,
+            pub columns: Vec<(&'static str, u32)>,
+            // None sends the bound parameters back as the only row
+            pub rows: Option<Vec<Vec<Value>>>,
+            pub tag: &'static str,
+        }
+
+        pub struct Server {
+            pub url: String,
+            // every statement executed, in order
+            pub log: Arc<Mutex<Vec<String>>>,
+        }
+
+        pub async fn start(script: Vec<(&'static str, Reply)>) -> Server {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!(
+                "postgres://qrex@{}/qrex?sslmode=disable",
+                listener.local_addr().unwrap()
+            );
+            let script = Arc::new(script.into_iter().collect::<HashMap<_, _>>());
+            let log = Arc::new(Mutex::new(vec![]));
+
+            let server_log = log.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, script.clone(), server_log.clone()));
+                }
+            });
+            Server { url, log }
+        }
+
+        struct Reader<'a>(&'a [u8]);
+
+        impl<'a> Reader<'a> {
+            fn take(&mut self, n: usize) -> &'a [u8] {
+                let (head, rest) = self.0.split_at(n);
+                self.0 = rest;
+                head
+            }
+
+            fn string(&mut self) -> String {
+                let end = self.0.iter().position(|&b| b == 0).unwrap();
+                let text = String::from_utf8(self.take(end).to_vec()).unwrap();
+                self.take(1);
+                text
+            }
+
+            fn i16(&mut self) -> i16 {
+                i16::from_be_bytes(self.take(2).try_into().unwrap())
+            }
+
+            fn i32(&mut self) -> i32 {
+                i32::from_be_bytes(self.take(4).try_into().unwrap())
+            }
+        }
+
+        fn message(out: &mut Vec<u8>, tag: u8, body: &[u8]) {
+            out.push(tag);
+            out.extend((body.len() as i32 + 4).to_be_bytes());
+            out.extend(body);
+        }
+
+        fn row_description(out: &mut Vec<u8>, columns: &[(&str, u32)]) {
+            if columns.is_empty() {
+                return message(out, b'n', &[]);
+            }
+            let mut body = (columns.len() as i16).to_be_bytes().to_vec();
+            for (name, oid) in columns {
+                body.extend(name.as_bytes());
+                body.push(0);
+                body.extend(0i32.to_be_bytes());
+                body.extend(0i16.to_be_bytes());
+                body.extend(oid.to_be_bytes());
+                body.extend((-1i16).to_be_bytes());
+                body.extend((-1i32).to_be_bytes());
+                body.extend(0i16.to_be_bytes());
+            }
+            message(out, b'T', &body);
+        }
+
+        async fn read(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+            let len = stream.read_i32().await?;
+            let mut body = vec![0; len as usize - 4];
+            stream.read_exact(&mut body).await?;
+            Ok(body)
+        }
+
+        async fn serve(
+            mut stream: TcpStream,
+            script: Arc<HashMap<&'static str, Reply>>,
+            log: Arc<Mutex<Vec<String>>>,
+        ) -> std::io::Result<()> {
+            stream.set_nodelay(true)?;
+            // no to ssl, then any startup message is let in
+            while read(&mut stream).await?[..4] == 80_877_103i32.to_be_bytes() {
+                stream.write_all(b"N").await?;
+            }
+            let mut out = vec![];
+            message(&mut out, b'R', &0i32.to_be_bytes());
+            message(&mut out, b'K', &[0; 8]);
+            message(&mut out, b'Z', b"I");
+            stream.write_all(&out).await?;
+
+            let reply = |sql: &str| script.get(sql).cloned().unwrap_or_default();
+            // statement name to its sql and the parameter types the client gave
+            let mut statements = HashMap::<String, (String, Vec<u32>)>::new();
+            // portal name to its sql and bound values
+            let mut portals = HashMap::<String, (String, Vec<Value>)>::new();
+
+            loop {
+                let tag = stream.read_u8().await?;
+                let body = read(&mut stream).await?;
+                let mut reader = Reader(&body);
+                let mut out = vec![];
+
+                match tag {
+                    b'P' => {
+                        let name = reader.string();
+                        let sql = reader.string();
+                        let oids = (0..reader.i16()).map(|_| reader.i32() as u32).collect();
+                        statements.insert(name, (sql, oids));
+                        message(&mut out, b'1', &[]);
+                    }
+                    b'D' => {
+                        let kind = reader.take(1)[0];
+                        let name = reader.string();
+                        let sql = match kind {
+                            b'S' => &statements[&name].0,
+                            _ => &portals[&name].0,
+                        };
+                        let reply = reply(sql);
+                        if kind == b'S' {
+                            // like postgres, a type the client gave wins over the inferred one
+                            let given = &statements[&name].1;
+                            let mut body = (reply.params.len() as i16).to_be_bytes().to_vec();
+                            for (i, oid) in reply.params.iter().enumerate() {
+                                let oid = given.get(i).filter(|&&o| o != 0).unwrap_or(oid);
+                                body.extend(oid.to_be_bytes());
+                            }
+                            message(&mut out, b't', &body);
+                        }
+                        row_description(&mut out, &reply.columns);
+                    }
+                    b'B' => {
+                        let portal = reader.string();
+                        let statement = reader.string();
+                        for _ in 0..reader.i16() {
+                            reader.i16();
+                        }
+                        let values = (0..reader.i16())
+                            .map(|_| match reader.i32() {
+                                -1 => None,
+                                len => Some(reader.take(len as usize).to_vec()),
+                            })
+                            .collect();
+                        portals.insert(portal, (statements[&statement].0.clone(), values));
+                        message(&mut out, b'2', &[]);
+                    }
+                    b'E' => {
+                        let (sql, values) = &portals[&reader.string()];
+                        log.lock().unwrap().push(sql.clone());
+                        let reply = reply(sql);
+                        for row in reply.rows.unwrap_or_else(|| vec![values.clone()]) {
+                            let mut body = (row.len() as i16).to_be_bytes().to_vec();
+                            for value in row {
+                                match value {
+                                    Some(value) => {
+                                        body.extend((value.len() as i32).to_be_bytes());
+                                        body.extend(value);
+                                    }
+                                    None => body.extend((-1i32).to_be_bytes()),
+                                }
+                            }
+                            message(&mut out, b'D', &body);
+                        }
+                        message(&mut out, b'C', format!("{}\0", reply.tag).as_bytes());
+                    }
+                    b'Q' => {
+                        let sql = reader.string();
+                        log.lock().unwrap().push(sql.clone());
+                        message(&mut out, b'C', format!("{}\0", reply(&sql).tag).as_bytes());
+                        message(&mut out, b'Z', b"I");
+                    }
+                    b'C' => message(&mut out, b'3', &[]),
+                    b'S' => message(&mut out, b'Z', b"I"),
+                    b'H' => {}
+                    b'X' => return Ok(()),
+                    tag => panic!("the stand-in does not take {:?} messages", tag as char),
+                }
+                stream.write_all(&out).await?;
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: i32,
+        name: String,
+        score: Option<f64>,
+        avatar: Vec<u8>,
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                score: row.try_get("score")?,
+                avatar: row.try_get("avatar")?,
+            })
+        }
+    }
+
+    fn url() -> String {
+        std::env::var("QREX_POSTGRES_URL").expect("QREX_POSTGRES_URL is not set")
+    }
+
+    async fn round_trip(executor: &impl Executor, table: &str) {
+        executor
+            .execute(&Query::new(format!(
+                "CREATE TEMPORARY TABLE {} (id INT4 PRIMARY KEY, name TEXT NOT NULL, score FLOAT8, avatar BYTEA NOT NULL)",
+                table
+            )))
+            .await
+            .unwrap();
+
+        let insert = Query::new(format!(
+            "INSERT INTO {} (id, name, score, avatar) VALUES ($1, $2, $3, $4)",
+            table
+        ))
+        .bind(1i64)
+        .bind("O'Brien")
+        .bind(2.5)
+        .bind(vec![0u8, 255]);
+        assert_eq!(executor.execute(&insert).await, Ok(1));
+
+        // a statement of its own, so the NULL is not typed by an earlier prepare
+        let nulls = Query::new(format!(
+            "INSERT INTO {} (score, id, name, avatar) VALUES ($1, $2, $3, $4)",
+            table
+        ))
+        .bind(SqlValue::Null)
+        .bind(3)
+        .bind("nobody")
+        .bind(Vec::<u8>::new());
+        assert_eq!(executor.execute(&nulls).await, Ok(1));
+
+        let select = Query::new(format!(
+            "SELECT id, name, score, avatar FROM {} WHERE id = $1",
+            table
+        ));
+        assert_eq!(
+            executor.fetch_one::<User>(&select.clone().bind(1)).await,
+            Ok(User {
+                id: 1,
+                name: "O'Brien".to_owned(),
+                score: Some(2.5),
+                avatar: vec![0, 255],
+            })
+        );
+        assert_eq!(
+            executor
+                .fetch_one::<User>(&select.clone().bind(3))
+                .await
+                .map(|u| u.score),
+            Ok(None)
+        );
+        assert_eq!(
+            executor
+                .fetch_optional::<User>(&select.clone().bind(2))
+                .await,
+            Ok(None)
+        );
+        assert_eq!(
+            executor.fetch_one::<User>(&select.bind(2)).await,
+            Err(QueryError::RowNotFound)
+        );
+    }
+
+    struct Decoded<T> {
+        value: T,
+        text: String,
+    }
+
+    impl<T: FromSql> FromRow for Decoded<T> {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self {
+                value: row.try_get("value")?,
+                text: row.try_get("text")?,
+            })
+        }
+    }
+
+    // the binary decoding has to agree with the text postgres prints
+    async fn decode_types(executor: &impl Executor) {
+        executor
+            .execute(&Query::new("SET TIME ZONE 'UTC'"))
+            .await
+            .unwrap();
+
+        for expression in [
+            "'2024-02-29'::date",
+            "'0044-03-15 BC'::date",
+            "'1999-12-31 23:59:58.25'::timestamp",
+            "'infinity'::timestamp",
+            "'2024-02-29 13:45:01.5+02'::timestamptz",
+            "'13:45:01.000123'::time",
+            "'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid",
+            "'{\"a\": [1, 2]}'::jsonb",
+            "'{\"a\": 1}'::json",
+            "'ann'::varchar",
+            "'x'::\"char\"",
+            "'-12345.678'::numeric",
+            "'0.00000005'::numeric",
+            "'12.50'::numeric",
+            "'1e20'::numeric",
+            "'1234567890123456.789012345678'::numeric",
+        ] {
+            let query = Query::new(format!(
+                "SELECT {0} AS value, ({0})::text AS text",
+                expression
+            ));
+            let decoded = executor.fetch_one::<Decoded<String>>(&query).await.unwrap();
+            assert_eq!(decoded.value, decoded.text);
+        }
+
+        let numeric = Query::new("SELECT '-2.5'::numeric AS value, '' AS text");
+        assert_eq!(
+            executor
+                .fetch_one::<Decoded<f64>>(&numeric)
+                .await
+                .map(|d| d.value),
+            Ok(-2.5)
+        );
+
+        let inet = Query::new("SELECT '10.0.0.1'::inet AS value, '' AS text");
+        assert_eq!(
+            executor
+                .fetch_one::<Decoded<String>>(&inet)
+                .await
+                .map(|d| d.value),
+            Err(QueryError::UnknownSqlType(
+                "value".to_owned(),
+                "inet".to_owned()
+            ))
+        );
+    }
+
+    #[cfg(feature = "tokio-postgres")]
+    #[tokio::test]
+    #[ignore = "needs QREX_POSTGRES_URL"]
+    async fn test_tokio_postgres_executor() {
+        let url = url();
+        let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls)
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+        round_trip(&client, "qrex_tokio_postgres").await;
+        decode_types(&client).await;
+
+        // values are checked against the parameter type postgres inferred
+        for (sql, value) in [
+            ("SELECT $1::numeric", SqlValue::I32(1)),
+            ("SELECT $1::text", SqlValue::I64(1)),
+            ("SELECT $1::int8", SqlValue::from("1")),
+            ("SELECT $1::bool", SqlValue::F64(1.0)),
+        ] {
+            assert!(matches!(
+                Executor::execute(&client, &Query::new(sql).bind(value)).await,
+                Err(QueryError::Driver(error)) if error.contains("cannot convert")
+            ));
+        }
+        let widened = Query::new("SELECT $1::int8, $2::int2")
+            .bind(SqlValue::I32(7))
+            .bind(3);
+        assert_eq!(Executor::execute(&client, &widened).await, Ok(1));
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    #[ignore = "needs QREX_POSTGRES_URL"]
+    async fn test_sqlx_executor() {
+        let url = url();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        round_trip(&pool, "qrex_sqlx").await;
+        decode_types(&pool).await;
+    }
+
+    const INT4: u32 = 23;
+    const TEXT: u32 = 25;
+    const FLOAT8: u32 = 701;
+    const BYTEA: u32 = 17;
+    const NUMERIC: u32 = 1700;
+
+    fn users_script() -> Vec<(&'static str, stand_in::Reply)> {
+        let user = vec![INT4, TEXT, FLOAT8, BYTEA];
+        vec![
+            (
+                "INSERT INTO users (id, name, score, avatar) VALUES ($1, $2, $3, $4)",
+                stand_in::Reply {
+                    params: user.clone(),
+                    rows: Some(vec![]),
+                    tag: "INSERT 0 1",
+                    ..Default::default()
+                },
+            ),
+            // sends the bound values back, through the driver encoding and our decoding
+            (
+                "SELECT $1 AS id, $2 AS name, $3 AS score, $4 AS avatar",
+                stand_in::Reply {
+                    params: user.clone(),
+                    columns: vec![
+                        ("id", INT4),
+                        ("name", TEXT),
+                        ("score", FLOAT8),
+                        ("avatar", BYTEA),
+                    ],
+                    tag: "SELECT 1",
+                    ..Default::default()
+                },
+            ),
+            (
+                "SELECT $1 AS value, $2 AS text",
+                stand_in::Reply {
+                    params: vec![NUMERIC, TEXT],
+                    columns: vec![("value", NUMERIC), ("text", TEXT)],
+                    tag: "SELECT 1",
+                    ..Default::default()
+                },
+            ),
+        ]
+    }
+
+    async fn stand_in_round_trip(executor: &impl Executor) {
+        let insert =
+            Query::new("INSERT INTO users (id, name, score, avatar) VALUES ($1, $2, $3, $4)")
+                .bind(1)
+                .bind("O'Brien")
+                .bind(2.5)
+                .bind(vec![0u8, 255]);
+        assert_eq!(executor.execute(&insert).await, Ok(1));
+
+        let select = Query::new("SELECT $1 AS id, $2 AS name, $3 AS score, $4 AS avatar");
+        assert_eq!(
+            executor
+                .fetch_one::<User>(
+                    &select
+                        .clone()
+                        .bind(1)
+                        .bind("O'Brien")
+                        .bind(2.5)
+                        .bind(vec![0u8, 255])
+                )
+                .await,
+            Ok(User {
+                id: 1,
+                name: "O'Brien".to_owned(),
+                score: Some(2.5),
+                avatar: vec![0, 255],
+            })
+        );
+        assert_eq!(
+            executor
+                .fetch_one::<User>(
+                    &select
+                        .bind(3)
+                        .bind("nobody")
+                        .bind(SqlValue::Null)
+                        .bind(Vec::<u8>::new())
+                )
+                .await
+                .map(|u| u.score),
+            Ok(None)
+        );
+
+        // every digit survives the numeric encoding both ways
+        let numeric = Query::new("SELECT $1 AS value, $2 AS text")
+            .bind(SqlValue::Numeric("-12345.678000000000000001".to_owned()))
+            .bind("");
+        assert_eq!(
+            executor
+                .fetch_one::<Decoded<String>>(&numeric)
+                .await
+                .map(|d| d.value),
+            Ok("-12345.678000000000000001".to_owned())
+        );
+    }
+
+    // values as postgres sends them in binary, read by column
+    const DECODED: [(&str, u32, Option<&[u8]>); 11] = [
+        ("day", 1082, Some(&8_825i32.to_be_bytes())),
+        ("bc", 1082, Some(&(-730_485i32).to_be_bytes())),
+        ("forever", 1082, Some(&i32::MAX.to_be_bytes())),
+        ("at", 1114, Some(&(-1_500_000i64).to_be_bytes())),
+        ("zoned", 1184, Some(&0i64.to_be_bytes())),
+        // -12345.678 is digits 1, 2345, 6780 with weight 1 and scale 3
+        (
+            "price",
+            NUMERIC,
+            Some(&[0, 3, 0, 1, 0x40, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1a, 0x7c]),
+        ),
+        ("key", 2950, Some(&[0xab; 16])),
+        ("doc", 3802, Some(b"\x01{\"a\": 1}")),
+        ("grade", 18, Some(b"A")),
+        ("missing", 20, None),
+        ("address", 869, Some(&[2, 32, 0, 4, 10, 0, 0, 1])),
+    ];
+
+    struct Values(Vec<Result<SqlValue, QueryError>>);
+
+    impl FromRow for Values {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self(DECODED.iter().map(|c| row.get_value(c.0)).collect()))
+        }
+    }
+
+    fn decoded_script() -> (&'static str, stand_in::Reply) {
+        (
+            "SELECT decoded",
+            stand_in::Reply {
+                columns: DECODED.iter().map(|c| (c.0, c.1)).collect(),
+                rows: Some(vec![DECODED
+                    .iter()
+                    .map(|c| c.2.map(<[u8]>::to_vec))
+                    .collect()]),
+                tag: "SELECT 1",
+                ..Default::default()
+            },
+        )
+    }
+
+    async fn stand_in_decode(executor: &impl Executor) {
+        let text = |text: &str| Ok(SqlValue::Text(text.to_owned()));
+        let date = |text: &str| Ok(SqlValue::StringDate(text.to_owned()));
+        let values = executor
+            .fetch_one::<Values>(&Query::new("SELECT decoded"))
+            .await
+            .map(|v| v.0);
+        assert_eq!(
+            values,
+            Ok(vec![
+                date("2024-02-29"),
+                date("0001-01-01 BC"),
+                date("infinity"),
+                date("1999-12-31 23:59:58.5"),
+                date("2000-01-01 00:00:00+00"),
+                Ok(SqlValue::Numeric("-12345.678".to_owned())),
+                text("abababab-abab-abab-abab-abababababab"),
+                text("{\"a\": 1}"),
+                text("A"),
+                Ok(SqlValue::Null),
+                Err(QueryError::UnknownSqlType(
+                    "address".to_owned(),
+                    "inet".to_owned()
+                )),
+            ])
+        );
+    }
+
+    #[cfg(feature = "tokio-postgres")]
+    #[tokio::test]
+    async fn test_tokio_postgres_stand_in() {
+        let mut script = users_script();
+        script.push(decoded_script());
+        script.push((
+            "SELECT $1",
+            stand_in::Reply {
+                params: vec![NUMERIC],
+                ..Default::default()
+            },
+        ));
+        let server = stand_in::start(script).await;
+        let (client, connection) = tokio_postgres::connect(&server.url, tokio_postgres::NoTls)
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+
+        stand_in_round_trip(&client).await;
+        stand_in_decode(&client).await;
+        // values are checked against the parameter type the server inferred
+        assert!(matches!(
+            Executor::execute(&client, &Query::new("SELECT $1").bind(SqlValue::I32(1))).await,
+            Err(QueryError::Driver(error)) if error.contains("cannot convert")
+        ));
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    async fn test_sqlx_stand_in() {
+        let mut script = users_script();
+        script.push(decoded_script());
+        let server = stand_in::start(script).await;
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&server.url)
+            .await
+            .unwrap();
+
+        stand_in_round_trip(&pool).await;
+        stand_in_decode(&pool).await;
+    }
+
+    struct Count {
+        n: i64,
+    }
+
+    impl FromRow for Count {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self {
+                n: row.try_get("n")?,
+            })
+        }
+    }
+
+    async fn scoped_transaction<E: SingleConnection>(connection: &Connection<E>, table: &str) {
+        connection
+            .execute(&Query::new(format!(
+                "CREATE TEMPORARY TABLE {} (id INT4 PRIMARY KEY)",
+                table
+            )))
+            .await
+            .unwrap();
+        let insert =
+            |id: i32| Query::new(format!("INSERT INTO {} (id) VALUES ($1)", table)).bind(id);
+        let begin = Begin::default().isolation(IsolationLevel::Serializable);
+
+        let failed = transaction(connection, begin.clone(), async |c: &Connection<E>| {
+            c.execute(&insert(1)).await?;
+            Err::<(), _>(QueryError::RowNotFound)
+        })
+        .await;
+        assert_eq!(failed, Err(QueryError::RowNotFound));
+
+        let committed = transaction(connection, begin.clone(), async |c: &Connection<E>| {
+            c.execute(&insert(2)).await
+        })
+        .await;
+        assert_eq!(committed, Ok(1));
+
+        let (inserted, wait) = tokio::sync::oneshot::channel();
+        tokio::select! {
+            _ = transaction(connection, begin, async move |c: &Connection<E>| {
+                c.execute(&insert(3)).await?;
+                let _ = inserted.send(());
+                std::future::pending::<Result<(), QueryError>>().await
+            }) => unreachable!(),
+            _ = wait => {}
+        }
+
+        // the abandoned transaction stays open until recover rolls it back
+        let count = Query::new(format!("SELECT COUNT(*) AS n FROM {}", table));
+        assert!(connection.is_poisoned());
+        assert_eq!(
+            connection.fetch_one::<Count>(&count).await.map(|c| c.n),
+            Err(QueryError::AbandonedTransaction)
+        );
+        assert_eq!(connection.recover().await, Ok(()));
+        assert_eq!(
+            connection.fetch_one::<Count>(&count).await.map(|c| c.n),
+            Ok(1)
+        );
+
+        // the inner failure only rolls back to its savepoint
+        let nested = transaction(connection, Begin::default(), async |c: &Connection<E>| {
+            c.execute(&insert(4)).await?;
+            let inner = transaction(c, Begin::default(), async |c: &Connection<E>| {
+                c.execute(&insert(5)).await?;
+                c.execute(&insert(5)).await
+            })
+            .await;
+            assert!(matches!(inner, Err(QueryError::Driver(_))));
+            transaction(c, Begin::default(), async |c: &Connection<E>| {
+                c.execute(&insert(6)).await
+            })
+            .await
+        })
+        .await;
+        assert_eq!(nested, Ok(1));
+        assert_eq!(
+            connection.fetch_one::<Count>(&count).await.map(|c| c.n),
+            Ok(3)
+        );
+    }
+
+    async fn stand_in_transaction<E: SingleConnection>(
+        connection: &Connection<E>,
+        log: &std::sync::Mutex<Vec<String>>,
+    ) {
+        let step = Query::new("SELECT 1");
+        let nested = transaction(connection, Begin::default(), async |c: &Connection<E>| {
+            c.execute(&step).await?;
+            let failed = transaction(c, Begin::default(), async |c: &Connection<E>| {
+                c.execute(&step).await?;
+                Err::<(), _>(QueryError::RowNotFound)
+            })
+            .await;
+            assert_eq!(failed, Err(QueryError::RowNotFound));
+            let serializable = Begin::default().isolation(IsolationLevel::Serializable);
+            assert!(matches!(
+                transaction(c, serializable, async |_: &Connection<E>| Ok(())).await,
+                Err(QueryError::Unsupported(..))
+            ));
+            transaction(c, Begin::default(), async |c: &Connection<E>| {
+                c.execute(&step).await
+            })
+            .await
+        })
+        .await;
+        assert_eq!(nested, Ok(0));
+
+        let (stepped, wait) = tokio::sync::oneshot::channel();
+        tokio::select! {
+            _ = transaction(connection, Begin::default(), async move |c: &Connection<E>| {
+                c.execute(&step).await?;
+                let _ = stepped.send(());
+                std::future::pending::<Result<(), QueryError>>().await
+            }) => unreachable!(),
+            _ = wait => {}
+        }
+        assert!(connection.is_poisoned());
+        assert_eq!(
+            connection.execute(&Query::new("SELECT 2")).await,
+            Err(QueryError::AbandonedTransaction)
+        );
+        assert_eq!(connection.recover().await, Ok(()));
+        assert!(!connection.is_poisoned());
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "BEGIN",
+                "SELECT 1",
+                "SAVEPOINT qrex_savepoint_1",
+                "SELECT 1",
+                "ROLLBACK TO SAVEPOINT qrex_savepoint_1",
+                "SAVEPOINT qrex_savepoint_1",
+                "SELECT 1",
+                "RELEASE SAVEPOINT qrex_savepoint_1",
+                "COMMIT",
+                "BEGIN",
+                "SELECT 1",
+                "ROLLBACK",
+            ]
+        );
+    }
+
+    #[cfg(feature = "tokio-postgres")]
+    #[tokio::test]
+    async fn test_tokio_postgres_stand_in_transaction() {
+        let server = stand_in::start(vec![]).await;
+        let (client, connection) = tokio_postgres::connect(&server.url, tokio_postgres::NoTls)
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+        stand_in_transaction(&Connection::new(client, Dialect::Postgres), &server.log).await;
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    async fn test_sqlx_stand_in_transaction() {
+        use sqlx::Connection as _;
+
+        let server = stand_in::start(vec![]).await;
+        let connection = sqlx::postgres::PgConnection::connect(&server.url)
+            .await
+            .unwrap();
+        stand_in_transaction(
+            &Connection::new(tokio::sync::Mutex::new(connection), Dialect::Postgres),
+            &server.log,
+        )
+        .await;
+    }
+
+    #[cfg(feature = "tokio-postgres")]
+    #[tokio::test]
+    #[ignore = "needs QREX_POSTGRES_URL"]
+    async fn test_tokio_postgres_transaction() {
+        let url = url();
+        let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls)
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+        scoped_transaction(
+            &Connection::new(client, Dialect::Postgres),
+            "qrex_tokio_postgres_transaction",
+        )
+        .await;
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    #[ignore = "needs QREX_POSTGRES_URL"]
+    async fn test_sqlx_transaction() {
+        use sqlx::Connection as _;
+
+        let url = url();
+        let connection = sqlx::postgres::PgConnection::connect(&url).await.unwrap();
+        scoped_transaction(
+            &Connection::new(tokio::sync::Mutex::new(connection), Dialect::Postgres),
+            "qrex_sqlx_transaction",
+        )
+        .await;
+    }
+}
@@ -1,30 +1,113 @@
-use super::condition::Condition;
+use super::{condition::Condition, dialect::Dialect, error::QueryError};
+
+pub enum Grouping {
+    Rollup(Vec<String>),
+    Cube(Vec<String>),
+    Sets(Vec<Vec<String>>),
+}
+
+impl Grouping {
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            Grouping::Rollup(fields) | Grouping::Cube(fields) => {
+                fields.iter().map(|f| f.as_str()).collect()
+            }
+            Grouping::Sets(sets) => sets.iter().flatten().map(|f| f.as_str()).collect(),
+        }
+    }
+}
 
 pub trait GroupBy {
     fn get_group(&self) -> Vec<&str>;
     fn set_group(&mut self, group: Vec<String>);
 
+    fn get_grouping(&self) -> Option<&Grouping>;
+    fn set_grouping(&mut self, grouping: Grouping);
+
     fn get_having_condition(&self) -> Option<&Condition>;
 
     fn set_having_condition(&mut self, condition: Condition);
 
     fn group_by_field(&mut self, field: &str) -> &mut Self {
-        self.set_group(vec![field.to_owned()]);
+        self.group_by_fields(vec![field.to_owned()])
+    }
+
+    fn rollup(&mut self, fields: &[&str]) -> &mut Self {
+        self.set_grouping(Grouping::Rollup(
+            fields.iter().map(|&f| f.to_owned()).collect(),
+        ));
+        self
+    }
+
+    fn cube(&mut self, fields: &[&str]) -> &mut Self {
+        self.set_grouping(Grouping::Cube(
+            fields.iter().map(|&f| f.to_owned()).collect(),
+        ));
+        self
+    }
+
+    fn grouping_sets(&mut self, sets: &[&[&str]]) -> &mut Self {
+        self.set_grouping(Grouping::Sets(
+            sets.iter()
+                .map(|set| set.iter().map(|&f| f.to_owned()).collect())
+                .collect(),
+        ));
         self
     }
 
-    fn build_group_by(&self) -> Option<String> {
-        let having = self.get_having_condition()?.build_conditions()?;
+    fn build_grouping(&self, dialect: Dialect) -> Result<Option<String>, QueryError> {
+        let grouping = match self.get_grouping() {
+            Some(grouping) => grouping,
+            None => return Ok(None),
+        };
+
+        let grouping = match (dialect, grouping) {
+            (Dialect::Sqlite, _) => return Err(QueryError::Unsupported("GROUPING SETS", dialect)),
+            (Dialect::MySql, Grouping::Rollup(fields)) => {
+                if !self.get_group().is_empty() {
+                    return Err(QueryError::Unsupported("partial ROLLUP", dialect));
+                }
+                format!("{} WITH ROLLUP", fields.join(", "))
+            }
+            (Dialect::MySql, _) => return Err(QueryError::Unsupported("GROUPING SETS", dialect)),
+            (_, Grouping::Rollup(fields)) => format!("ROLLUP ({})", fields.join(", ")),
+            (_, Grouping::Cube(fields)) => format!("CUBE ({})", fields.join(", ")),
+            (_, Grouping::Sets(sets)) => format!(
+                "GROUPING SETS ({})",
+                sets.iter()
+                    .map(|set| format!("({})", set.join(", ")))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        };
 
-        let fields = self.get_group();
+        Ok(Some(grouping))
+    }
 
-        if fields.is_empty() {
-            return None;
+    fn build_group_by(&self, dialect: Dialect) -> Result<Option<String>, QueryError> {
+        let having = match self.get_having_condition() {
+            Some(c) => c.for_dialect(dialect)?.build_conditions(),
+            None => None,
+        };
+
+        let mut fields = self
+            .get_group()
+            .iter()
+            .map(|&f| f.to_owned())
+            .collect::<Vec<String>>();
+
+        if let Some(grouping) = self.build_grouping(dialect)? {
+            fields.push(grouping);
         }
 
-        let str_field = fields.join(", ");
+        let group_by = match (fields.is_empty(), having) {
+            (true, None) => return Ok(None),
+            (true, Some(having)) => format!("HAVING {}", having),
+            (false, None) => format!("GROUP BY {}", fields.join(", ")),
+            (false, Some(having)) => format!("GROUP BY {} HAVING {}", fields.join(", "), having),
+        };
 
-        Some(format!("GROUP BY {} HAVING {}", str_field, having))
+        Ok(Some(group_by))
     }
 
     fn having(&mut self, condition: Condition) -> &Self {
@@ -33,20 +116,30 @@ pub trait GroupBy {
     }
 
     fn group_by_fields(&mut self, fields: Vec<String>) -> &mut Self {
-        self.set_group(fields);
+        let mut group = self
+            .get_group()
+            .iter()
+            .map(|&f| f.to_owned())
+            .collect::<Vec<String>>();
+        group.extend(fields);
+        self.set_group(group);
         self
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::query_builder::condition::Condition;
+    use crate::query_builder::{
+        condition::Condition, dialect::Dialect, error::QueryError, select::Agregate,
+        value::SqlValue,
+    };
 
-    use super::GroupBy;
+    use super::{GroupBy, Grouping};
 
-    #[derive(Clone, Default)]
+    #[derive(Default)]
     struct GrouByTest {
         groups: Vec<String>,
+        grouping: Option<Grouping>,
         having: Option<Condition>,
     }
 
@@ -55,12 +148,138 @@ mod test {
             self.groups.iter().map(|s| s.as_str()).collect()
         }
 
-        fn set_having_condition(&mut self, condition: Condition) {}
+        fn set_having_condition(&mut self, condition: Condition) {
+            self.having = Some(condition)
+        }
 
-        fn set_group(&mut self, group: Vec<String>) {}
+        fn set_group(&mut self, group: Vec<String>) {
+            self.groups = group
+        }
+
+        fn get_grouping(&self) -> Option<&Grouping> {
+            self.grouping.as_ref()
+        }
+
+        fn set_grouping(&mut self, grouping: Grouping) {
+            self.grouping = Some(grouping)
+        }
 
         fn get_having_condition(&self) -> Option<&Condition> {
             self.having.as_ref()
         }
     }
+
+    fn having() -> Condition {
+        Condition::Gt("SUM(amount)".to_owned(), SqlValue::Int(0))
+    }
+
+    #[test]
+    fn test_rollup() {
+        let mut group = GrouByTest::default();
+        group.rollup(&["year", "quarter"]).having(having());
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "GROUP BY ROLLUP (year, quarter) HAVING SUM(amount) > 0"
+        );
+        assert_eq!(
+            group.build_group_by(Dialect::MySql).unwrap().unwrap(),
+            "GROUP BY year, quarter WITH ROLLUP HAVING SUM(amount) > 0"
+        );
+    }
+
+    #[test]
+    fn test_partial_rollup() {
+        let mut group = GrouByTest::default();
+        group
+            .group_by_field("region")
+            .rollup(&["year"])
+            .having(having());
+        assert_eq!(
+            group.build_group_by(Dialect::SqlServer).unwrap().unwrap(),
+            "GROUP BY region, ROLLUP (year) HAVING SUM(amount) > 0"
+        );
+        assert_eq!(
+            group.build_group_by(Dialect::MySql),
+            Err(QueryError::Unsupported("partial ROLLUP", Dialect::MySql))
+        );
+    }
+
+    #[test]
+    fn test_cube() {
+        let mut group = GrouByTest::default();
+        group.cube(&["product", "region"]).having(having());
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "GROUP BY CUBE (product, region) HAVING SUM(amount) > 0"
+        );
+        assert_eq!(
+            group.build_group_by(Dialect::MySql),
+            Err(QueryError::Unsupported("GROUPING SETS", Dialect::MySql))
+        );
+    }
+
+    #[test]
+    fn test_grouping_sets() {
+        let mut group = GrouByTest::default();
+        group
+            .grouping_sets(&[&["account", "month"], &["account"], &[]])
+            .having(having());
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "GROUP BY GROUPING SETS ((account, month), (account), ()) HAVING SUM(amount) > 0"
+        );
+        assert_eq!(
+            group.build_group_by(Dialect::Sqlite),
+            Err(QueryError::Unsupported("GROUPING SETS", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_group_by_without_having() {
+        let mut group = GrouByTest::default();
+        assert_eq!(group.build_group_by(Dialect::Postgres), Ok(None));
+        group.group_by_field("department");
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "GROUP BY department"
+        );
+    }
+
+    #[test]
+    fn test_group_by_appends() {
+        let mut group = GrouByTest::default();
+        group
+            .group_by_field("country")
+            .group_by_field("city")
+            .group_by_fields(vec!["street".to_owned(), "zip".to_owned()]);
+        assert_eq!(group.get_group(), vec!["country", "city", "street", "zip"]);
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "GROUP BY country, city, street, zip"
+        );
+    }
+
+    #[test]
+    fn test_having_without_group_by() {
+        let mut group = GrouByTest::default();
+        group.having(Agregate::CountAll.gt(SqlValue::Int(0)));
+        assert_eq!(
+            group.build_group_by(Dialect::Postgres).unwrap().unwrap(),
+            "HAVING COUNT(*) > 0"
+        );
+    }
+
+    #[test]
+    fn test_having_over_agregates() {
+        let mut group = GrouByTest::default();
+        group
+            .group_by_field("customer_id")
+            .having(Agregate::CountAll.gt(SqlValue::Int(5)).and(
+                Agregate::Sum("total".to_owned()).between(SqlValue::Int(100), SqlValue::Int(900)),
+            ));
+        assert_eq!(
+            group.build_group_by(Dialect::MySql).unwrap().unwrap(),
+            "GROUP BY customer_id HAVING (COUNT(*) > 5 AND SUM(total) BETWEEN 100 AND 900)"
+        );
+    }
 }
@@ -0,0 +1,221 @@
+use super::{
+    dialect::Dialect,
+    error::QueryError,
+    query::{BuildQuery, Query},
+    returning::Returning,
+    select::Field,
+    value::SqlValue,
+    with::{CteEntry, With},
+};
+
+pub struct Insert {
+    table: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<SqlValue>>,
+    returning: Vec<(Field, String)>,
+    ctes: Vec<CteEntry>,
+    dialect: Dialect,
+}
+
+impl Insert {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_owned(),
+            columns: vec![],
+            rows: vec![],
+            returning: vec![],
+            ctes: vec![],
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn columns(&mut self, columns: &[&str]) -> &mut Self {
+        self.columns.extend(columns.iter().map(|&c| c.to_owned()));
+        self
+    }
+
+    pub fn values(&mut self, row: Vec<SqlValue>) -> &mut Self {
+        self.rows.push(row);
+        self
+    }
+
+    // values are bound as params, never inlined, after the params of the ctes.
+    // mysql only takes WITH inside INSERT ... SELECT
+    pub fn build_insert(&self) -> Result<Query, QueryError> {
+        if self.columns.is_empty() || self.rows.is_empty() {
+            return Err(QueryError::NoFields);
+        }
+
+        if !self.ctes.is_empty() && self.dialect == Dialect::MySql {
+            return Err(QueryError::Unsupported("WITH on INSERT", self.dialect));
+        }
+
+        let mut params = vec![];
+        let with = self.build_with(self.dialect, &mut params);
+        let mut rows = vec![];
+
+        for row in self.rows.iter() {
+            if row.len() != self.columns.len() {
+                return Err(QueryError::ColumnCountMismatch(
+                    self.columns.len(),
+                    row.len(),
+                ));
+            }
+
+            let placeholders = row
+                .iter()
+                .map(|value| {
+                    params.push(value.clone());
+                    self.dialect.placeholder(params.len())
+                })
+                .collect::<Vec<String>>();
+            rows.push(format!("({})", placeholders.join(", ")));
+        }
+
+        let mut sql = match with {
+            Some(ctes) => format!("{} INSERT INTO {}", ctes, self.table),
+            None => format!("INSERT INTO {}", self.table),
+        };
+        sql.push_str(&format!(" ({})", self.columns.join(", ")));
+
+        let returning = self.build_returning(self.dialect, "INSERTED")?;
+
+        if let (Some(output), Dialect::SqlServer) = (&returning, self.dialect) {
+            sql.push_str(&format!(" {}", output));
+        }
+
+        sql.push_str(&format!(" VALUES {}", rows.join(", ")));
+
+        if let (Some(returning), false) = (&returning, self.dialect == Dialect::SqlServer) {
+            sql.push_str(&format!(" {}", returning));
+        }
+
+        Ok(Query::new(sql)
+            .with_fields(self.returning_fields())
+            .with_params(params))
+    }
+}
+
+impl Returning for Insert {
+    fn get_returning(&self) -> Vec<(&Field, &str)> {
+        self.returning
+            .iter()
+            .map(|(f, a)| (f, a.as_str()))
+            .collect()
+    }
+
+    fn set_returning(&mut self, f: impl FnOnce(&mut Vec<(Field, String)>)) {
+        f(&mut self.returning)
+    }
+}
+
+impl With for Insert {
+    fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>)) {
+        f(&mut self.ctes)
+    }
+
+    fn get_with(&self) -> Vec<&CteEntry> {
+        self.ctes.iter().collect()
+    }
+}
+
+impl BuildQuery for Insert {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_insert()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_rows() {
+        let mut insert = Insert::new("users");
+        insert
+            .columns(&["name", "age"])
+            .values(vec!["bob".into(), 30.into()])
+            .values(vec!["alice".into(), SqlValue::Null]);
+        let query = insert.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "INSERT INTO users (name, age) VALUES ($1, $2), ($3, $4)"
+        );
+        assert_eq!(
+            query.params(),
+            &[
+                SqlValue::Text("bob".to_owned()),
+                SqlValue::I32(30),
+                SqlValue::Text("alice".to_owned()),
+                SqlValue::Null
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_returning() {
+        let mut insert = Insert::new("users");
+        insert
+            .columns(&["name"])
+            .values(vec!["bob".into()])
+            .returning(&["id"])
+            .returning_alias("created_at", "created");
+        let query = insert.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "INSERT INTO users (name) VALUES ($1) RETURNING id, created_at AS created"
+        );
+        assert_eq!(query.fields(), &["id".to_owned(), "created".to_owned()]);
+
+        insert.dialect(Dialect::SqlServer);
+        assert_eq!(
+            insert.build().unwrap().sql(),
+            "INSERT INTO users (name) OUTPUT INSERTED.id, INSERTED.created_at AS created VALUES (@p1)"
+        );
+        insert.dialect(Dialect::MySql);
+        assert_eq!(
+            insert.build(),
+            Err(QueryError::Unsupported("RETURNING", Dialect::MySql))
+        );
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let mut insert = Insert::new("users");
+        assert_eq!(insert.build(), Err(QueryError::NoFields));
+        insert.columns(&["name", "age"]).values(vec!["bob".into()]);
+        assert_eq!(insert.build(), Err(QueryError::ColumnCountMismatch(2, 1)));
+    }
+
+    #[test]
+    fn test_insert_with_cte() {
+        let mut insert = Insert::new("audit");
+        insert
+            .with(
+                "recent",
+                Query::new("SELECT id FROM users WHERE age > $1").bind(30),
+            )
+            .columns(&["action"])
+            .values(vec!["purge".into()]);
+        let query = insert.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "WITH recent AS (SELECT id FROM users WHERE age > $1) INSERT INTO audit (action) VALUES ($2)"
+        );
+        assert_eq!(
+            query.params(),
+            &[SqlValue::I32(30), SqlValue::Text("purge".to_owned())]
+        );
+
+        insert.dialect(Dialect::MySql);
+        assert_eq!(
+            insert.build(),
+            Err(QueryError::Unsupported("WITH on INSERT", Dialect::MySql))
+        );
+    }
+}
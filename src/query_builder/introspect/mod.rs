@@ -0,0 +1,880 @@
+use super::{
+    condition::Condition,
+    ddl::{CreateIndex, ForeignKey, ForeignKeyAction},
+    dialect::Dialect,
+    error::QueryError,
+    executor::Executor,
+    query::Query,
+    row::{FromRow, Row},
+    schema::{ColumnDef, SqlType, TableDef},
+    value::SqlValue,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableSchema {
+    table: TableDef,
+    indexes: Vec<CreateIndex>, // The primary key index is in TableDef::get_primary_key
+    foreign_keys: Vec<ForeignKey>,
+}
+
+impl TableSchema {
+    pub fn get_table(&self) -> &TableDef {
+        &self.table
+    }
+
+    pub fn get_indexes(&self) -> &[CreateIndex] {
+        &self.indexes
+    }
+
+    pub fn get_foreign_keys(&self) -> &[ForeignKey] {
+        &self.foreign_keys
+    }
+
+    // a #[derive(Table)] struct, indexes and foreign keys are listed in comments above it
+    pub fn to_rust(&self) -> Result<String, QueryError> {
+        let mut source = String::new();
+
+        for index in self.indexes.iter() {
+            source.push_str(&format!("// {}\n", index.build_create_index()?.sql()));
+        }
+
+        for foreign_key in self.foreign_keys.iter() {
+            let on_delete = match foreign_key.get_on_delete() {
+                Some(ForeignKeyAction::NoAction) | None => "",
+                Some(ForeignKeyAction::Restrict) => " on delete restrict",
+                Some(ForeignKeyAction::Cascade) => " on delete cascade",
+                Some(ForeignKeyAction::SetNull) => " on delete set null",
+                Some(ForeignKeyAction::SetDefault) => " on delete set default",
+            };
+            source.push_str(&format!(
+                "// foreign key ({}) references {} ({}){}\n",
+                foreign_key.get_columns().join(", "),
+                foreign_key.get_table(),
+                foreign_key.get_references().join(", "),
+                on_delete
+            ));
+        }
+
+        let name = self.table.get_name();
+        source.push_str("#[derive(Debug, Table, Selectable, FromRow)]\n");
+        source.push_str(&format!("#[qrex(table = \"{}\")]\n", name));
+        source.push_str(&format!("pub struct {} {{\n", struct_name(name)));
+
+        let primary_key = self.table.get_primary_key();
+        for column in self.table.get_columns() {
+            let field = field_name(column.get_name());
+
+            let mut attributes = vec![];
+            if primary_key.contains(&column.get_name()) {
+                attributes.push("primary_key".to_owned());
+            }
+            if field != column.get_name() {
+                attributes.push(format!("rename = \"{}\"", column.get_name()));
+            }
+            if !attributes.is_empty() {
+                source.push_str(&format!("    #[qrex({})]\n", attributes.join(", ")));
+            }
+
+            let rust_type = rust_type(column.get_sql_type());
+            match column.is_nullable() {
+                true => source.push_str(&format!("    pub {}: Option<{}>,\n", field, rust_type)),
+                false => source.push_str(&format!("    pub {}: {},\n", field, rust_type)),
+            }
+        }
+
+        source.push_str("}\n");
+        Ok(source)
+    }
+}
+
+// the ColumnType a field needs for #[derive(Table)], smallint and numeric are widened
+fn rust_type(sql_type: SqlType) -> &'static str {
+    match sql_type {
+        SqlType::Boolean => "bool",
+        SqlType::SmallInt | SqlType::Integer => "i32",
+        SqlType::BigInt => "i64",
+        SqlType::Real => "f32",
+        SqlType::Double | SqlType::Numeric(_, _) | SqlType::UnboundedNumeric => "f64",
+        SqlType::Varchar(_) | SqlType::Text | SqlType::Date | SqlType::Timestamp => "String",
+        SqlType::Blob => "Vec<u8>",
+    }
+}
+
+fn struct_name(table: &str) -> String {
+    table
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+// snake case, keywords get a trailing underscore and a rename attribute
+fn field_name(column: &str) -> String {
+    const KEYWORDS: [&str; 37] = [
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ];
+
+    let mut field = String::new();
+    for (i, c) in column.chars().enumerate() {
+        match c {
+            c if c.is_ascii_uppercase() => {
+                if i > 0 && !field.ends_with('_') {
+                    field.push('_');
+                }
+                field.push(c.to_ascii_lowercase());
+            }
+            c if c.is_ascii_alphanumeric() => field.push(c),
+            _ if !field.ends_with('_') => field.push('_'),
+            _ => {}
+        }
+    }
+
+    if field.is_empty() || field.starts_with(|c: char| c.is_ascii_digit()) {
+        field.insert(0, '_');
+    }
+    if KEYWORDS.contains(&field.as_str()) {
+        field.push('_');
+    }
+
+    field
+}
+
+// the type name as sqlite stores it or as postgres format_type prints it, types with no
+// SqlType (uuid, json, time, char(n), typeless sqlite columns) read as Text like their values
+fn parse_type(data_type: &str) -> SqlType {
+    let upper = data_type.trim().to_uppercase();
+    let (base, args) = match upper.split_once('(') {
+        Some((base, rest)) => (base.trim(), rest.split(')').next().unwrap_or_default()),
+        None => (upper.as_str(), ""),
+    };
+    let args = args
+        .split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.parse())
+        .collect::<Result<Vec<u32>, _>>();
+
+    match (base, args.as_deref()) {
+        ("BOOLEAN" | "BOOL", _) => SqlType::Boolean,
+        ("SMALLINT" | "INT2", _) => SqlType::SmallInt,
+        ("INTEGER" | "INT" | "INT4", _) => SqlType::Integer,
+        ("BIGINT" | "INT8", _) => SqlType::BigInt,
+        ("REAL" | "FLOAT4", _) => SqlType::Real,
+        ("DOUBLE PRECISION" | "DOUBLE" | "FLOAT8" | "FLOAT", _) => SqlType::Double,
+        ("NUMERIC" | "DECIMAL", Ok([])) => SqlType::UnboundedNumeric,
+        ("NUMERIC" | "DECIMAL", Ok([precision])) => SqlType::Numeric(*precision, 0),
+        ("NUMERIC" | "DECIMAL", Ok([precision, scale])) => SqlType::Numeric(*precision, *scale),
+        ("VARCHAR" | "CHARACTER VARYING", Ok([length])) => SqlType::Varchar(*length),
+        ("BYTEA" | "BLOB", _) => SqlType::Blob,
+        ("DATE", _) => SqlType::Date,
+        ("DATETIME", _) => SqlType::Timestamp,
+        (base, _) if base.starts_with("TIMESTAMP") => SqlType::Timestamp,
+        _ => SqlType::Text,
+    }
+}
+
+// literal defaults only, expressions like now() or nextval(...) are left out
+fn parse_default(default: &str) -> Option<SqlValue> {
+    const NUMBERS: [&str; 6] = [
+        "smallint",
+        "integer",
+        "bigint",
+        "numeric",
+        "real",
+        "double precision",
+    ];
+
+    let default = default.trim();
+
+    // postgres casts literals, 'abc'::character varying or '-1'::integer
+    let (literal, cast) = match default.rsplit_once("::") {
+        Some((literal, cast)) if literal.ends_with('\'') => (literal, Some(cast)),
+        _ => (default, None),
+    };
+
+    // sqlite keeps blob defaults as written, X'89ab'
+    if let Some(hex) = literal
+        .strip_prefix("X'")
+        .or_else(|| literal.strip_prefix("x'"))
+        .and_then(|l| l.strip_suffix('\''))
+    {
+        return hex_bytes(hex).map(SqlValue::Bytes);
+    }
+
+    if literal.len() >= 2 && literal.starts_with('\'') && literal.ends_with('\'') {
+        let text = literal[1..literal.len() - 1].replace("''", "'");
+        return match cast {
+            Some(cast) if NUMBERS.iter().any(|n| cast.starts_with(n)) => parse_default(&text),
+            Some(cast) if cast.starts_with("date") || cast.starts_with("timestamp") => {
+                Some(SqlValue::StringDate(text))
+            }
+            Some("bytea") => text
+                .strip_prefix("\\x")
+                .and_then(hex_bytes)
+                .map(SqlValue::Bytes),
+            _ => Some(SqlValue::from(text.as_str())),
+        };
+    }
+
+    match literal.to_lowercase().as_str() {
+        "true" => return Some(SqlValue::Bool(true)),
+        "false" => return Some(SqlValue::Bool(false)),
+        _ => {}
+    }
+
+    // the variants ColumnDef::default gets from integer and float literals
+    if let Ok(val) = literal.parse::<i32>() {
+        return Some(SqlValue::I32(val));
+    }
+    if let Ok(val) = literal.parse::<i64>() {
+        return Some(SqlValue::I64(val));
+    }
+    literal.parse::<f64>().ok().map(SqlValue::F64)
+}
+
+// None when a digit is missing or not hex
+fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// the key parts and the predicate of a CREATE INDEX statement, how sqlite keeps expression
+// and partial indexes
+fn index_clauses(definition: &str) -> (Vec<String>, Option<String>) {
+    let Some(open) = definition.find('(') else {
+        return (vec![], None);
+    };
+
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut end = definition.len();
+
+    for (i, c) in definition[open..].char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 1 => {
+                parts.push(part.trim().to_owned());
+                part.clear();
+                continue;
+            }
+            _ => {}
+        }
+        if depth == 0 {
+            end = open + i + 1;
+            break;
+        }
+        if depth > 1 || c != '(' {
+            part.push(c);
+        }
+    }
+    parts.push(part.trim().to_owned());
+
+    let rest = definition[end..].trim();
+    let predicate = match rest.get(..5) {
+        Some(keyword) if keyword.eq_ignore_ascii_case("WHERE") => Some(rest[5..].trim().to_owned()),
+        _ => None,
+    };
+
+    (parts, predicate)
+}
+
+// lower(email) from the (lower(email)) part CreateIndex renders
+fn unwrap_parentheses(part: &str) -> &str {
+    let Some(inner) = part.strip_prefix('(').and_then(|p| p.strip_suffix(')')) else {
+        return part;
+    };
+
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return part, // (a) + (b), the parentheses do not pair
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    inner
+}
+
+fn parse_action(action: &str) -> Option<ForeignKeyAction> {
+    match action {
+        "RESTRICT" => Some(ForeignKeyAction::Restrict),
+        "CASCADE" => Some(ForeignKeyAction::Cascade),
+        "SET NULL" => Some(ForeignKeyAction::SetNull),
+        "SET DEFAULT" => Some(ForeignKeyAction::SetDefault),
+        _ => None, // NO ACTION, what a foreign key declared without ON DELETE gets
+    }
+}
+
+struct NameRow {
+    name: String,
+}
+
+impl FromRow for NameRow {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            name: row.try_get("name")?,
+        })
+    }
+}
+
+struct ColumnRow {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    default_value: Option<String>,
+    key_position: i64, // Position in the primary key, 0 outside of it
+}
+
+impl FromRow for ColumnRow {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            name: row.try_get("name")?,
+            data_type: row.try_get("data_type")?,
+            not_null: row.try_get("not_null")?,
+            default_value: row.try_get("default_value")?,
+            key_position: row.try_get("key_position")?,
+        })
+    }
+}
+
+struct IndexRow {
+    name: String,
+    is_unique: bool,
+    part: Option<String>, // The column, or the expression where postgres renders it
+    is_expression: bool,
+    is_included: bool,
+    predicate: Option<String>,
+    definition: Option<String>, // The CREATE INDEX statement sqlite keeps
+}
+
+impl FromRow for IndexRow {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            name: row.try_get("name")?,
+            is_unique: row.try_get("is_unique")?,
+            part: row.try_get("part")?,
+            is_expression: row.try_get("is_expression")?,
+            is_included: row.try_get("is_included")?,
+            predicate: row.try_get("predicate")?,
+            definition: row.try_get("definition")?,
+        })
+    }
+}
+
+struct ForeignKeyRow {
+    id: String,
+    referenced_table: String,
+    column_name: String,
+    referenced_column: Option<String>,
+    on_delete: String,
+}
+
+impl FromRow for ForeignKeyRow {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            referenced_table: row.try_get("referenced_table")?,
+            column_name: row.try_get("column_name")?,
+            referenced_column: row.try_get("referenced_column")?,
+            on_delete: row.try_get("on_delete")?,
+        })
+    }
+}
+
+// the four catalog queries, each but the first takes the table name as its only param
+fn catalog_queries(dialect: Dialect) -> Result<[&'static str; 4], QueryError> {
+    match dialect {
+        Dialect::Sqlite => Ok([
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            "SELECT name, type AS data_type, \"notnull\" AS not_null, dflt_value AS default_value, pk AS key_position \
+             FROM pragma_table_info(?) ORDER BY cid",
+            "SELECT il.name AS name, il.\"unique\" AS is_unique, ii.name AS part, \
+             ii.cid = -2 AS is_expression, 0 AS is_included, NULL AS predicate, m.sql AS definition \
+             FROM pragma_index_list(?) AS il JOIN pragma_index_xinfo(il.name) AS ii \
+             LEFT JOIN sqlite_master AS m ON m.type = 'index' AND m.name = il.name \
+             WHERE il.origin != 'pk' AND ii.key = 1 ORDER BY il.name, ii.seqno",
+            "SELECT CAST(id AS TEXT) AS id, \"table\" AS referenced_table, \"from\" AS column_name, \
+             \"to\" AS referenced_column, on_delete FROM pragma_foreign_key_list(?) ORDER BY id, seq",
+        ]),
+        Dialect::Postgres => Ok([
+            "SELECT table_name::text AS name FROM information_schema.tables \
+             WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY table_name",
+            "SELECT a.attname::text AS name, format_type(a.atttypid, a.atttypmod) AS data_type, \
+             a.attnotnull AS not_null, pg_get_expr(d.adbin, d.adrelid) AS default_value, \
+             COALESCE((SELECT k.position FROM unnest(pk.indkey::int2[]) WITH ORDINALITY AS k(attnum, position) \
+             WHERE k.attnum = a.attnum), 0)::int8 AS key_position \
+             FROM pg_attribute a JOIN pg_class c ON c.oid = a.attrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+             LEFT JOIN pg_index pk ON pk.indrelid = c.oid AND pk.indisprimary \
+             WHERE n.nspname = current_schema() AND c.relname::text = $1 \
+             AND a.attnum > 0 AND NOT a.attisdropped ORDER BY a.attnum",
+            "SELECT i.relname::text AS name, ix.indisunique AS is_unique, \
+             pg_get_indexdef(ix.indexrelid, k.position, true) AS part, \
+             (ix.indkey::int2[])[k.position - 1] = 0 AS is_expression, \
+             k.position > ix.indnkeyatts AS is_included, \
+             pg_get_expr(ix.indpred, ix.indrelid, true) AS predicate, NULL::text AS definition \
+             FROM pg_index ix JOIN pg_class t ON t.oid = ix.indrelid \
+             JOIN pg_class i ON i.oid = ix.indexrelid \
+             JOIN pg_namespace n ON n.oid = t.relnamespace \
+             CROSS JOIN LATERAL generate_series(1, ix.indnatts::int4) AS k(position) \
+             WHERE n.nspname = current_schema() AND t.relname::text = $1 \
+             AND NOT ix.indisprimary ORDER BY i.relname, k.position",
+            "SELECT con.conname::text AS id, ref.relname::text AS referenced_table, \
+             a.attname::text AS column_name, ra.attname::text AS referenced_column, \
+             CASE con.confdeltype WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE' \
+             WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' ELSE 'NO ACTION' END AS on_delete \
+             FROM pg_constraint con JOIN pg_class t ON t.oid = con.conrelid \
+             JOIN pg_namespace n ON n.oid = t.relnamespace \
+             JOIN pg_class ref ON ref.oid = con.confrelid \
+             CROSS JOIN LATERAL unnest(con.conkey, con.confkey) WITH ORDINALITY AS k(attnum, refnum, position) \
+             JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum \
+             JOIN pg_attribute ra ON ra.attrelid = con.confrelid AND ra.attnum = k.refnum \
+             WHERE con.contype = 'f' AND n.nspname = current_schema() AND t.relname::text = $1 \
+             ORDER BY con.conname, k.position",
+        ]),
+        _ => Err(QueryError::Unsupported("schema introspection", dialect)),
+    }
+}
+
+fn index_def(
+    index: &str,
+    table: &str,
+    rows: &[IndexRow],
+    dialect: Dialect,
+) -> Result<CreateIndex, QueryError> {
+    let (parts, predicate) = match rows[0].definition.as_deref() {
+        Some(definition) => index_clauses(definition),
+        None => (vec![], None),
+    };
+
+    let mut create = CreateIndex::new(index, table);
+    create.dialect(dialect);
+    if rows[0].is_unique {
+        create.unique();
+    }
+
+    for (position, row) in rows.iter().enumerate() {
+        let part = match &row.part {
+            Some(part) if !row.is_expression || row.definition.is_none() => part.as_str(),
+            _ => parts
+                .get(position)
+                .map(|p| unwrap_parentheses(p))
+                .ok_or_else(|| QueryError::Driver(format!("cannot read index {}", index)))?,
+        };
+        match (row.is_included, row.is_expression) {
+            (true, _) => create.include(&[part]),
+            (false, true) => create.expression(part),
+            (false, false) => create.columns(&[part]),
+        };
+    }
+
+    if let Some(predicate) = rows[0].predicate.clone().or(predicate) {
+        create.r#where(Condition::Native(predicate));
+    }
+
+    Ok(create)
+}
+
+// every table of the current schema, the main database on sqlite
+pub async fn introspect<E: Executor>(
+    executor: &E,
+    dialect: Dialect,
+) -> Result<Vec<TableSchema>, QueryError> {
+    let [tables, columns, indexes, foreign_keys] = catalog_queries(dialect)?;
+
+    let mut schemas = vec![];
+    for table in executor.fetch_all::<NameRow>(&Query::new(tables)).await? {
+        let name = table.name;
+
+        let columns = executor
+            .fetch_all::<ColumnRow>(&Query::new(columns).bind(name.as_str()))
+            .await?;
+        let mut table = TableDef::new(&name);
+        for row in columns.iter() {
+            let mut column =
+                ColumnDef::new(&row.name, parse_type(&row.data_type)).nullable(!row.not_null);
+            if let Some(default) = row.default_value.as_deref().and_then(parse_default) {
+                column = column.default(default);
+            }
+            table = table.column(column);
+        }
+
+        let mut primary_key = columns
+            .iter()
+            .filter(|c| c.key_position > 0)
+            .collect::<Vec<&ColumnRow>>();
+        primary_key.sort_by_key(|c| c.key_position);
+        if !primary_key.is_empty() {
+            table = table.primary_key(
+                &primary_key
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<&str>>(),
+            );
+        }
+
+        // rows come ordered by index then position, one index per run of names
+        let mut index_rows: Vec<(String, Vec<IndexRow>)> = vec![];
+        for row in executor
+            .fetch_all::<IndexRow>(&Query::new(indexes).bind(name.as_str()))
+            .await?
+        {
+            match index_rows.last_mut() {
+                Some((index, rows)) if *index == row.name => rows.push(row),
+                _ => index_rows.push((row.name.clone(), vec![row])),
+            }
+        }
+        let mut index_defs = vec![];
+        for (index, rows) in index_rows {
+            index_defs.push(index_def(&index, &name, &rows, dialect)?);
+        }
+
+        let mut foreign_key_rows: Vec<(String, Vec<ForeignKeyRow>)> = vec![];
+        for row in executor
+            .fetch_all::<ForeignKeyRow>(&Query::new(foreign_keys).bind(name.as_str()))
+            .await?
+        {
+            match foreign_key_rows.last_mut() {
+                Some((id, rows)) if *id == row.id => rows.push(row),
+                _ => foreign_key_rows.push((row.id.clone(), vec![row])),
+            }
+        }
+        let foreign_key_defs = foreign_key_rows
+            .iter()
+            .map(|(_, rows)| {
+                let columns = rows
+                    .iter()
+                    .map(|r| r.column_name.as_str())
+                    .collect::<Vec<&str>>();
+                let references = rows
+                    .iter()
+                    .map(|r| r.referenced_column.as_deref().unwrap_or_default())
+                    .collect::<Vec<&str>>();
+                let foreign_key = ForeignKey::new(&columns, &rows[0].referenced_table, &references);
+                match parse_action(&rows[0].on_delete) {
+                    Some(action) => foreign_key.on_delete(action),
+                    None => foreign_key,
+                }
+            })
+            .collect();
+
+        schemas.push(TableSchema {
+            table,
+            indexes: index_defs,
+            foreign_keys: foreign_key_defs,
+        });
+    }
+
+    Ok(schemas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type() {
+        assert_eq!(parse_type("character varying(255)"), SqlType::Varchar(255));
+        assert_eq!(parse_type("character varying"), SqlType::Text);
+        assert_eq!(parse_type("numeric(10,2)"), SqlType::Numeric(10, 2));
+        assert_eq!(parse_type("NUMERIC(10, 2)"), SqlType::Numeric(10, 2));
+        assert_eq!(parse_type("numeric(10)"), SqlType::Numeric(10, 0));
+        assert_eq!(parse_type("numeric"), SqlType::UnboundedNumeric);
+        assert_eq!(parse_type("DECIMAL"), SqlType::UnboundedNumeric);
+        assert_eq!(
+            parse_type("timestamp without time zone"),
+            SqlType::Timestamp
+        );
+        assert_eq!(parse_type("double precision"), SqlType::Double);
+        assert_eq!(parse_type("REAL"), SqlType::Real);
+        assert_eq!(parse_type("DOUBLE"), SqlType::Double);
+        assert_eq!(parse_type("real"), SqlType::Real);
+
+        // no SqlType of their own
+        assert_eq!(parse_type("uuid"), SqlType::Text);
+        assert_eq!(parse_type("json"), SqlType::Text);
+        assert_eq!(parse_type("jsonb"), SqlType::Text);
+        assert_eq!(parse_type("time without time zone"), SqlType::Text);
+        assert_eq!(parse_type("character(3)"), SqlType::Text);
+        assert_eq!(parse_type(""), SqlType::Text);
+    }
+
+    #[test]
+    fn test_parse_default() {
+        assert_eq!(parse_default("0"), Some(SqlValue::I32(0)));
+        assert_eq!(parse_default("'-1'::integer"), Some(SqlValue::I32(-1)));
+        assert_eq!(parse_default("false"), Some(SqlValue::Bool(false)));
+        assert_eq!(parse_default("2.5"), Some(SqlValue::F64(2.5)));
+        assert_eq!(
+            parse_default("'O''Brien'::character varying"),
+            Some(SqlValue::from("O'Brien"))
+        );
+        assert_eq!(parse_default("'-1.5'::numeric"), Some(SqlValue::F64(-1.5)));
+        assert_eq!(
+            parse_default("'3000000000'::bigint"),
+            Some(SqlValue::I64(3000000000))
+        );
+        assert_eq!(
+            parse_default("'2024-01-01'::date"),
+            Some(SqlValue::StringDate("2024-01-01".to_owned()))
+        );
+        assert_eq!(
+            parse_default("'\\x8950'::bytea"),
+            Some(SqlValue::Bytes(vec![0x89, 0x50]))
+        );
+        assert_eq!(
+            parse_default("X'8950'"),
+            Some(SqlValue::Bytes(vec![0x89, 0x50]))
+        );
+        assert_eq!(parse_default("now()"), None);
+        assert_eq!(parse_default("nextval('users_id_seq'::regclass)"), None);
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let schema = TableSchema {
+            table: TableDef::new("order_lines")
+                .column(ColumnDef::new("id", SqlType::BigInt))
+                .column(ColumnDef::new("type", SqlType::Varchar(16)))
+                .column(ColumnDef::new("unitPrice", SqlType::Numeric(10, 2)).nullable(true))
+                .primary_key(&["id"]),
+            indexes: vec![{
+                let mut index = CreateIndex::new("order_lines_type_idx", "order_lines");
+                index.columns(&["type"]);
+                index
+            }],
+            foreign_keys: vec![
+                ForeignKey::new(&["id"], "orders", &["id"]).on_delete(ForeignKeyAction::Cascade)
+            ],
+        };
+        assert_eq!(
+            schema.to_rust().unwrap(),
+            "// CREATE INDEX order_lines_type_idx ON order_lines (type)
+// foreign key (id) references orders (id) on delete cascade
+#[derive(Debug, Table, Selectable, FromRow)]
+#[qrex(table = \"order_lines\")]
+pub struct OrderLines {
+    #[qrex(primary_key)]
+    pub id: i64,
+    #[qrex(rename = \"type\")]
+    pub type_: String,
+    #[qrex(rename = \"unitPrice\")]
+    pub unit_price: Option<f64>,
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_dialect() {
+        assert!(matches!(
+            catalog_queries(Dialect::MySql),
+            Err(QueryError::Unsupported(
+                "schema introspection",
+                Dialect::MySql
+            ))
+        ));
+    }
+
+    // the statements creating two related tables and what introspection should read back
+    #[cfg(any(feature = "sqlite", feature = "tokio-postgres"))]
+    fn fixture(dialect: Dialect) -> (Vec<Query>, Vec<TableSchema>) {
+        use crate::query_builder::ddl::CreateTable;
+
+        let customers = TableDef::new("customers")
+            .column(ColumnDef::new("id", SqlType::BigInt))
+            .column(ColumnDef::new("email", SqlType::Varchar(255)))
+            .column(ColumnDef::new("deleted_at", SqlType::Timestamp).nullable(true))
+            .primary_key(&["id"]);
+        let orders = TableDef::new("orders")
+            .column(ColumnDef::new("id", SqlType::BigInt))
+            .column(ColumnDef::new("customer_id", SqlType::BigInt))
+            .column(ColumnDef::new("total", SqlType::Numeric(10, 2)).default(0))
+            .column(ColumnDef::new("paid", SqlType::Boolean).default(false))
+            .primary_key(&["id"]);
+        let foreign_key = ForeignKey::new(&["customer_id"], "customers", &["id"])
+            .on_delete(ForeignKeyAction::Cascade);
+
+        let index = |predicate: Condition| {
+            let mut index = CreateIndex::new("customers_email_key", "customers");
+            index
+                .dialect(dialect)
+                .unique()
+                .columns(&["email"])
+                .r#where(predicate);
+            index
+        };
+        let lower = |expression: &str| {
+            let mut index = CreateIndex::new("customers_lower_email_idx", "customers");
+            index.dialect(dialect).expression(expression);
+            index
+        };
+
+        let statements = vec![
+            CreateTable::from_table(customers.clone())
+                .dialect(dialect)
+                .build_create()
+                .unwrap(),
+            CreateTable::from_table(orders.clone())
+                .dialect(dialect)
+                .foreign_key(foreign_key.clone())
+                .build_create()
+                .unwrap(),
+            index(Condition::Null("deleted_at".to_owned()))
+                .build_create_index()
+                .unwrap(),
+            lower("lower(email)").build_create_index().unwrap(),
+        ];
+
+        // predicates and expressions read back as the database prints them
+        let expression = match dialect {
+            Dialect::Postgres => "lower(email::text)",
+            _ => "lower(email)",
+        };
+        let schemas = vec![
+            TableSchema {
+                table: customers,
+                indexes: vec![
+                    index(Condition::Native("deleted_at IS NULL".to_owned())),
+                    lower(expression),
+                ],
+                foreign_keys: vec![],
+            },
+            TableSchema {
+                table: orders,
+                indexes: vec![],
+                foreign_keys: vec![foreign_key],
+            },
+        ];
+
+        (statements, schemas)
+    }
+
+    // a column of every type with a default, introspection has to read back what was declared
+    #[cfg(any(feature = "sqlite", feature = "tokio-postgres"))]
+    fn declared_types() -> TableDef {
+        TableDef::new("samples")
+            .column(ColumnDef::new("flag", SqlType::Boolean).default(true))
+            .column(ColumnDef::new("small", SqlType::SmallInt).default(-1))
+            .column(ColumnDef::new("count", SqlType::Integer).default(5))
+            .column(ColumnDef::new("big", SqlType::BigInt).default(3000000000i64))
+            .column(ColumnDef::new("ratio", SqlType::Real).default(-1.5f32))
+            .column(ColumnDef::new("precise", SqlType::Double).default(2.5))
+            .column(ColumnDef::new("amount", SqlType::Numeric(10, 2)).default(-1))
+            .column(ColumnDef::new("total", SqlType::UnboundedNumeric).nullable(true))
+            .column(ColumnDef::new("code", SqlType::Varchar(8)).default("O'Neil"))
+            .column(ColumnDef::new("body", SqlType::Text).nullable(true))
+            .column(ColumnDef::new("magic", SqlType::Blob).default(vec![0x89, 0x50]))
+            .column(ColumnDef::new("day", SqlType::Date).default("2024-01-01"))
+            .column(ColumnDef::new("at", SqlType::Timestamp).nullable(true))
+    }
+
+    #[cfg(any(feature = "sqlite", feature = "tokio-postgres"))]
+    async fn assert_round_trip<E: Executor>(executor: &E, dialect: Dialect) {
+        use crate::query_builder::{ddl::CreateTable, diff::diff_schema};
+
+        let create = CreateTable::from_table(declared_types())
+            .dialect(dialect)
+            .build_create()
+            .unwrap();
+        executor.execute(&create).await.unwrap();
+
+        let live = introspect(executor, dialect)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|schema| schema.table)
+            .filter(|table| table.get_name() == "samples")
+            .collect::<Vec<TableDef>>();
+        assert_eq!(diff_schema(&[declared_types()], &live), vec![]);
+    }
+
+    // columns of types with no SqlType read as Text rather than failing the table
+    #[cfg(any(feature = "sqlite", feature = "tokio-postgres"))]
+    async fn assert_loose_types<E: Executor>(executor: &E, dialect: Dialect, columns: &str) {
+        let create = format!(
+            "CREATE TABLE loose ({}, amount numeric(10), total numeric)",
+            columns
+        );
+        executor.execute(&Query::new(&create)).await.unwrap();
+
+        let live = introspect(executor, dialect)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|schema| schema.table.get_name() == "loose")
+            .unwrap();
+        let types = live
+            .table
+            .get_columns()
+            .iter()
+            .map(|column| column.get_sql_type())
+            .collect::<Vec<SqlType>>();
+        let mut expected = vec![SqlType::Text; types.len() - 2];
+        expected.extend([SqlType::Numeric(10, 0), SqlType::UnboundedNumeric]);
+        assert_eq!(types, expected);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_introspect_sqlite() {
+        let connection = std::sync::Mutex::new(rusqlite::Connection::open_in_memory().unwrap());
+        let (statements, schemas) = fixture(Dialect::Sqlite);
+        for statement in statements.iter() {
+            connection.execute(statement).await.unwrap();
+        }
+        assert_eq!(introspect(&connection, Dialect::Sqlite).await, Ok(schemas));
+        assert_round_trip(&connection, Dialect::Sqlite).await;
+        assert_loose_types(&connection, Dialect::Sqlite, "anything, id uuid, at time").await;
+    }
+
+    // needs a running server, run it with QREX_POSTGRES_URL set and cargo test -- --ignored
+    #[cfg(feature = "tokio-postgres")]
+    #[tokio::test]
+    #[ignore = "needs QREX_POSTGRES_URL"]
+    async fn test_introspect_postgres() {
+        let url = std::env::var("QREX_POSTGRES_URL").expect("QREX_POSTGRES_URL is not set");
+        let (client, connection) = tokio_postgres::connect(&url, tokio_postgres::NoTls)
+            .await
+            .unwrap();
+        tokio::spawn(connection);
+
+        let setup = [
+            "DROP SCHEMA IF EXISTS qrex_introspect CASCADE",
+            "CREATE SCHEMA qrex_introspect",
+            "SET search_path TO qrex_introspect",
+        ];
+        for sql in setup {
+            Executor::execute(&client, &Query::new(sql)).await.unwrap();
+        }
+        let (statements, schemas) = fixture(Dialect::Postgres);
+        for statement in statements.iter() {
+            Executor::execute(&client, statement).await.unwrap();
+        }
+        let result = introspect(&client, Dialect::Postgres).await;
+        assert_round_trip(&client, Dialect::Postgres).await;
+        let loose = "id uuid, doc json, data jsonb, at time, code char(3)";
+        assert_loose_types(&client, Dialect::Postgres, loose).await;
+        Executor::execute(&client, &Query::new("DROP SCHEMA qrex_introspect CASCADE"))
+            .await
+            .unwrap();
+        assert_eq!(result, Ok(schemas));
+    }
+}
@@ -1,15 +1,24 @@
-use super::condition::Condition;
+use super::{
+    condition::Condition, dialect::Dialect, error::QueryError, query::Query, value::SqlValue,
+};
 
 pub enum JoinType {
     Inner,
     Outer,
     Left,
     Right,
+    Cross,
+}
+
+pub enum JoinTarget {
+    Table(String, Option<String>), // Takes table name and optional alias
+    Subquery(Query, String),       // Takes derived table and its alias
 }
 
 pub struct JoinEntry {
-    table: String,
+    target: JoinTarget,
     join_type: JoinType,
+    lateral: bool,
     on: Option<Condition>,
 }
 
@@ -20,90 +29,322 @@ pub trait Join {
     fn get_join(&self) -> Vec<&JoinEntry>;
 
     fn inner_join(&mut self, table_to_join: &str, condition: Option<Condition>) {
-        self.set_join(|f| {
-            f.push(JoinEntry {
-                table: table_to_join.to_owned(),
-                join_type: JoinType::Inner,
-                on: condition, // TODO: me later
-            })
-        })
+        self.join_target(
+            JoinType::Inner,
+            JoinTarget::Table(table_to_join.to_owned(), None),
+            condition,
+        )
     }
 
     fn outer_join(&mut self, table_to_join: &str, condition: Option<Condition>) {
-        self.set_join(|f| {
-            f.push(JoinEntry {
-                table: table_to_join.to_owned(),
-                join_type: JoinType::Outer,
-                on: condition, // TODO: me later
-            })
-        })
+        self.join_target(
+            JoinType::Outer,
+            JoinTarget::Table(table_to_join.to_owned(), None),
+            condition,
+        )
     }
 
     fn left_join(&mut self, table_to_join: &str, condition: Option<Condition>) {
+        self.join_target(
+            JoinType::Left,
+            JoinTarget::Table(table_to_join.to_owned(), None),
+            condition,
+        )
+    }
+
+    fn right_join(&mut self, table_to_join: &str, condition: Option<Condition>) {
+        self.join_target(
+            JoinType::Right,
+            JoinTarget::Table(table_to_join.to_owned(), None),
+            condition,
+        )
+    }
+
+    fn cross_join(&mut self, table_to_join: &str) {
+        self.join_target(
+            JoinType::Cross,
+            JoinTarget::Table(table_to_join.to_owned(), None),
+            None,
+        )
+    }
+
+    fn join_as(
+        &mut self,
+        join_type: JoinType,
+        table_to_join: &str,
+        alias: &str,
+        condition: Option<Condition>,
+    ) {
+        self.join_target(
+            join_type,
+            JoinTarget::Table(table_to_join.to_owned(), Some(alias.to_owned())),
+            condition,
+        )
+    }
+
+    fn join_subquery(
+        &mut self,
+        join_type: JoinType,
+        subquery: Query,
+        alias: &str,
+        condition: Option<Condition>,
+    ) {
+        self.join_target(
+            join_type,
+            JoinTarget::Subquery(subquery, alias.to_owned()),
+            condition,
+        )
+    }
+
+    // lateral subquery sees the tables before it, no condition renders as ON TRUE
+    fn join_lateral(
+        &mut self,
+        join_type: JoinType,
+        subquery: Query,
+        alias: &str,
+        condition: Option<Condition>,
+    ) {
         self.set_join(|f| {
             f.push(JoinEntry {
-                table: table_to_join.to_owned(),
-                join_type: JoinType::Left,
-                on: condition, // TODO: me later
+                target: JoinTarget::Subquery(subquery, alias.to_owned()),
+                join_type,
+                lateral: true,
+                on: condition,
             })
         })
     }
 
-    fn right_join(&mut self, table_to_join: &str, condition: Option<Condition>) {
+    fn join_target(
+        &mut self,
+        join_type: JoinType,
+        target: JoinTarget,
+        condition: Option<Condition>,
+    ) {
         self.set_join(|f| {
             f.push(JoinEntry {
-                table: table_to_join.to_owned(),
-                join_type: JoinType::Right,
-                on: condition, // TODO: me later
+                target,
+                join_type,
+                lateral: false,
+                on: condition,
             })
         })
     }
 
-    fn build_join(&self) -> Option<String> {
+    // the params of derived tables are appended to params in the order they are rendered
+    fn build_join(
+        &self,
+        dialect: Dialect,
+        params: &mut Vec<SqlValue>,
+    ) -> Result<Option<String>, QueryError> {
         let joins = self.get_join();
         if joins.is_empty() {
-            return None;
+            return Ok(None);
         }
 
         let join_strings = joins
             .iter()
-            .map(|f| match f.join_type {
-                JoinType::Left => format!(
-                    "LEFT JOIN {} {}",
-                    f.table,
-                    f.on.as_ref().map_or_else(
-                        || "".to_owned(),
-                        |d| d.build_conditions().unwrap_or("".to_owned())
-                    )
-                ),
-                JoinType::Right => format!(
-                    "RIGHT JOIN {} {}",
-                    f.table,
-                    f.on.as_ref().map_or_else(
-                        || "".to_owned(),
-                        |d| d.build_conditions().unwrap_or("".to_owned())
-                    )
-                ),
-                JoinType::Inner => format!(
-                    "INNER JOIN {} {}",
-                    f.table,
-                    f.on.as_ref().map_or_else(
-                        || "".to_owned(),
-                        |d| d.build_conditions().unwrap_or("".to_owned())
-                    )
-                ),
-                JoinType::Outer => format!(
-                    "OUTER JOIN {} {}",
-                    f.table,
-                    f.on.as_ref().map_or_else(
-                        || "".to_owned(),
-                        |d| d.build_conditions().unwrap_or("".to_owned())
-                    )
-                ),
-            })
-            .collect::<Vec<String>>()
+            .map(|f| f.build(dialect, params))
+            .collect::<Result<Vec<String>, QueryError>>()?
             .join(" ");
 
-        Some(join_strings)
+        Ok(Some(join_strings))
+    }
+}
+
+impl JoinEntry {
+    fn build(&self, dialect: Dialect, params: &mut Vec<SqlValue>) -> Result<String, QueryError> {
+        let target = match &self.target {
+            JoinTarget::Table(table, None) => table.clone(),
+            JoinTarget::Table(table, Some(alias)) => format!("{} AS {}", table, alias),
+            JoinTarget::Subquery(query, alias) => {
+                format!("({}) AS {}", query.embed(dialect, params), alias)
+            }
+        };
+
+        let on = match &self.on {
+            Some(c) => c.for_dialect(dialect)?.build_conditions(),
+            None => None,
+        };
+
+        if !self.lateral {
+            return Ok(match (&self.join_type, on) {
+                // a cross join takes no ON, with a condition it is an inner join
+                (JoinType::Cross, Some(on)) => format!("INNER JOIN {} ON {}", target, on),
+                (_, Some(on)) => format!("{} {} ON {}", self.keyword(), target, on),
+                (_, None) => format!("{} {}", self.keyword(), target),
+            });
+        }
+
+        match (dialect, &self.join_type, on) {
+            (Dialect::Postgres | Dialect::MySql, JoinType::Cross, None) => {
+                Ok(format!("CROSS JOIN LATERAL {}", target))
+            }
+            (Dialect::Postgres | Dialect::MySql, JoinType::Cross, Some(on)) => {
+                Ok(format!("INNER JOIN LATERAL {} ON {}", target, on))
+            }
+            (Dialect::Postgres | Dialect::MySql, JoinType::Inner | JoinType::Left, on) => {
+                Ok(format!(
+                    "{} LATERAL {} ON {}",
+                    self.keyword(),
+                    target,
+                    on.unwrap_or("TRUE".to_owned())
+                ))
+            }
+            (Dialect::SqlServer, JoinType::Inner | JoinType::Cross, None) => {
+                Ok(format!("CROSS APPLY {}", target))
+            }
+            (Dialect::SqlServer, JoinType::Left, None) => Ok(format!("OUTER APPLY {}", target)),
+            _ => Err(QueryError::Unsupported("LATERAL join", dialect)),
+        }
+    }
+
+    fn keyword(&self) -> &'static str {
+        match self.join_type {
+            JoinType::Left => "LEFT JOIN",
+            JoinType::Right => "RIGHT JOIN",
+            JoinType::Inner => "INNER JOIN",
+            JoinType::Outer => "OUTER JOIN",
+            JoinType::Cross => "CROSS JOIN",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::value::SqlValue;
+
+    #[derive(Default)]
+    struct JoinTest {
+        joins: Vec<JoinEntry>,
+    }
+
+    impl Join for JoinTest {
+        fn set_join(&mut self, f: impl FnOnce(&mut Vec<JoinEntry>)) {
+            f(&mut self.joins)
+        }
+
+        fn get_join(&self) -> Vec<&JoinEntry> {
+            self.joins.iter().collect()
+        }
+    }
+
+    fn on(lhs: &str, rhs: &str) -> Option<Condition> {
+        Some(Condition::Native(format!("{} = {}", lhs, rhs)))
+    }
+
+    #[test]
+    fn test_no_join() {
+        let join = JoinTest::default();
+        assert_eq!(join.build_join(Dialect::Postgres, &mut vec![]), Ok(None));
+    }
+
+    #[test]
+    fn test_plain_join_renders_on() {
+        let mut join = JoinTest::default();
+        join.inner_join("orders", on("orders.user_id", "users.id"));
+        join.left_join("invoices", None);
+        assert_eq!(
+            join.build_join(Dialect::Postgres, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            "INNER JOIN orders ON orders.user_id = users.id LEFT JOIN invoices"
+        );
+    }
+
+    #[test]
+    fn test_self_join_with_alias() {
+        let mut join = JoinTest::default();
+        join.join_as(JoinType::Left, "users", "m", on("m.id", "u.manager_id"));
+        assert_eq!(
+            join.build_join(Dialect::Sqlite, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            "LEFT JOIN users AS m ON m.id = u.manager_id"
+        );
+    }
+
+    #[test]
+    fn test_join_derived_table() {
+        let mut join = JoinTest::default();
+        join.join_subquery(
+            JoinType::Inner,
+            Query::new("SELECT user_id, COUNT(*) AS total FROM orders GROUP BY user_id"),
+            "o",
+            Some(
+                Condition::Native("o.user_id = u.id".to_owned())
+                    .and(Condition::Gt("o.total".to_owned(), SqlValue::Int(5))),
+            ),
+        );
+        assert_eq!(
+            join.build_join(Dialect::MySql, &mut vec![]).unwrap().unwrap(),
+            "INNER JOIN (SELECT user_id, COUNT(*) AS total FROM orders GROUP BY user_id) AS o ON (o.user_id = u.id AND o.total > 5)"
+        );
+    }
+
+    #[test]
+    fn test_lateral_join() {
+        let mut join = JoinTest::default();
+        join.join_lateral(
+            JoinType::Left,
+            Query::new("SELECT * FROM orders WHERE orders.user_id = u.id LIMIT 3"),
+            "recent",
+            None,
+        );
+        let expected =
+            "LEFT JOIN LATERAL (SELECT * FROM orders WHERE orders.user_id = u.id LIMIT 3) AS recent ON TRUE";
+        assert_eq!(
+            join.build_join(Dialect::Postgres, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            join.build_join(Dialect::MySql, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_cross_join() {
+        let mut join = JoinTest::default();
+        join.cross_join("sizes");
+        join.join_target(
+            JoinType::Cross,
+            JoinTarget::Table("colors".to_owned(), None),
+            on("colors.id", "sizes.color_id"),
+        );
+        join.join_lateral(JoinType::Cross, Query::new("SELECT 1 AS one"), "x", None);
+        assert_eq!(
+            join.build_join(Dialect::Postgres, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            "CROSS JOIN sizes INNER JOIN colors ON colors.id = sizes.color_id \
+             CROSS JOIN LATERAL (SELECT 1 AS one) AS x"
+        );
+    }
+
+    #[test]
+    fn test_lateral_join_as_apply_on_sql_server() {
+        let mut join = JoinTest::default();
+        join.join_lateral(JoinType::Inner, Query::new("SELECT 1 AS one"), "x", None);
+        assert_eq!(
+            join.build_join(Dialect::SqlServer, &mut vec![])
+                .unwrap()
+                .unwrap(),
+            "CROSS APPLY (SELECT 1 AS one) AS x"
+        );
+    }
+
+    #[test]
+    fn test_lateral_join_unsupported() {
+        let mut join = JoinTest::default();
+        join.join_lateral(JoinType::Inner, Query::new("SELECT 1 AS one"), "x", None);
+        assert_eq!(
+            join.build_join(Dialect::Sqlite, &mut vec![]),
+            Err(QueryError::Unsupported("LATERAL join", Dialect::Sqlite))
+        );
     }
 }
@@ -0,0 +1,212 @@
+use super::{dialect::Dialect, error::QueryError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockStrength {
+    Update,
+    NoKeyUpdate,
+    Share,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockWait {
+    NoWait,
+    SkipLocked,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowLock {
+    strength: LockStrength,
+    of: Vec<String>, // Tables whose rows are locked, all of them when empty
+    wait: Option<LockWait>,
+}
+
+impl RowLock {
+    fn new(strength: LockStrength) -> Self {
+        Self {
+            strength,
+            of: vec![],
+            wait: None,
+        }
+    }
+
+    fn build(&self, dialect: Dialect) -> Result<String, QueryError> {
+        let strength = match (dialect, self.strength) {
+            (Dialect::Sqlite, _) => return Err(QueryError::Unsupported("row locking", dialect)),
+            (Dialect::SqlServer, _) => return self.build_hints(dialect),
+            (Dialect::MySql, LockStrength::NoKeyUpdate) => {
+                return Err(QueryError::Unsupported("FOR NO KEY UPDATE", dialect))
+            }
+            (_, LockStrength::Update) => "FOR UPDATE",
+            (_, LockStrength::NoKeyUpdate) => "FOR NO KEY UPDATE",
+            (_, LockStrength::Share) => "FOR SHARE",
+        };
+
+        let mut lock = strength.to_owned();
+
+        if !self.of.is_empty() {
+            lock.push_str(&format!(" OF {}", self.of.join(", ")));
+        }
+
+        match self.wait {
+            Some(LockWait::NoWait) => lock.push_str(" NOWAIT"),
+            Some(LockWait::SkipLocked) => lock.push_str(" SKIP LOCKED"),
+            None => {}
+        }
+
+        Ok(lock)
+    }
+
+    // sql server locks through hints on the table it reads from
+    fn build_hints(&self, dialect: Dialect) -> Result<String, QueryError> {
+        if !self.of.is_empty() {
+            return Err(QueryError::Unsupported("FOR UPDATE OF", dialect));
+        }
+
+        let mut hints = match self.strength {
+            LockStrength::Update => vec!["UPDLOCK", "ROWLOCK"],
+            LockStrength::NoKeyUpdate => {
+                return Err(QueryError::Unsupported("FOR NO KEY UPDATE", dialect))
+            }
+            LockStrength::Share => vec!["HOLDLOCK", "ROWLOCK"],
+        };
+
+        match self.wait {
+            Some(LockWait::NoWait) => hints.push("NOWAIT"),
+            Some(LockWait::SkipLocked) => hints.push("READPAST"),
+            None => {}
+        }
+
+        Ok(format!("WITH ({})", hints.join(", ")))
+    }
+}
+
+pub trait Lock {
+    fn get_lock(&self) -> Option<&RowLock>;
+    fn set_lock(&mut self, f: impl FnOnce(&mut Option<RowLock>));
+
+    fn for_update(&mut self) -> &mut Self {
+        self.set_lock(|l| *l = Some(RowLock::new(LockStrength::Update)));
+        self
+    }
+
+    fn for_no_key_update(&mut self) -> &mut Self {
+        self.set_lock(|l| *l = Some(RowLock::new(LockStrength::NoKeyUpdate)));
+        self
+    }
+
+    fn for_share(&mut self) -> &mut Self {
+        self.set_lock(|l| *l = Some(RowLock::new(LockStrength::Share)));
+        self
+    }
+
+    // of, nowait and skip_locked refine the lock set by for_update, for_no_key_update or for_share
+    fn of(&mut self, tables: &[&str]) -> &mut Self {
+        self.set_lock(|l| {
+            if let Some(lock) = l {
+                lock.of.extend(tables.iter().map(|&t| t.to_owned()))
+            }
+        });
+        self
+    }
+
+    fn nowait(&mut self) -> &mut Self {
+        self.set_lock(|l| {
+            if let Some(lock) = l {
+                lock.wait = Some(LockWait::NoWait)
+            }
+        });
+        self
+    }
+
+    fn skip_locked(&mut self) -> &mut Self {
+        self.set_lock(|l| {
+            if let Some(lock) = l {
+                lock.wait = Some(LockWait::SkipLocked)
+            }
+        });
+        self
+    }
+
+    // a trailing clause, or the table hint on sql server
+    fn build_lock(&self, dialect: Dialect) -> Result<Option<String>, QueryError> {
+        self.get_lock().map(|l| l.build(dialect)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct LockTest {
+        lock: Option<RowLock>,
+    }
+
+    impl Lock for LockTest {
+        fn get_lock(&self) -> Option<&RowLock> {
+            self.lock.as_ref()
+        }
+
+        fn set_lock(&mut self, f: impl FnOnce(&mut Option<RowLock>)) {
+            f(&mut self.lock)
+        }
+    }
+
+    #[test]
+    fn test_no_lock() {
+        let mut lock = LockTest::default();
+        assert_eq!(lock.build_lock(Dialect::Sqlite), Ok(None));
+        lock.skip_locked();
+        assert_eq!(lock.build_lock(Dialect::Postgres), Ok(None));
+    }
+
+    #[test]
+    fn test_for_update_skip_locked() {
+        let mut lock = LockTest::default();
+        lock.for_update().skip_locked();
+        assert_eq!(
+            lock.build_lock(Dialect::Postgres).unwrap().unwrap(),
+            "FOR UPDATE SKIP LOCKED"
+        );
+        assert_eq!(
+            lock.build_lock(Dialect::MySql).unwrap().unwrap(),
+            "FOR UPDATE SKIP LOCKED"
+        );
+        assert_eq!(
+            lock.build_lock(Dialect::SqlServer).unwrap().unwrap(),
+            "WITH (UPDLOCK, ROWLOCK, READPAST)"
+        );
+        assert_eq!(
+            lock.build_lock(Dialect::Sqlite),
+            Err(QueryError::Unsupported("row locking", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_lock_of_tables() {
+        let mut lock = LockTest::default();
+        lock.for_share().of(&["orders", "customers"]).nowait();
+        assert_eq!(
+            lock.build_lock(Dialect::Postgres).unwrap().unwrap(),
+            "FOR SHARE OF orders, customers NOWAIT"
+        );
+        assert_eq!(
+            lock.build_lock(Dialect::SqlServer),
+            Err(QueryError::Unsupported("FOR UPDATE OF", Dialect::SqlServer))
+        );
+    }
+
+    #[test]
+    fn test_for_no_key_update() {
+        let mut lock = LockTest::default();
+        lock.for_no_key_update();
+        assert_eq!(
+            lock.build_lock(Dialect::Postgres).unwrap().unwrap(),
+            "FOR NO KEY UPDATE"
+        );
+        assert_eq!(
+            lock.build_lock(Dialect::MySql),
+            Err(QueryError::Unsupported("FOR NO KEY UPDATE", Dialect::MySql))
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use super::{
+    condition::Condition,
+    ddl::CreateTable,
+    delete::Delete,
+    dialect::Dialect,
+    error::QueryError,
+    executor::{transaction, Connection, Executor, SingleConnection},
+    insert::Insert,
+    query::Query,
+    row::{FromRow, Row},
+    schema::{ColumnDef, SqlType},
+    transaction::Begin,
+    value::SqlValue,
+};
+
+#[derive(Clone, Debug)]
+pub struct Migration {
+    version: i64,
+    name: String,
+    up: Vec<Query>,
+    down: Vec<Query>,
+}
+
+impl Migration {
+    pub fn new(version: i64, name: &str) -> Self {
+        Self {
+            version,
+            name: name.to_owned(),
+            up: vec![],
+            down: vec![],
+        }
+    }
+
+    pub fn up(mut self, query: Query) -> Self {
+        self.up.push(query);
+        self
+    }
+
+    // run in the order given, a migration without down statements cannot be rolled back
+    pub fn down(mut self, query: Query) -> Self {
+        self.down.push(query);
+        self
+    }
+
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // fnv-1a over the up then the down statements, DefaultHasher is not stable across rust
+    // releases. a NUL starts each down statement so none can pass for an up one
+    pub fn checksum(&self) -> String {
+        let up = self.up.iter().flat_map(|q| q.sql().bytes().chain([b';']));
+        let down = self
+            .down
+            .iter()
+            .flat_map(|q| [0].into_iter().chain(q.sql().bytes()).chain([b';']));
+
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in up.chain(down) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MigrationState {
+    Pending,
+    Applied,
+    Changed, // Applied, but its statements were edited since
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationStatus {
+    version: i64,
+    name: String,
+    state: MigrationState,
+}
+
+impl MigrationStatus {
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> MigrationState {
+        self.state
+    }
+}
+
+struct AppliedMigration {
+    version: i64,
+    checksum: String,
+}
+
+struct Found {
+    found: bool,
+}
+
+impl FromRow for Found {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            found: row.try_get("found")?,
+        })
+    }
+}
+
+impl FromRow for AppliedMigration {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+        Ok(Self {
+            version: row.try_get("version")?,
+            checksum: row.try_get("checksum")?,
+        })
+    }
+}
+
+// each migration runs in a transaction on the connection, whose dialect the statements use
+pub struct Migrator {
+    migrations: Vec<Migration>,
+    table: String, // Bookkeeping table holding the applied versions and their checksums
+}
+
+impl Default for Migrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrator {
+    pub fn new() -> Self {
+        Self {
+            migrations: vec![],
+            table: "qrex_migrations".to_owned(),
+        }
+    }
+
+    pub fn table(&mut self, table: &str) -> &mut Self {
+        self.table = table.to_owned();
+        self
+    }
+
+    pub fn migration(&mut self, migration: Migration) -> &mut Self {
+        self.migrations.push(migration);
+        self
+    }
+
+    // sorted by version, whatever order they were declared in
+    fn migrations(&self) -> Result<Vec<&Migration>, QueryError> {
+        let mut migrations = self.migrations.iter().collect::<Vec<&Migration>>();
+        migrations.sort_by_key(|m| m.version);
+
+        match migrations.windows(2).find(|w| w[0].version == w[1].version) {
+            Some(w) => Err(QueryError::DuplicateMigration(w[0].version)),
+            None => Ok(migrations),
+        }
+    }
+
+    fn build_bookkeeping(&self, dialect: Dialect) -> Result<Query, QueryError> {
+        let mut create = CreateTable::new(&self.table);
+        create
+            .dialect(dialect)
+            .column(ColumnDef::new("version", SqlType::BigInt))
+            .column(ColumnDef::new("name", SqlType::Varchar(255)))
+            .column(ColumnDef::new("checksum", SqlType::Varchar(16)))
+            .primary_key(&["version"]);
+
+        match dialect {
+            Dialect::SqlServer => Ok(Query::new(format!(
+                "IF OBJECT_ID(N'{}', N'U') IS NULL {}",
+                self.table,
+                create.build_create()?.sql()
+            ))),
+            _ => create.if_not_exists().build_create(),
+        }
+    }
+
+    fn build_exists(&self, dialect: Dialect) -> Query {
+        let sql = match dialect {
+            Dialect::Postgres => "SELECT to_regclass($1) IS NOT NULL AS found",
+            Dialect::Sqlite => {
+                "SELECT COUNT(*) AS found FROM sqlite_master WHERE type = 'table' AND name = ?"
+            }
+            Dialect::MySql => {
+                "SELECT COUNT(*) AS found FROM information_schema.tables \
+                 WHERE table_schema = DATABASE() AND table_name = ?"
+            }
+            Dialect::SqlServer => {
+                "SELECT CASE WHEN OBJECT_ID(@p1, N'U') IS NULL THEN 0 ELSE 1 END AS found"
+            }
+        };
+        Query::new(sql).bind(self.table.as_str())
+    }
+
+    // read only, no bookkeeping table means nothing was applied yet
+    async fn applied<E: SingleConnection>(
+        &self,
+        connection: &Connection<E>,
+    ) -> Result<Vec<AppliedMigration>, QueryError> {
+        let exists = connection
+            .fetch_one::<Found>(&self.build_exists(connection.dialect()))
+            .await?;
+        if !exists.found {
+            return Ok(vec![]);
+        }
+
+        connection
+            .fetch_all(&Query::new(format!(
+                "SELECT version, checksum FROM {} ORDER BY version",
+                self.table
+            )))
+            .await
+    }
+
+    // statements and their bookkeeping row commit together, mysql commits each DDL statement anyway
+    async fn run<E: SingleConnection>(
+        &self,
+        connection: &Connection<E>,
+        statements: &[Query],
+        bookkeeping: Query,
+    ) -> Result<(), QueryError> {
+        transaction(connection, Begin::default(), async |connection| {
+            for query in statements.iter().chain([&bookkeeping]) {
+                connection.execute(query).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn status<E: SingleConnection>(
+        &self,
+        connection: &Connection<E>,
+    ) -> Result<Vec<MigrationStatus>, QueryError> {
+        let migrations = self.migrations()?;
+        let applied = self.applied(connection).await?;
+
+        if let Some(unknown) = applied
+            .iter()
+            .find(|a| migrations.iter().all(|m| m.version != a.version))
+        {
+            return Err(QueryError::UnknownMigration(unknown.version));
+        }
+
+        Ok(migrations
+            .iter()
+            .map(|m| {
+                let state = match applied.iter().find(|a| a.version == m.version) {
+                    Some(a) if a.checksum == m.checksum() => MigrationState::Applied,
+                    Some(_) => MigrationState::Changed,
+                    None => MigrationState::Pending,
+                };
+                MigrationStatus {
+                    version: m.version,
+                    name: m.name.clone(),
+                    state,
+                }
+            })
+            .collect())
+    }
+
+    // runs every pending migration in version order and returns their versions, creating
+    // the bookkeeping table first
+    pub async fn apply<E: SingleConnection>(
+        &self,
+        connection: &Connection<E>,
+    ) -> Result<Vec<i64>, QueryError> {
+        let status = self.status(connection).await?;
+
+        if status.iter().any(|s| s.state == MigrationState::Pending) {
+            connection
+                .execute(&self.build_bookkeeping(connection.dialect())?)
+                .await?;
+        }
+
+        if let Some(changed) = status.iter().find(|s| s.state == MigrationState::Changed) {
+            return Err(QueryError::ChecksumMismatch(changed.version));
+        }
+
+        let mut versions = vec![];
+        for migration in self.migrations()? {
+            if status
+                .iter()
+                .any(|s| s.version == migration.version && s.state == MigrationState::Applied)
+            {
+                continue;
+            }
+
+            let mut insert = Insert::new(&self.table);
+            insert
+                .dialect(connection.dialect())
+                .columns(&["version", "name", "checksum"])
+                .values(vec![
+                    migration.version.into(),
+                    migration.name.as_str().into(),
+                    migration.checksum().as_str().into(),
+                ]);
+            self.run(connection, &migration.up, insert.build_insert()?)
+                .await?;
+            versions.push(migration.version);
+        }
+
+        Ok(versions)
+    }
+
+    // reverts the latest applied migration, None when nothing is applied
+    pub async fn rollback<E: SingleConnection>(
+        &self,
+        connection: &Connection<E>,
+    ) -> Result<Option<i64>, QueryError> {
+        let status = self.status(connection).await?;
+
+        let Some(latest) = status
+            .iter()
+            .rev()
+            .find(|s| s.state != MigrationState::Pending)
+        else {
+            return Ok(None);
+        };
+
+        let migration = self
+            .migrations
+            .iter()
+            .find(|m| m.version == latest.version)
+            .ok_or(QueryError::UnknownMigration(latest.version))?;
+
+        if migration.down.is_empty() {
+            return Err(QueryError::IrreversibleMigration(migration.version));
+        }
+
+        let mut delete = Delete::new(&self.table);
+        delete.dialect(connection.dialect()).r#where(Condition::Eq(
+            "version".to_owned(),
+            SqlValue::from(migration.version),
+        ));
+        self.run(connection, &migration.down, delete.build_delete()?)
+            .await?;
+
+        Ok(Some(migration.version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users() -> Migration {
+        Migration::new(1, "create users")
+            .up(Query::new(
+                "CREATE TABLE users (id INTEGER NOT NULL, PRIMARY KEY (id))",
+            ))
+            .down(Query::new("DROP TABLE users"))
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(users().checksum(), users().checksum());
+        assert_ne!(
+            users().checksum(),
+            users()
+                .up(Query::new("CREATE INDEX users_id_idx ON users (id)"))
+                .checksum()
+        );
+        assert_eq!(Migration::new(1, "empty").checksum(), "cbf29ce484222325");
+
+        // the down statements count, and cannot be mistaken for up ones
+        assert_ne!(
+            users().checksum(),
+            users()
+                .down(Query::new("DROP INDEX users_id_idx"))
+                .checksum()
+        );
+        let up = Migration::new(1, "users").up(Query::new("CREATE TABLE users (id INTEGER)"));
+        assert_ne!(
+            up.clone().up(Query::new("DROP TABLE users")).checksum(),
+            up.down(Query::new("DROP TABLE users")).checksum()
+        );
+    }
+
+    #[test]
+    fn test_bookkeeping_table() {
+        let mut migrator = Migrator::new();
+        assert_eq!(
+            migrator
+                .build_bookkeeping(Dialect::Postgres)
+                .unwrap()
+                .sql(),
+            "CREATE TABLE IF NOT EXISTS qrex_migrations (version BIGINT NOT NULL, name VARCHAR(255) NOT NULL, checksum VARCHAR(16) NOT NULL, PRIMARY KEY (version))"
+        );
+        assert_eq!(
+            migrator
+                .table("schema_versions")
+                .build_bookkeeping(Dialect::SqlServer)
+                .unwrap()
+                .sql(),
+            "IF OBJECT_ID(N'schema_versions', N'U') IS NULL CREATE TABLE schema_versions (version BIGINT NOT NULL, name NVARCHAR(255) NOT NULL, checksum NVARCHAR(16) NOT NULL, PRIMARY KEY (version))"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_migration() {
+        let mut migrator = Migrator::new();
+        migrator
+            .migration(users())
+            .migration(Migration::new(1, "create posts"));
+        assert!(matches!(
+            migrator.migrations(),
+            Err(QueryError::DuplicateMigration(1))
+        ));
+    }
+
+    #[cfg(feature = "sqlite")]
+    mod sqlite {
+        use std::sync::Mutex;
+
+        use super::*;
+
+        fn connection() -> Connection<Mutex<rusqlite::Connection>> {
+            Connection::new(
+                Mutex::new(rusqlite::Connection::open_in_memory().unwrap()),
+                Dialect::Sqlite,
+            )
+        }
+
+        fn migrator() -> Migrator {
+            let mut migrator = Migrator::new();
+            migrator
+                .migration(
+                    Migration::new(2, "add users email")
+                        .up(Query::new("ALTER TABLE users ADD COLUMN email TEXT"))
+                        .down(Query::new("ALTER TABLE users DROP COLUMN email")),
+                )
+                .migration(users());
+            migrator
+        }
+
+        fn states(status: Vec<MigrationStatus>) -> Vec<(i64, MigrationState)> {
+            status.iter().map(|s| (s.version(), s.state())).collect()
+        }
+
+        #[tokio::test]
+        async fn test_apply_and_rollback() {
+            let connection = connection();
+            let migrator = migrator();
+            assert_eq!(
+                states(migrator.status(&connection).await.unwrap()),
+                vec![(1, MigrationState::Pending), (2, MigrationState::Pending)]
+            );
+            // status leaves the database alone
+            let exists = connection
+                .fetch_one::<Found>(&migrator.build_exists(Dialect::Sqlite))
+                .await;
+            assert!(matches!(exists, Ok(Found { found: false })));
+
+            assert_eq!(migrator.apply(&connection).await.unwrap(), vec![1, 2]);
+            assert!(migrator.apply(&connection).await.unwrap().is_empty());
+            connection
+                .execute(&Query::new(
+                    "INSERT INTO users (id, email) VALUES (1, 'ann@example.com')",
+                ))
+                .await
+                .unwrap();
+
+            assert_eq!(migrator.rollback(&connection).await.unwrap(), Some(2));
+            assert_eq!(
+                states(migrator.status(&connection).await.unwrap()),
+                vec![(1, MigrationState::Applied), (2, MigrationState::Pending)]
+            );
+            assert_eq!(migrator.rollback(&connection).await.unwrap(), Some(1));
+            assert_eq!(migrator.rollback(&connection).await.unwrap(), None);
+        }
+
+        #[tokio::test]
+        async fn test_failed_migration_rolls_back() {
+            let connection = connection();
+            let mut migrator = migrator();
+            migrator.migration(
+                Migration::new(3, "broken")
+                    .up(Query::new("CREATE TABLE posts (id INTEGER)"))
+                    .up(Query::new("CREATE TABLE posts (id INTEGER)")),
+            );
+            assert!(matches!(
+                migrator.apply(&connection).await,
+                Err(QueryError::Driver(_))
+            ));
+            assert_eq!(
+                states(migrator.status(&connection).await.unwrap()),
+                vec![
+                    (1, MigrationState::Applied),
+                    (2, MigrationState::Applied),
+                    (3, MigrationState::Pending)
+                ]
+            );
+            assert!(matches!(
+                connection.execute(&Query::new("DROP TABLE posts")).await,
+                Err(QueryError::Driver(_))
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_changed_and_unknown_migrations() {
+            let connection = connection();
+            migrator().apply(&connection).await.unwrap();
+
+            let mut changed = Migrator::new();
+            changed
+                .migration(users().up(Query::new("CREATE INDEX users_id_idx ON users (id)")))
+                .migration(Migration::new(2, "add users email"));
+            assert!(matches!(
+                changed.apply(&connection).await,
+                Err(QueryError::ChecksumMismatch(1))
+            ));
+            assert!(matches!(
+                changed.rollback(&connection).await,
+                Err(QueryError::IrreversibleMigration(2))
+            ));
+
+            let mut unknown = Migrator::new();
+            unknown.migration(users());
+            assert!(matches!(
+                unknown.status(&connection).await,
+                Err(QueryError::UnknownMigration(2))
+            ));
+        }
+    }
+}
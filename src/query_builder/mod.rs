@@ -3,14 +3,35 @@
 * TODO: support GEOMITRY, GEOGRAPHY []
 * TODO: support INTERVAL []
 * FIX: parse types in conditions preperly strings '' for example [x]
-* TODO: add procedural macro to select based on struct
+* TODO: add procedural macro to select based on struct [x]
 * TODO: add support for parsing bytes sql value
 * TODO: add support for native time
 * */
-mod condition;
-mod group_by;
-mod join;
-mod order_by;
-mod query;
-mod select;
-mod value;
+pub mod compound;
+pub mod condition;
+pub mod ddl;
+pub mod delete;
+pub mod dialect;
+pub mod diff;
+pub mod error;
+pub mod executor;
+pub mod group_by;
+pub mod insert;
+pub mod introspect;
+pub mod join;
+pub mod lock;
+pub mod migration;
+pub mod order_by;
+pub mod parser;
+pub mod query;
+pub mod returning;
+pub mod row;
+pub mod schema;
+pub mod select;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod transaction;
+pub mod update;
+pub mod value;
+pub mod window;
+pub mod with;
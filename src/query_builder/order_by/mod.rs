@@ -1,56 +1,351 @@
+use std::fmt;
+
+use super::{dialect::Dialect, error::QueryError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Order {
     Asc,
     Desc,
 }
 
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Order::Asc => write!(f, "ASC"),
+            Order::Desc => write!(f, "DESC"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+#[derive(Clone, Debug)]
+pub struct OrderEntry {
+    key: String,          // Column, row position, expression or raw clause
+    order: Option<Order>, // None for raw clauses which carry their own direction
+    nulls: Option<Nulls>,
+    collation: Option<String>,
+}
+
+impl OrderEntry {
+    fn new(key: String, order: Order) -> Self {
+        Self {
+            key,
+            order: Some(order),
+            nulls: None,
+            collation: None,
+        }
+    }
+
+    fn build(&self, dialect: Dialect) -> String {
+        let order = match self.order {
+            Some(order) => order,
+            None => return self.key.clone(),
+        };
+
+        let key = match &self.collation {
+            Some(collation) => format!("{} COLLATE {}", self.key, collation),
+            None => self.key.clone(),
+        };
+
+        let nulls = match self.nulls {
+            Some(nulls) => nulls,
+            None => return format!("{} {}", key, order),
+        };
+
+        // mysql and sql server have no NULLS FIRST/LAST, a leading null test sorts them instead
+        match (dialect, nulls) {
+            (Dialect::Postgres | Dialect::Sqlite, Nulls::First) => {
+                format!("{} {} NULLS FIRST", key, order)
+            }
+            (Dialect::Postgres | Dialect::Sqlite, Nulls::Last) => {
+                format!("{} {} NULLS LAST", key, order)
+            }
+            (Dialect::MySql, Nulls::First) => {
+                format!("{} IS NULL DESC, {} {}", self.key, key, order)
+            }
+            (Dialect::MySql, Nulls::Last) => format!("{} IS NULL ASC, {} {}", self.key, key, order),
+            (Dialect::SqlServer, Nulls::First) => format!(
+                "CASE WHEN {} IS NULL THEN 0 ELSE 1 END, {} {}",
+                self.key, key, order
+            ),
+            (Dialect::SqlServer, Nulls::Last) => format!(
+                "CASE WHEN {} IS NULL THEN 1 ELSE 0 END, {} {}",
+                self.key, key, order
+            ),
+        }
+    }
+}
+
 pub trait OrderBy {
-    fn get_order(&self) -> &[(&str, &Order)];
-    fn set_order(&mut self, group: Vec<(String, Order)>);
+    fn get_order(&self) -> Vec<&OrderEntry>;
+    fn set_order(&mut self, f: impl FnOnce(&mut Vec<OrderEntry>));
 
-    fn raw_order(&mut self, raw: &str) -> &mut Self;
+    fn raw_order(&mut self, raw: &str) -> &mut Self {
+        self.set_order(|f| {
+            f.push(OrderEntry {
+                key: raw.to_owned(),
+                order: None,
+                nulls: None,
+                collation: None,
+            })
+        });
+        self
+    }
 
-    fn order_by_expression(&mut self, exp: &str) -> &mut Self {
-        // TODO: IMPL ME LATER
+    fn order_by_expression(&mut self, exp: &str, order: Order) -> &mut Self {
+        self.set_order(|f| f.push(OrderEntry::new(exp.to_owned(), order)));
         self
     }
 
-    // fn order_by_row(&mut self, row: u32, order: Order) -> &mut Self {
-    //     self.set_order(vec![(row.to_string(), Order::Asc)]);
-    //     self
-    // }
-    //
     fn order_by_row_asc(&mut self, row: u32) -> &mut Self {
-        self.set_order(vec![(row.to_string(), Order::Asc)]);
-        self
+        self.order_by_row(row, Order::Asc)
     }
 
     fn order_by_row(&mut self, row: u32, order: Order) -> &mut Self {
-        self.set_order(vec![(row.to_string(), order)]);
+        self.set_order(|f| f.push(OrderEntry::new(row.to_string(), order)));
         self
     }
 
     fn order_by_row_desc(&mut self, row: u32) -> &mut Self {
-        self.set_order(vec![(row.to_string(), Order::Desc)]);
-        self
+        self.order_by_row(row, Order::Desc)
     }
 
     fn order_by_field_asc(&mut self, field: &str) -> &mut Self {
-        self.set_order(vec![(field.to_owned(), Order::Asc)]);
-        self
+        self.order_by_field(field, Order::Asc)
     }
 
     fn order_by_field_desc(&mut self, field: &str) -> &mut Self {
-        self.set_order(vec![(field.to_owned(), Order::Desc)]);
-        self
+        self.order_by_field(field, Order::Desc)
     }
 
     fn order_by_field(&mut self, field: &str, order: Order) -> &mut Self {
-        self.set_order(vec![(field.to_owned(), order)]);
+        self.set_order(|f| f.push(OrderEntry::new(field.to_owned(), order)));
         self
     }
 
     fn order_by_fields(&mut self, fields: Vec<(String, Order)>) -> &mut Self {
-        self.set_order(fields);
+        self.set_order(|f| {
+            f.extend(
+                fields
+                    .into_iter()
+                    .map(|(field, order)| OrderEntry::new(field, order)),
+            )
+        });
+        self
+    }
+
+    // client spec like `-created_at,name`, names mapped through `allowed`, nothing applied on a rejected name
+    fn order_by_spec(
+        &mut self,
+        spec: &str,
+        allowed: &[(&str, &str)],
+    ) -> Result<&mut Self, QueryError> {
+        let mut entries = vec![];
+
+        for key in spec.split(',').map(|k| k.trim()).filter(|k| !k.is_empty()) {
+            let (name, order) = match key.strip_prefix('-') {
+                Some(name) => (name, Order::Desc),
+                None => (key.strip_prefix('+').unwrap_or(key), Order::Asc),
+            };
+
+            let target = allowed
+                .iter()
+                .find(|(public, _)| *public == name)
+                .map(|(_, target)| *target)
+                .ok_or_else(|| QueryError::UnknownSortField(name.to_owned()))?;
+
+            entries.push(OrderEntry::new(target.to_owned(), order));
+        }
+
+        self.set_order(|f| f.extend(entries));
+        Ok(self)
+    }
+
+    // nulls_first, nulls_last and collate apply to the last added key
+    fn nulls_first(&mut self) -> &mut Self {
+        self.set_order(|f| {
+            if let Some(entry) = f.last_mut() {
+                entry.nulls = Some(Nulls::First)
+            }
+        });
+        self
+    }
+
+    fn nulls_last(&mut self) -> &mut Self {
+        self.set_order(|f| {
+            if let Some(entry) = f.last_mut() {
+                entry.nulls = Some(Nulls::Last)
+            }
+        });
         self
     }
+
+    fn collate(&mut self, collation: &str) -> &mut Self {
+        self.set_order(|f| {
+            if let Some(entry) = f.last_mut() {
+                entry.collation = Some(collation.to_owned())
+            }
+        });
+        self
+    }
+
+    fn build_order_by(&self, dialect: Dialect) -> Option<String> {
+        let order = self.get_order();
+        if order.is_empty() {
+            return None;
+        }
+
+        let order_strings = order
+            .iter()
+            .map(|o| o.build(dialect))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Some(format!("ORDER BY {}", order_strings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct OrderByTest {
+        order: Vec<OrderEntry>,
+    }
+
+    impl OrderBy for OrderByTest {
+        fn get_order(&self) -> Vec<&OrderEntry> {
+            self.order.iter().collect()
+        }
+
+        fn set_order(&mut self, f: impl FnOnce(&mut Vec<OrderEntry>)) {
+            f(&mut self.order)
+        }
+    }
+
+    #[test]
+    fn test_no_order() {
+        let order = OrderByTest::default();
+        assert_eq!(order.build_order_by(Dialect::Postgres), None);
+    }
+
+    #[test]
+    fn test_multi_key_order_appends() {
+        let mut order = OrderByTest::default();
+        order
+            .order_by_field_asc("last_name")
+            .order_by_field_desc("created_at")
+            .order_by_row_asc(1)
+            .order_by_fields(vec![("id".to_owned(), Order::Asc)]);
+        assert_eq!(
+            order.build_order_by(Dialect::Postgres).unwrap(),
+            "ORDER BY last_name ASC, created_at DESC, 1 ASC, id ASC"
+        );
+    }
+
+    #[test]
+    fn test_expression_and_raw_keys() {
+        let mut order = OrderByTest::default();
+        order
+            .order_by_expression("LENGTH(name)", Order::Desc)
+            .raw_order("RANDOM()");
+        assert_eq!(
+            order.build_order_by(Dialect::Sqlite).unwrap(),
+            "ORDER BY LENGTH(name) DESC, RANDOM()"
+        );
+    }
+
+    #[test]
+    fn test_nulls_native() {
+        let mut order = OrderByTest::default();
+        order
+            .order_by_field_desc("last_login")
+            .nulls_last()
+            .order_by_field_asc("score")
+            .nulls_first();
+        assert_eq!(
+            order.build_order_by(Dialect::Postgres).unwrap(),
+            "ORDER BY last_login DESC NULLS LAST, score ASC NULLS FIRST"
+        );
+    }
+
+    #[test]
+    fn test_nulls_emulated() {
+        let mut order = OrderByTest::default();
+        order.order_by_field_desc("last_login").nulls_last();
+        assert_eq!(
+            order.build_order_by(Dialect::MySql).unwrap(),
+            "ORDER BY last_login IS NULL ASC, last_login DESC"
+        );
+        assert_eq!(
+            order.build_order_by(Dialect::SqlServer).unwrap(),
+            "ORDER BY CASE WHEN last_login IS NULL THEN 1 ELSE 0 END, last_login DESC"
+        );
+    }
+
+    #[test]
+    fn test_collation() {
+        let mut order = OrderByTest::default();
+        order
+            .order_by_field_asc("name")
+            .collate("NOCASE")
+            .nulls_first();
+        assert_eq!(
+            order.build_order_by(Dialect::Sqlite).unwrap(),
+            "ORDER BY name COLLATE NOCASE ASC NULLS FIRST"
+        );
+        assert_eq!(
+            order.build_order_by(Dialect::MySql).unwrap(),
+            "ORDER BY name IS NULL DESC, name COLLATE NOCASE ASC"
+        );
+    }
+
+    const ALLOWED: &[(&str, &str)] = &[
+        ("created_at", "users.created_at"),
+        ("name", "LOWER(users.name)"),
+        ("id", "users.id"),
+    ];
+
+    #[test]
+    fn test_order_by_spec() {
+        let mut order = OrderByTest::default();
+        order
+            .order_by_spec("-created_at, name,+id", ALLOWED)
+            .unwrap();
+        assert_eq!(
+            order.build_order_by(Dialect::Postgres).unwrap(),
+            "ORDER BY users.created_at DESC, LOWER(users.name) ASC, users.id ASC"
+        );
+    }
+
+    #[test]
+    fn test_order_by_empty_spec() {
+        let mut order = OrderByTest::default();
+        order.order_by_spec(" , ", ALLOWED).unwrap();
+        assert_eq!(order.build_order_by(Dialect::Postgres), None);
+    }
+
+    #[test]
+    fn test_order_by_spec_rejects_unknown_field() {
+        let mut order = OrderByTest::default();
+        assert_eq!(
+            order
+                .order_by_spec("name,password; DROP TABLE users", ALLOWED)
+                .err(),
+            Some(QueryError::UnknownSortField(
+                "password; DROP TABLE users".to_owned()
+            ))
+        );
+        assert_eq!(
+            order.order_by_spec("--name", ALLOWED).err(),
+            Some(QueryError::UnknownSortField("-name".to_owned()))
+        );
+        assert_eq!(order.build_order_by(Dialect::Postgres), None);
+    }
 }
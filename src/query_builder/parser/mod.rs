@@ -0,0 +1,1697 @@
+use std::fmt;
+
+use super::{
+    compound::{CompoundQuery, SetOperator},
+    condition::Condition,
+    error::QueryError,
+    group_by::GroupBy,
+    join::{Join, JoinTarget, JoinType},
+    lock::Lock,
+    order_by::{Order, OrderBy},
+    query::Query,
+    select::{Agregate, Select, SelectQuery},
+    value::SqlValue,
+    window::{FrameBound, NamedWindow, Window, WindowExpr, WindowFunction},
+    with::{Materialization, With},
+};
+
+// words that end an implicit alias, the unsupported ones are reported by name
+const RESERVED: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "GROUP",
+    "BY",
+    "HAVING",
+    "ORDER",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "OUTER",
+    "ON",
+    "AND",
+    "OR",
+    "NOT",
+    "IN",
+    "IS",
+    "NULL",
+    "LIKE",
+    "BETWEEN",
+    "AS",
+    "ASC",
+    "DESC",
+    "NULLS",
+    "COLLATE",
+    "FOR",
+    "TRUE",
+    "FALSE",
+    "WITH",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "WINDOW",
+    "OVER",
+    "FILTER",
+    "CROSS",
+    "LATERAL",
+];
+
+// LIMIT, OFFSET and FETCH are read after the members of a compound only
+const UNSUPPORTED: &[&str] = &[
+    "DISTINCT", "LIMIT", "OFFSET", "FETCH", "TOP", "NATURAL", "USING", "INTO", "EXISTS",
+];
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "<>", "!=", "||", "=", "<", ">", "(", ")", ",", ".", "*", ";", "+", "-", "/", "%",
+];
+
+const COMPARISONS: &[&str] = &["=", "!=", "<>", "<", "<=", ">", ">="];
+
+const OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "||"];
+
+// parentheses, NOT and aggregate arguments recurse, deeper input would overflow the stack
+const MAX_DEPTH: usize = 64;
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED
+        .iter()
+        .chain(UNSUPPORTED.iter())
+        .any(|r| r.eq_ignore_ascii_case(word))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String), // Keeps its quotes so it renders back as written
+    Number(String),
+    Text(String),
+    Symbol(&'static str),
+    Param,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{}", word),
+            Token::Quoted(quoted) => write!(f, "{}", quoted),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Text(text) => write!(f, "'{}'", text),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+            Token::Param => write!(f, "a bound parameter"),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Spanned {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+fn syntax_error(sql: &str, offset: usize, message: String) -> QueryError {
+    let before = &sql[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    QueryError::Syntax(message, line, column)
+}
+
+// the text between the quotes with doubled quotes collapsed, and the length including them
+fn quoted(rest: &str, close: char) -> Option<(String, usize)> {
+    let mut text = String::new();
+    let mut chars = rest.char_indices().skip(1).peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != close {
+            text.push(c);
+        } else if chars.peek().map(|&(_, next)| next) == Some(close) {
+            text.push(close);
+            chars.next();
+        } else {
+            return Some((text, i + c.len_utf8()));
+        }
+    }
+
+    None
+}
+
+fn number_length(rest: &str) -> usize {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+
+    let mut len = digits(rest);
+    if rest[len..].starts_with('.') {
+        len += 1 + digits(&rest[len + 1..]);
+    }
+
+    let exponent = &rest[len..];
+    if exponent.starts_with(['e', 'E']) {
+        let sign = usize::from(exponent[1..].starts_with(['+', '-']));
+        let count = digits(&exponent[1 + sign..]);
+        if count > 0 {
+            len += 1 + sign + count;
+        }
+    }
+
+    len
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Spanned>, QueryError> {
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while let Some(c) = sql[i..].chars().next() {
+        let rest = &sql[i..];
+        let start = i;
+
+        let token = if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        } else if rest.starts_with("--") {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        } else if rest.starts_with("/*") {
+            match rest.find("*/") {
+                Some(end) => i += end + 2,
+                None => return Err(syntax_error(sql, start, "unterminated comment".to_owned())),
+            }
+            continue;
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            i += len;
+            Token::Word(rest[..len].to_owned())
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = number_length(rest);
+            i += len;
+            Token::Number(rest[..len].to_owned())
+        } else if c == '\'' {
+            let (text, len) = quoted(rest, '\'')
+                .ok_or_else(|| syntax_error(sql, start, "unterminated string".to_owned()))?;
+            i += len;
+            Token::Text(text)
+        } else if c == '"' || c == '`' || c == '[' {
+            let close = if c == '[' { ']' } else { c };
+            let (_, len) = quoted(rest, close)
+                .ok_or_else(|| syntax_error(sql, start, "unterminated identifier".to_owned()))?;
+            i += len;
+            Token::Quoted(rest[..len].to_owned())
+        } else if c == '?' || (c == '$' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            i += 1 + rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - 1);
+            Token::Param
+        } else if let Some(&symbol) = SYMBOLS.iter().find(|&&s| rest.starts_with(s)) {
+            i += symbol.len();
+            Token::Symbol(symbol)
+        } else {
+            return Err(syntax_error(
+                sql,
+                start,
+                format!("unexpected character {}", c),
+            ));
+        };
+
+        tokens.push(Spanned {
+            token,
+            start,
+            end: i,
+        });
+    }
+
+    tokens.push(Spanned {
+        token: Token::End,
+        start: sql.len(),
+        end: sql.len(),
+    });
+
+    Ok(tokens)
+}
+
+fn number_value(number: &str) -> SqlValue {
+    if let Ok(value) = number.parse::<i32>() {
+        SqlValue::I32(value)
+    } else if let Ok(value) = number.parse::<i64>() {
+        SqlValue::I64(value)
+    } else {
+        number.parse::<f64>().map_or(SqlValue::Null, SqlValue::F64)
+    }
+}
+
+fn compare(column: String, operator: &str, value: SqlValue) -> Condition {
+    match operator {
+        "=" => Condition::Eq(column, value),
+        "!=" | "<>" => Condition::Neq(column, value),
+        "<" => Condition::Lt(column, value),
+        "<=" => Condition::Lte(column, value),
+        ">" => Condition::Gt(column, value),
+        _ => Condition::Gte(column, value),
+    }
+}
+
+// a literal on the left reads the other way round, 30 < age is age > 30
+fn flip(operator: &str) -> &str {
+    match operator {
+        "<" => ">",
+        "<=" => ">=",
+        ">" => "<",
+        ">=" => "<=",
+        operator => operator,
+    }
+}
+
+enum Operand {
+    Column(String),     // A plain or qualified column
+    Expression(String), // Any other expression, kept as written
+    Agregate(Agregate),
+    Window(WindowExpr, String), // Takes the call and its text as written
+    Value(SqlValue),
+}
+
+impl Operand {
+    fn into_sql(self) -> String {
+        match self {
+            Operand::Column(sql) | Operand::Expression(sql) | Operand::Window(_, sql) => sql,
+            // the aggregates the parser builds render the same in every dialect
+            Operand::Agregate(agregate) => agregate.to_string(),
+            Operand::Value(value) => value.to_sql(),
+        }
+    }
+}
+
+// a whole query, or the body of a cte or a derived table
+enum Parsed {
+    Select(Box<SelectQuery>),
+    Compound(CompoundQuery),
+}
+
+impl Parsed {
+    fn build(&self) -> Result<Query, QueryError> {
+        match self {
+            Parsed::Select(select) => select.build_query(),
+            Parsed::Compound(compound) => compound.build_compound(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Spanned>,
+    position: usize,
+    strict: bool, // Filters reject what only Condition::Native could hold
+    allowed: Option<&'a [&'a str]>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(sql: &'a str) -> Result<Self, QueryError> {
+        Ok(Self {
+            sql,
+            tokens: tokenize(sql)?,
+            position: 0,
+            strict: false,
+            allowed: None,
+            depth: 0,
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].token
+    }
+
+    fn peek_next(&self) -> &Token {
+        &self.tokens[(self.position + 1).min(self.tokens.len() - 1)].token
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].token.clone();
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn start(&self) -> usize {
+        self.tokens[self.position].start
+    }
+
+    // the source from start to the end of the last consumed token
+    fn slice(&self, start: usize) -> String {
+        let end = self.tokens[self.position.saturating_sub(1)].end;
+        self.sql[start..end.max(start)].to_owned()
+    }
+
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, QueryError>,
+    ) -> Result<T, QueryError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("nesting deeper than {} levels", MAX_DEPTH)));
+        }
+
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    fn error(&self, message: String) -> QueryError {
+        syntax_error(self.sql, self.start(), message)
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryError {
+        match self.peek() {
+            Token::Word(word) if word.eq_ignore_ascii_case("SELECT") => {
+                self.error("subqueries are not supported".to_owned())
+            }
+            Token::Word(word) if UNSUPPORTED.iter().any(|u| u.eq_ignore_ascii_case(word)) => {
+                self.error(format!("{} is not supported", word.to_ascii_uppercase()))
+            }
+            Token::Param => self.error("bound parameters are not supported".to_owned()),
+            token => self.error(format!("expected {}, found {}", expected, token)),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.unexpected(keyword)),
+        }
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), QueryError> {
+        match self.eat_symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.unexpected(symbol)),
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), QueryError> {
+        self.eat_symbol(";");
+        match self.peek() {
+            Token::End => Ok(()),
+            _ => Err(self.unexpected("end of input")),
+        }
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<String, QueryError> {
+        match self.peek().clone() {
+            Token::Word(word) if !is_reserved(&word) => {
+                self.advance();
+                Ok(word)
+            }
+            Token::Quoted(quoted) => {
+                self.advance();
+                Ok(quoted)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    // a possibly qualified name like public.users, u.id or u.*
+    fn name(&mut self, expected: &str) -> Result<String, QueryError> {
+        let mut name = self.identifier(expected)?;
+
+        while self.eat_symbol(".") {
+            if self.eat_symbol("*") {
+                name.push_str(".*");
+                break;
+            }
+            name.push('.');
+            name.push_str(&self.identifier("a name")?);
+        }
+
+        Ok(name)
+    }
+
+    fn alias(&mut self) -> Result<Option<String>, QueryError> {
+        if self.eat_keyword("AS") {
+            return self.identifier("an alias").map(Some);
+        }
+
+        match self.peek() {
+            Token::Word(word) if !is_reserved(word) => self.identifier("an alias").map(Some),
+            Token::Quoted(_) => self.identifier("an alias").map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn literal(&mut self) -> Result<SqlValue, QueryError> {
+        let negative = self.eat_symbol("-");
+
+        let value = match self.peek() {
+            Token::Number(number) if negative => number_value(&format!("-{}", number)),
+            Token::Number(number) => number_value(number),
+            Token::Text(text) if !negative => SqlValue::from(text.as_str()),
+            Token::Word(word) if !negative && word.eq_ignore_ascii_case("TRUE") => {
+                SqlValue::Bool(true)
+            }
+            Token::Word(word) if !negative && word.eq_ignore_ascii_case("FALSE") => {
+                SqlValue::Bool(false)
+            }
+            Token::Word(word) if !negative && word.eq_ignore_ascii_case("NULL") => SqlValue::Null,
+            _ => return Err(self.unexpected("a value")),
+        };
+
+        self.advance();
+        Ok(value)
+    }
+
+    // skips to the parenthesis closing one that was just consumed
+    fn skip_group(&mut self) -> Result<(), QueryError> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.peek() {
+                Token::Symbol("(") => depth += 1,
+                Token::Symbol(")") => depth -= 1,
+                Token::End | Token::Param => return Err(self.unexpected(")")),
+                _ => {}
+            }
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, function: &str) -> Result<Operand, QueryError> {
+        let start = self.start();
+        self.advance();
+        self.expect_symbol("(")?;
+        let arguments = self.position;
+
+        let function = function.to_ascii_uppercase();
+        let operand = match function.as_str() {
+            "COUNT" if self.eat_symbol("*") => {
+                self.expect_symbol(")")?;
+                Operand::Agregate(Agregate::CountAll)
+            }
+            "COUNT" | "SUM" | "MIN" | "MAX" | "AVG" => {
+                let distinct = self.eat_keyword("DISTINCT");
+                let argument = self.nested(Self::expression)?.into_sql();
+                self.expect_symbol(")")?;
+
+                let agregate = match function.as_str() {
+                    "COUNT" => Agregate::Count(argument),
+                    "SUM" => Agregate::Sum(argument),
+                    "MIN" => Agregate::Min(argument),
+                    "MAX" => Agregate::Max(argument),
+                    _ => Agregate::Avg(argument),
+                };
+
+                Operand::Agregate(match distinct {
+                    true => agregate.distinct(),
+                    false => agregate,
+                })
+            }
+            _ => {
+                self.skip_group()?;
+                Operand::Expression(self.slice(start))
+            }
+        };
+
+        let operand = match operand {
+            Operand::Agregate(agregate) if self.eat_keyword("FILTER") => {
+                self.expect_symbol("(")?;
+                self.expect_keyword("WHERE")?;
+                let condition = self.nested(Self::condition)?;
+                self.expect_symbol(")")?;
+                Operand::Agregate(agregate.filter(condition))
+            }
+            operand => operand,
+        };
+
+        if !self.is_keyword("OVER") {
+            return Ok(operand);
+        }
+
+        let window_function = match operand {
+            Operand::Agregate(agregate) => Some(WindowFunction::Agregate(agregate)),
+            _ => self.window_function(&function, arguments),
+        };
+        self.advance();
+
+        // a function WindowFunction has no variant for is kept as written
+        let window_function = match window_function {
+            Some(window_function) => window_function,
+            None => {
+                match self.eat_symbol("(") {
+                    true => self.skip_group()?,
+                    false => {
+                        self.identifier("a window")?;
+                    }
+                }
+                return Ok(Operand::Expression(self.slice(start)));
+            }
+        };
+
+        let window = match self.eat_symbol("(") {
+            true => {
+                let window = self.window()?;
+                self.expect_symbol(")")?;
+                window_function.over(window)
+            }
+            false => window_function.over_named(&self.identifier("a window")?),
+        };
+
+        Ok(Operand::Window(window, self.slice(start)))
+    }
+
+    // reads the arguments again as a ranking or offset function, None when they are not one
+    fn window_function(&mut self, function: &str, arguments: usize) -> Option<WindowFunction> {
+        let end = self.position;
+        self.position = arguments;
+
+        let parsed = match function {
+            "ROW_NUMBER" => Ok(WindowFunction::RowNumber),
+            "RANK" => Ok(WindowFunction::Rank),
+            "DENSE_RANK" => Ok(WindowFunction::DenseRank),
+            "NTILE" => self.count().map(WindowFunction::Ntile),
+            "LAG" | "LEAD" => {
+                self.offset_arguments()
+                    .map(|(column, offset, default)| match function {
+                        "LAG" => WindowFunction::Lag(column, offset, default),
+                        _ => WindowFunction::Lead(column, offset, default),
+                    })
+            }
+            _ => Err(self.unexpected("a window function")),
+        };
+
+        let parsed = parsed
+            .and_then(|parsed| self.expect_symbol(")").map(|_| parsed))
+            .ok()
+            .filter(|_| self.position == end);
+        self.position = end;
+        parsed
+    }
+
+    fn offset_arguments(&mut self) -> Result<(String, Option<u32>, Option<SqlValue>), QueryError> {
+        let column = self.nested(Self::expression)?.into_sql();
+
+        let offset = match self.eat_symbol(",") {
+            true => Some(self.count()?),
+            false => None,
+        };
+
+        let default = match offset.is_some() && self.eat_symbol(",") {
+            true => Some(self.literal()?),
+            false => None,
+        };
+
+        Ok((column, offset, default))
+    }
+
+    // a whole number like an NTILE bucket count or a frame offset
+    fn count<T: std::str::FromStr>(&mut self) -> Result<T, QueryError> {
+        let count = match self.peek() {
+            Token::Number(number) => number.parse().ok(),
+            _ => None,
+        };
+
+        match count {
+            Some(count) => {
+                self.advance();
+                Ok(count)
+            }
+            None => Err(self.unexpected("a count")),
+        }
+    }
+
+    // the inside of OVER (...) or of a WINDOW definition
+    fn window(&mut self) -> Result<Window, QueryError> {
+        let mut window = Window::new();
+
+        if self.eat_keyword("PARTITION") {
+            self.expect_keyword("BY")?;
+            let mut fields = vec![self.expression()?.into_sql()];
+            while self.eat_symbol(",") {
+                fields.push(self.expression()?.into_sql());
+            }
+            window = window.partition_by(&fields.iter().map(|f| f.as_str()).collect::<Vec<&str>>());
+        }
+
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            self.order_by(&mut window)?;
+        }
+
+        let rows = self.eat_keyword("ROWS");
+        if rows || self.eat_keyword("RANGE") {
+            // a lone bound is the start of a frame ending at the current row
+            let (start, end) = match self.eat_keyword("BETWEEN") {
+                true => {
+                    let start = self.frame_bound()?;
+                    self.expect_keyword("AND")?;
+                    (start, self.frame_bound()?)
+                }
+                false => (self.frame_bound()?, FrameBound::CurrentRow),
+            };
+
+            window = match rows {
+                true => window.rows_between(start, end),
+                false => window.range_between(start, end),
+            };
+        }
+
+        Ok(window)
+    }
+
+    fn frame_bound(&mut self) -> Result<FrameBound, QueryError> {
+        if self.eat_keyword("UNBOUNDED") {
+            if self.eat_keyword("PRECEDING") {
+                return Ok(FrameBound::UnboundedPreceding);
+            }
+            self.expect_keyword("FOLLOWING")?;
+            return Ok(FrameBound::UnboundedFollowing);
+        }
+
+        if self.eat_keyword("CURRENT") {
+            self.expect_keyword("ROW")?;
+            return Ok(FrameBound::CurrentRow);
+        }
+
+        let offset = self.count()?;
+        if self.eat_keyword("PRECEDING") {
+            return Ok(FrameBound::Preceding(offset));
+        }
+        self.expect_keyword("FOLLOWING")?;
+        Ok(FrameBound::Following(offset))
+    }
+
+    fn term(&mut self) -> Result<Operand, QueryError> {
+        let start = self.start();
+
+        match self.peek().clone() {
+            Token::Symbol("(") => {
+                self.advance();
+                self.skip_group()?;
+                Ok(Operand::Expression(self.slice(start)))
+            }
+            Token::Word(word) if !is_reserved(&word) && self.peek_next() == &Token::Symbol("(") => {
+                self.call(&word)
+            }
+            Token::Word(word) if !is_reserved(&word) => Ok(Operand::Column(self.name("a column")?)),
+            Token::Quoted(_) => Ok(Operand::Column(self.name("a column")?)),
+            _ => self.literal().map(Operand::Value),
+        }
+    }
+
+    // a term, or terms joined by arithmetic which are kept as written
+    fn expression(&mut self) -> Result<Operand, QueryError> {
+        let start = self.start();
+        let term = self.term()?;
+
+        if !matches!(self.peek(), Token::Symbol(s) if OPERATORS.contains(s)) {
+            return Ok(term);
+        }
+
+        while matches!(self.peek(), Token::Symbol(s) if OPERATORS.contains(s)) {
+            self.advance();
+            self.term()?;
+        }
+
+        Ok(Operand::Expression(self.slice(start)))
+    }
+
+    fn subject(&self, operand: Operand, start: usize) -> Result<String, QueryError> {
+        let column = match operand {
+            Operand::Value(_) => {
+                return Err(syntax_error(
+                    self.sql,
+                    start,
+                    "expected a column, found a value".to_owned(),
+                ))
+            }
+            Operand::Column(column) => column,
+            _ if self.strict => {
+                return Err(syntax_error(
+                    self.sql,
+                    start,
+                    "expected a column, found an expression".to_owned(),
+                ))
+            }
+            operand => return Ok(operand.into_sql()),
+        };
+
+        match self.allowed {
+            Some(allowed) if !allowed.contains(&column.as_str()) => Err(syntax_error(
+                self.sql,
+                start,
+                format!("unknown column {}", column),
+            )),
+            _ => Ok(column),
+        }
+    }
+
+    // an aggregate subject is kept whole so it renders for the dialect of the query
+    fn on_subject(
+        &self,
+        operand: Operand,
+        start: usize,
+        condition: impl FnOnce(String) -> Condition,
+    ) -> Result<Condition, QueryError> {
+        match operand {
+            Operand::Agregate(agregate) if !self.strict => Ok(Condition::Agregate(
+                Box::new(agregate),
+                Box::new(condition(String::new())),
+            )),
+            operand => Ok(condition(self.subject(operand, start)?)),
+        }
+    }
+
+    fn native(&self, start: usize, construct: &str) -> Result<Condition, QueryError> {
+        match self.strict {
+            true => Err(syntax_error(
+                self.sql,
+                start,
+                format!("{} is not supported in a filter", construct),
+            )),
+            false => Ok(Condition::Native(self.slice(start))),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, QueryError> {
+        let mut condition = self.conjunction()?;
+        while self.eat_keyword("OR") {
+            condition = condition.or(self.conjunction()?);
+        }
+        Ok(condition)
+    }
+
+    fn conjunction(&mut self) -> Result<Condition, QueryError> {
+        let mut condition = self.predicate()?;
+        while self.eat_keyword("AND") {
+            condition = condition.and(self.predicate()?);
+        }
+        Ok(condition)
+    }
+
+    fn predicate(&mut self) -> Result<Condition, QueryError> {
+        let start = self.start();
+
+        if self.eat_keyword("NOT") {
+            self.nested(Self::predicate)?;
+            return self.native(start, "NOT");
+        }
+
+        if !self.is_symbol("(") {
+            return self.comparison(start);
+        }
+
+        // a parenthesised condition, or an expression like (price - discount) > 10
+        let saved = self.position;
+        let group = self.nested(|parser| {
+            parser.advance();
+            let condition = parser.condition()?;
+            parser.expect_symbol(")")?;
+            Ok(condition)
+        });
+
+        match group {
+            Ok(condition) => Ok(condition),
+            Err(error) => {
+                self.position = saved;
+                self.comparison(start).map_err(|_| error)
+            }
+        }
+    }
+
+    fn comparison(&mut self, start: usize) -> Result<Condition, QueryError> {
+        let left = self.expression()?;
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return self.on_subject(left, start, |column| match negated {
+                true => Condition::NotNull(column),
+                false => Condition::Null(column),
+            });
+        }
+
+        let negated = self.eat_keyword("NOT");
+
+        if self.eat_keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut values = vec![self.literal()?];
+            while self.eat_symbol(",") {
+                values.push(self.literal()?);
+            }
+            self.expect_symbol(")")?;
+
+            return self.on_subject(left, start, |column| match negated {
+                true => Condition::NotIn(column, values),
+                false => Condition::In(column, values),
+            });
+        }
+
+        // the enum has no NOT BETWEEN or NOT LIKE, those are kept as written
+        if self.eat_keyword("BETWEEN") {
+            let low = self.literal()?;
+            self.expect_keyword("AND")?;
+            let high = self.literal()?;
+
+            return match negated {
+                true => self
+                    .subject(left, start)
+                    .and(self.native(start, "NOT BETWEEN")),
+                false => {
+                    self.on_subject(left, start, |column| Condition::Between(column, low, high))
+                }
+            };
+        }
+
+        if self.eat_keyword("LIKE") {
+            let pattern = self.literal()?;
+
+            return match negated {
+                true => self
+                    .subject(left, start)
+                    .and(self.native(start, "NOT LIKE")),
+                false => self.on_subject(left, start, |column| Condition::Like(column, pattern)),
+            };
+        }
+
+        if negated {
+            return Err(self.unexpected("IN, BETWEEN or LIKE"));
+        }
+
+        let operator = match self.peek() {
+            Token::Symbol(s) if COMPARISONS.contains(s) => *s,
+            _ => return Err(self.unexpected("a comparison")),
+        };
+        self.advance();
+
+        let right_start = self.start();
+        let right = self.expression()?;
+
+        match (left, right) {
+            // = NULL is never true, unlike IS NULL
+            (Operand::Value(SqlValue::Null), _) | (_, Operand::Value(SqlValue::Null)) => {
+                self.native(start, "comparing with NULL")
+            }
+            (Operand::Value(_), Operand::Value(_)) => self.native(start, "comparing two values"),
+            (Operand::Value(value), right) => self.on_subject(right, right_start, |column| {
+                compare(column, flip(operator), value)
+            }),
+            (left, Operand::Value(value)) => {
+                self.on_subject(left, start, |column| compare(column, operator, value))
+            }
+            _ => self.native(start, "comparing two columns"),
+        }
+    }
+
+    // a select up to its WINDOW clause, tail reads what may follow
+    fn select(&mut self) -> Result<SelectQuery, QueryError> {
+        let mut select = SelectQuery::new();
+        // the query ran as written, leave grouping checks to the database
+        select.allow_functional_dependence();
+
+        if self.eat_keyword("WITH") {
+            self.with(&mut select)?;
+        }
+
+        self.expect_keyword("SELECT")?;
+        self.fields(&mut select)?;
+
+        self.expect_keyword("FROM")?;
+        let table = self.name("a table")?;
+        match self.alias()? {
+            Some(alias) => select.table_as(&table, &alias),
+            None => select.table(&table),
+        };
+
+        while let Some((join_type, apply)) = self.join_type()? {
+            let lateral = apply || self.eat_keyword("LATERAL");
+
+            let target = match self.eat_symbol("(") {
+                true => {
+                    let query = self.nested(Self::query)?.build()?;
+                    self.expect_symbol(")")?;
+                    let alias = match self.alias()? {
+                        Some(alias) => alias,
+                        None => return Err(self.unexpected("an alias")),
+                    };
+                    JoinTarget::Subquery(query, alias)
+                }
+                false if lateral => return Err(self.unexpected("(")),
+                false => JoinTarget::Table(self.name("a table")?, self.alias()?),
+            };
+
+            let on = match join_type {
+                JoinType::Cross => None,
+                _ if apply => None,
+                _ => self.on(lateral)?,
+            };
+
+            match (target, lateral) {
+                (JoinTarget::Subquery(query, alias), true) => {
+                    select.join_lateral(join_type, query, &alias, on)
+                }
+                (target, _) => select.join_target(join_type, target, on),
+            }
+        }
+
+        if self.eat_keyword("WHERE") {
+            select.r#where(self.condition()?);
+        }
+
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            self.group_by(&mut select)?;
+        }
+
+        if self.eat_keyword("HAVING") {
+            select.having(self.condition()?);
+        }
+
+        if self.eat_keyword("WINDOW") {
+            loop {
+                let name = self.identifier("a window name")?;
+                self.expect_keyword("AS")?;
+                self.expect_symbol("(")?;
+                let window = self.window()?;
+                self.expect_symbol(")")?;
+                select.window(&name, window);
+
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        Ok(select)
+    }
+
+    // the ORDER BY and locking of a select that is not a member of a compound
+    fn tail(&mut self, select: &mut SelectQuery) -> Result<(), QueryError> {
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            self.order_by(select)?;
+        }
+
+        if self.eat_keyword("FOR") {
+            self.lock(select)?;
+        }
+
+        Ok(())
+    }
+
+    // the ON of a join, ON TRUE is how the builder renders a lateral join without one
+    fn on(&mut self, lateral: bool) -> Result<Option<Condition>, QueryError> {
+        self.expect_keyword("ON")?;
+
+        let bare_true = self.is_keyword("TRUE")
+            && match self.peek_next() {
+                Token::Word(word) => !["AND", "OR", "IS", "NOT", "IN", "BETWEEN", "LIKE"]
+                    .iter()
+                    .any(|w| w.eq_ignore_ascii_case(word)),
+                Token::Symbol(symbol) => [")", ";"].contains(symbol),
+                token => token == &Token::End,
+            };
+
+        match (bare_true, lateral) {
+            (true, true) => {
+                self.advance();
+                Ok(None)
+            }
+            (true, false) => {
+                self.advance();
+                Ok(Some(Condition::Native("TRUE".to_owned())))
+            }
+            _ => self.condition().map(Some),
+        }
+    }
+
+    // the ctes after WITH, a cte of WITH RECURSIVE that is one UNION ALL is split in two
+    fn with(&mut self, select: &mut SelectQuery) -> Result<(), QueryError> {
+        let recursive = self.eat_keyword("RECURSIVE");
+
+        loop {
+            let name = self.identifier("a cte name")?;
+
+            let mut columns = vec![];
+            if self.eat_symbol("(") {
+                columns.push(self.identifier("a column")?);
+                while self.eat_symbol(",") {
+                    columns.push(self.identifier("a column")?);
+                }
+                self.expect_symbol(")")?;
+            }
+
+            self.expect_keyword("AS")?;
+            let materialization = if self.eat_keyword("MATERIALIZED") {
+                Some(Materialization::Materialized)
+            } else if self.eat_keyword("NOT") {
+                self.expect_keyword("MATERIALIZED")?;
+                Some(Materialization::NotMaterialized)
+            } else {
+                None
+            };
+
+            self.expect_symbol("(")?;
+            let start = self.start();
+            let bare = !self.is_symbol("(");
+            let first = self.nested(Self::member)?;
+            let mut rest = self.set_members()?;
+
+            let split = recursive
+                && materialization.is_none()
+                && matches!(rest.as_slice(), [(SetOperator::UnionAll, _)])
+                && self.is_symbol(")");
+
+            if split {
+                let columns = columns.iter().map(|c| c.as_str()).collect::<Vec<&str>>();
+                let (_, recursive_part) = rest.remove(0);
+                select.with_recursive(&name, &columns, first.build_query()?, recursive_part);
+            } else if !columns.is_empty() {
+                return Err(syntax_error(
+                    self.sql,
+                    start,
+                    "a column list is only supported on a recursive cte".to_owned(),
+                ));
+            } else {
+                let query = self.finish(first, bare, rest, start)?.build()?;
+                match materialization {
+                    Some(materialization) => {
+                        select.with_materialization(&name, query, materialization)
+                    }
+                    None => select.with(&name, query),
+                };
+            }
+
+            self.expect_symbol(")")?;
+            if !self.eat_symbol(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    // a select, or selects joined by set operators
+    fn query(&mut self) -> Result<Parsed, QueryError> {
+        let start = self.start();
+        let bare = !self.is_symbol("(");
+        let first = self.member()?;
+        let rest = self.set_members()?;
+        self.finish(first, bare, rest, start)
+    }
+
+    // a member of a compound, in parentheses it keeps its own ORDER BY and locking
+    fn member(&mut self) -> Result<SelectQuery, QueryError> {
+        if !self.eat_symbol("(") {
+            return self.select();
+        }
+
+        let mut select = self.nested(Self::select)?;
+        self.tail(&mut select)?;
+        self.expect_symbol(")")?;
+        Ok(select)
+    }
+
+    fn set_members(&mut self) -> Result<Vec<(SetOperator, Query)>, QueryError> {
+        let mut members = vec![];
+
+        loop {
+            let operator = if self.eat_keyword("UNION") {
+                match self.eat_keyword("ALL") {
+                    true => SetOperator::UnionAll,
+                    false => SetOperator::Union,
+                }
+            } else if self.eat_keyword("INTERSECT") {
+                SetOperator::Intersect
+            } else if self.eat_keyword("EXCEPT") {
+                SetOperator::Except
+            } else {
+                return Ok(members);
+            };
+
+            members.push((operator, self.member()?.build_query()?));
+        }
+    }
+
+    // a lone bare select takes the ORDER BY and locking, otherwise they belong to the compound
+    fn finish(
+        &mut self,
+        mut first: SelectQuery,
+        bare: bool,
+        rest: Vec<(SetOperator, Query)>,
+        start: usize,
+    ) -> Result<Parsed, QueryError> {
+        if bare && rest.is_empty() {
+            self.tail(&mut first)?;
+            return Ok(Parsed::Select(Box::new(first)));
+        }
+
+        // rendered inside the first member, the other members would not see the ctes
+        if bare && !first.get_with().is_empty() {
+            return Err(syntax_error(
+                self.sql,
+                start,
+                "WITH on a compound query is not supported".to_owned(),
+            ));
+        }
+
+        let mut compound = CompoundQuery::new(first.build_query()?);
+        for (operator, query) in rest {
+            compound.combine(operator, query);
+        }
+
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            self.order_by(&mut compound)?;
+        }
+
+        self.limit(&mut compound)?;
+        Ok(Parsed::Compound(compound))
+    }
+
+    // every form CompoundQuery renders, sqlite's LIMIT -1 is no limit
+    fn limit(&mut self, compound: &mut CompoundQuery) -> Result<(), QueryError> {
+        if self.eat_keyword("LIMIT") {
+            match self.eat_symbol("-") {
+                true if self.peek() == &Token::Number("1".to_owned()) => {
+                    self.advance();
+                }
+                true => return Err(self.unexpected("1")),
+                false => {
+                    compound.limit(self.count()?);
+                }
+            }
+
+            if self.eat_keyword("OFFSET") {
+                compound.offset(self.count()?);
+            }
+        } else if self.eat_keyword("OFFSET") {
+            compound.offset(self.count()?);
+
+            if self.eat_keyword("ROWS") && self.eat_keyword("FETCH") {
+                self.expect_keyword("NEXT")?;
+                compound.limit(self.count()?);
+                self.expect_keyword("ROWS")?;
+                self.expect_keyword("ONLY")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fields(&mut self, select: &mut SelectQuery) -> Result<(), QueryError> {
+        if self.eat_symbol("*") {
+            select.select_all_fields();
+            return Ok(());
+        }
+
+        loop {
+            let field = self.expression()?;
+            let alias = self.alias()?.unwrap_or_default();
+
+            match field {
+                Operand::Agregate(agregate) => {
+                    select.select_agregate(agregate, &alias);
+                }
+                Operand::Window(window, _) => {
+                    select.select_window(window, &alias);
+                }
+                field => {
+                    select.select_alias_field(field.into_sql(), &alias);
+                }
+            }
+
+            if !self.eat_symbol(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    // sql server's CROSS and OUTER APPLY are lateral joins without ON, the bool tells
+    fn join_type(&mut self) -> Result<Option<(JoinType, bool)>, QueryError> {
+        let apply =
+            matches!(self.peek_next(), Token::Word(word) if word.eq_ignore_ascii_case("APPLY"));
+        if apply && (self.is_keyword("CROSS") || self.is_keyword("OUTER")) {
+            let join_type = match self.eat_keyword("CROSS") {
+                true => JoinType::Cross,
+                false => {
+                    self.advance();
+                    JoinType::Left
+                }
+            };
+            self.advance();
+            return Ok(Some((join_type, true)));
+        }
+
+        let join_type = if self.eat_keyword("INNER") || self.is_keyword("JOIN") {
+            JoinType::Inner
+        } else if self.eat_keyword("LEFT") {
+            self.eat_keyword("OUTER");
+            JoinType::Left
+        } else if self.eat_keyword("RIGHT") {
+            self.eat_keyword("OUTER");
+            JoinType::Right
+        } else if self.eat_keyword("FULL") || self.is_keyword("OUTER") {
+            self.eat_keyword("OUTER");
+            JoinType::Outer
+        } else if self.eat_keyword("CROSS") {
+            JoinType::Cross
+        } else if self.is_keyword("NATURAL") {
+            return Err(self.unexpected("JOIN"));
+        } else {
+            return Ok(None);
+        };
+
+        self.expect_keyword("JOIN")?;
+        Ok(Some((join_type, false)))
+    }
+
+    fn group_by(&mut self, select: &mut SelectQuery) -> Result<(), QueryError> {
+        let mut fields = vec![];
+
+        loop {
+            let grouping = ["ROLLUP", "CUBE"]
+                .into_iter()
+                .find(|&g| self.is_keyword(g) && self.peek_next() == &Token::Symbol("("));
+
+            match grouping {
+                Some(grouping) => {
+                    self.advance();
+                    self.advance();
+                    let mut columns = vec![self.expression()?.into_sql()];
+                    while self.eat_symbol(",") {
+                        columns.push(self.expression()?.into_sql());
+                    }
+                    self.expect_symbol(")")?;
+
+                    let columns = columns.iter().map(|c| c.as_str()).collect::<Vec<&str>>();
+                    match grouping {
+                        "ROLLUP" => select.rollup(&columns),
+                        _ => select.cube(&columns),
+                    };
+                }
+                None => fields.push(self.expression()?.into_sql()),
+            }
+
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+
+        select.group_by_fields(fields);
+        Ok(())
+    }
+
+    fn order_by(&mut self, target: &mut impl OrderBy) -> Result<(), QueryError> {
+        loop {
+            let start = self.start();
+            let key = self.expression()?;
+
+            let collation = match self.eat_keyword("COLLATE") {
+                true => Some(self.name("a collation")?),
+                false => None,
+            };
+
+            let order = match self.eat_keyword("DESC") {
+                true => Order::Desc,
+                false => {
+                    self.eat_keyword("ASC");
+                    Order::Asc
+                }
+            };
+
+            match key {
+                Operand::Value(SqlValue::I32(row)) if row > 0 => {
+                    target.order_by_row(row as u32, order);
+                }
+                Operand::Value(_) => {
+                    return Err(syntax_error(
+                        self.sql,
+                        start,
+                        "expected a column or a position".to_owned(),
+                    ))
+                }
+                key => {
+                    target.order_by_field(&key.into_sql(), order);
+                }
+            }
+
+            if let Some(collation) = collation {
+                target.collate(&collation);
+            }
+
+            if self.eat_keyword("NULLS") {
+                if self.eat_keyword("FIRST") {
+                    target.nulls_first();
+                } else {
+                    self.expect_keyword("LAST")?;
+                    target.nulls_last();
+                }
+            }
+
+            if !self.eat_symbol(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn lock(&mut self, select: &mut SelectQuery) -> Result<(), QueryError> {
+        if self.eat_keyword("UPDATE") {
+            select.for_update();
+        } else if self.eat_keyword("SHARE") {
+            select.for_share();
+        } else if self.eat_keyword("NO") {
+            self.expect_keyword("KEY")?;
+            self.expect_keyword("UPDATE")?;
+            select.for_no_key_update();
+        } else {
+            return Err(self.unexpected("UPDATE, NO KEY UPDATE or SHARE"));
+        }
+
+        if self.eat_keyword("OF") {
+            let mut tables = vec![self.name("a table")?];
+            while self.eat_symbol(",") {
+                tables.push(self.name("a table")?);
+            }
+            select.of(&tables.iter().map(|t| t.as_str()).collect::<Vec<&str>>());
+        }
+
+        if self.eat_keyword("NOWAIT") {
+            select.nowait();
+        } else if self.eat_keyword("SKIP") {
+            self.expect_keyword("LOCKED")?;
+            select.skip_locked();
+        }
+
+        Ok(())
+    }
+}
+
+// the subset the builder renders, anything else like LIMIT is a syntax error where it starts
+pub fn parse_select(sql: &str) -> Result<SelectQuery, QueryError> {
+    let mut parser = Parser::new(sql)?;
+    let mut select = parser.select()?;
+    parser.tail(&mut select)?;
+    parser.expect_end()?;
+    Ok(select)
+}
+
+// selects joined by UNION, INTERSECT or EXCEPT, with the ORDER BY and LIMIT of the whole
+pub fn parse_compound(sql: &str) -> Result<CompoundQuery, QueryError> {
+    let mut parser = Parser::new(sql)?;
+    let compound = match parser.query()? {
+        Parsed::Select(select) => CompoundQuery::new(select.build_query()?),
+        Parsed::Compound(compound) => compound,
+    };
+    parser.expect_end()?;
+    Ok(compound)
+}
+
+// a WHERE or HAVING condition on its own
+pub fn parse_condition(text: &str) -> Result<Condition, QueryError> {
+    let mut parser = Parser::new(text)?;
+    let condition = parser.condition()?;
+    parser.expect_end()?;
+    Ok(condition)
+}
+
+// a stored filter, every comparison is a column against values so nothing stays raw sql
+pub fn parse_filter(text: &str, allowed: Option<&[&str]>) -> Result<Condition, QueryError> {
+    let mut parser = Parser::new(text)?;
+    parser.strict = true;
+    parser.allowed = allowed;
+    let condition = parser.condition()?;
+    parser.expect_end()?;
+    Ok(condition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(sql: &str) -> String {
+        parse_select(sql).unwrap().build_select().unwrap()
+    }
+
+    fn render_compound(sql: &str) -> String {
+        parse_compound(sql)
+            .unwrap()
+            .build_compound()
+            .unwrap()
+            .sql()
+            .to_owned()
+    }
+
+    #[test]
+    fn test_parse_select() {
+        let sql = "select u.id, u.name as author, count(distinct p.id) posts \
+                   from users u \
+                   left join posts as p on p.user_id = u.id and p.published = true \
+                   where u.age >= 18 and (u.status = 'active' or u.last_login is not null) \
+                   group by u.id, u.name \
+                   having count(*) > 2 \
+                   order by posts desc nulls last, 2";
+
+        assert_eq!(
+            render(sql),
+            "SELECT u.id, u.name as author, COUNT(DISTINCT p.id) as posts FROM users AS u \
+             LEFT JOIN posts AS p ON (p.user_id = u.id AND p.published = true) \
+             WHERE (u.age >= 18 AND (u.status = 'active' OR u.last_login IS NOT NULL)) \
+             GROUP BY u.id, u.name HAVING COUNT(*) > 2 ORDER BY posts DESC NULLS LAST, 2 ASC"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let queries = [
+            "SELECT * FROM orders WHERE total BETWEEN 10 AND 99.5 FOR UPDATE SKIP LOCKED;",
+            "SELECT id, lower(email) AS email, price * qty total FROM items \
+             INNER JOIN carts c ON c.id = items.cart_id WHERE 5 < qty AND sku NOT LIKE 'x%'",
+            "SELECT region, SUM(amount) FROM sales GROUP BY ROLLUP (region) \
+             ORDER BY region COLLATE \"C\" DESC",
+            "SELECT id FROM t WHERE id IN (1, -2, 3000000000) AND name = 'it''s' \
+             AND NOT deleted = true OR (a + 1) > b",
+            "SELECT dept, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary DESC) AS rn, \
+             SUM(salary) OVER w total, LAG(salary, 1, 0) OVER (ORDER BY hired) prev, \
+             first_value(name) OVER (PARTITION BY dept) senior, \
+             COUNT(*) FILTER (WHERE remote = true) OVER w remote FROM staff \
+             WINDOW w AS (PARTITION BY dept ORDER BY hired ROWS UNBOUNDED PRECEDING)",
+            "WITH RECURSIVE chart (id, depth) AS (SELECT id, 0 FROM staff \
+             WHERE manager_id IS NULL UNION ALL SELECT s.id, chart.depth + 1 FROM staff s \
+             INNER JOIN chart ON s.manager_id = chart.id), \
+             recent AS NOT MATERIALIZED (SELECT id FROM hires WHERE year = 2024) \
+             SELECT * FROM chart INNER JOIN recent r ON r.id = chart.id",
+            "SELECT u.id, o.total, r.n FROM users u \
+             INNER JOIN (SELECT user_id, COUNT(*) AS total FROM orders GROUP BY user_id) AS o \
+             ON o.user_id = u.id \
+             LEFT JOIN LATERAL (SELECT COUNT(*) n FROM logins l WHERE l.user_id = u.id) r ON TRUE \
+             CROSS JOIN sizes CROSS APPLY (SELECT 1 AS one FROM colors) x",
+        ];
+
+        for sql in queries {
+            let rendered = render(sql);
+            assert_eq!(render(&rendered), rendered, "{}", sql);
+        }
+
+        let compounds = [
+            "SELECT id FROM a UNION SELECT id FROM b INTERSECT (SELECT id FROM c ORDER BY id) \
+             EXCEPT SELECT id FROM d ORDER BY 1 DESC LIMIT 10 OFFSET 5",
+            "(SELECT id FROM a) UNION ALL (SELECT id FROM b) \
+             OFFSET 5 ROWS FETCH NEXT 10 ROWS ONLY",
+        ];
+
+        for sql in compounds {
+            let rendered = render_compound(sql);
+            assert_eq!(render_compound(&rendered), rendered, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(
+            render(
+                "select name, rank() over (partition by dept order by pay desc), \
+                 avg(pay) over w, ntile(4) over (order by pay range between 1 preceding \
+                 and 2 following) from staff window w as (order by hired)"
+            ),
+            "SELECT name, RANK() OVER (PARTITION BY dept ORDER BY pay DESC), \
+             AVG(pay) OVER w, NTILE(4) OVER (ORDER BY pay ASC RANGE BETWEEN 1 PRECEDING \
+             AND 2 FOLLOWING) FROM staff WINDOW w AS (ORDER BY hired ASC)"
+        );
+        // functions WindowFunction does not name keep their call as written
+        assert_eq!(
+            render("SELECT nth_value(name, 2) OVER (ORDER BY pay) AS second FROM staff"),
+            "SELECT nth_value(name, 2) OVER (ORDER BY pay) as second FROM staff"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_and_joins() {
+        assert_eq!(
+            render(
+                "with recursive n (x) as (select 1 from one union all select x + 1 from n \
+                 where x < 10) select x from n cross join lateral (select x * 2 doubled \
+                 from one) d"
+            ),
+            "WITH RECURSIVE n (x) AS (SELECT 1 FROM one UNION ALL SELECT x + 1 FROM n \
+             WHERE x < 10) SELECT x FROM n CROSS JOIN LATERAL \
+             (SELECT x * 2 as doubled FROM one) AS d"
+        );
+        assert_eq!(
+            parse_select("WITH t (a) AS (SELECT 1 FROM one) SELECT a FROM t").map(|_| ()),
+            Err(QueryError::Syntax(
+                "a column list is only supported on a recursive cte".to_owned(),
+                1,
+                16
+            ))
+        );
+        assert_eq!(
+            parse_compound("WITH t AS (SELECT a FROM b) SELECT a FROM t UNION SELECT a FROM t")
+                .map(|_| ()),
+            Err(QueryError::Syntax(
+                "WITH on a compound query is not supported".to_owned(),
+                1,
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_modify_render() {
+        let mut select =
+            parse_select("SELECT id, title FROM documents WHERE archived = false ORDER BY title")
+                .unwrap();
+        select
+            .and_where(Condition::Eq("tenant_id".to_owned(), SqlValue::from(7)))
+            .set_order(|o| o.clear());
+        select.order_by_field_desc("updated_at");
+
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT id, title FROM documents WHERE (archived = false AND tenant_id = 7) \
+             ORDER BY updated_at DESC"
+        );
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let condition = parse_condition("a = 1 or b = 2 and c in ('x', 'y')").unwrap();
+        assert_eq!(
+            condition.build_conditions().unwrap(),
+            "(a = 1 OR (b = 2 AND c IN ('x', 'y')))"
+        );
+        assert!(matches!(
+            parse_condition("100 <= price").unwrap(),
+            Condition::Gte(column, SqlValue::I32(100)) if column == "price"
+        ));
+        assert!(matches!(
+            parse_condition("a.id = b.id").unwrap(),
+            Condition::Native(sql) if sql == "a.id = b.id"
+        ));
+        assert!(matches!(
+            parse_condition("deleted_at = NULL").unwrap(),
+            Condition::Native(sql) if sql == "deleted_at = NULL"
+        ));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = format!("{}a = 1{}", "(".repeat(64), ")".repeat(64));
+        assert!(matches!(
+            parse_condition(&nested).unwrap(),
+            Condition::Eq(column, SqlValue::I32(1)) if column == "a"
+        ));
+
+        for text in [
+            format!("{}a = 1", "(".repeat(100_000)),
+            format!("{}a = 1", "not ".repeat(100_000)),
+            format!("{}a{} = 1", "sum(".repeat(100_000), ")".repeat(100_000)),
+        ] {
+            assert!(matches!(
+                parse_condition(&text),
+                Err(QueryError::Syntax(message, 1, _)) if message == "nesting deeper than 64 levels"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_syntax_error_positions() {
+        assert_eq!(
+            parse_select("SELECT id\nFROM users\nWHERE age >").map(|_| ()),
+            Err(QueryError::Syntax(
+                "expected a value, found end of input".to_owned(),
+                3,
+                12
+            ))
+        );
+        assert_eq!(
+            parse_select("SELECT id FROM users LIMIT 10").map(|_| ()),
+            Err(QueryError::Syntax(
+                "LIMIT is not supported".to_owned(),
+                1,
+                22
+            ))
+        );
+        assert_eq!(
+            parse_select("SELECT id FROM users WHERE id = $1").map(|_| ()),
+            Err(QueryError::Syntax(
+                "bound parameters are not supported".to_owned(),
+                1,
+                33
+            ))
+        );
+        assert_eq!(
+            parse_select("SELECT id FROM users WHERE id IN (SELECT 1)").map(|_| ()),
+            Err(QueryError::Syntax(
+                "subqueries are not supported".to_owned(),
+                1,
+                35
+            ))
+        );
+        assert_eq!(
+            parse_select("SELECT id FROM users u JOIN orders o WHERE o.total > 1").map(|_| ()),
+            Err(QueryError::Syntax(
+                "expected ON, found WHERE".to_owned(),
+                1,
+                38
+            ))
+        );
+        assert_eq!(
+            parse_select("SELECT name FROM users WHERE name = 'ann").map(|_| ()),
+            Err(QueryError::Syntax("unterminated string".to_owned(), 1, 37))
+        );
+        assert_eq!(
+            parse_condition("a = 1 b = 2").map(|_| ()),
+            Err(QueryError::Syntax(
+                "expected end of input, found b".to_owned(),
+                1,
+                7
+            ))
+        );
+    }
+}
@@ -1,5 +1,138 @@
+use std::fmt;
+
+use super::{dialect::Dialect, error::QueryError, value::SqlValue};
+
 pub trait BuildQuery {
-    fn build(&mut self) -> Query;
+    fn build(&mut self) -> Result<Query, QueryError>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    sql: String,
+    fields: Vec<String>, // Output column names, empty when unknown (raw sql or *)
+    params: Vec<SqlValue>, // Values bound to the placeholders in sql, in order
+}
+
+impl Query {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            fields: vec![],
+            params: vec![],
+        }
+    }
+
+    pub fn with_fields(mut self, fields: Vec<String>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    pub fn with_params(mut self, params: Vec<SqlValue>) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn bind(mut self, value: impl Into<SqlValue>) -> Self {
+        self.params.push(value.into());
+        self
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn params(&self) -> &[SqlValue] {
+        &self.params
+    }
+
+    // the sql to nest in another query, its values are appended to params and its
+    // placeholders renumbered to follow the ones already there
+    pub fn embed(&self, dialect: Dialect, params: &mut Vec<SqlValue>) -> String {
+        let offset = params.len();
+        params.extend(self.params.iter().cloned());
+
+        let prefix = match dialect {
+            Dialect::Postgres => "$",
+            Dialect::SqlServer => "@p",
+            Dialect::MySql | Dialect::Sqlite => return self.sql.clone(),
+        };
+
+        if offset == 0 || self.params.is_empty() {
+            return self.sql.clone();
+        }
+
+        let mut sql = String::with_capacity(self.sql.len());
+        let mut chars = self.sql.char_indices();
+        let mut quote = None;
+
+        while let Some((i, c)) = chars.next() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None if self.sql[i..].starts_with(prefix) => {
+                    let digits = self.sql[i + prefix.len()..]
+                        .chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .count();
+                    let number = &self.sql[i + prefix.len()..i + prefix.len() + digits];
+                    if let Ok(index) = number.parse::<usize>() {
+                        sql.push_str(&dialect.placeholder(index + offset));
+                        for _ in 1..prefix.len() + digits {
+                            chars.next();
+                        }
+                        continue;
+                    }
+                }
+                None => {}
+            }
+            sql.push(c);
+        }
+
+        sql
+    }
 }
 
-pub struct Query {}
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_renumbers_placeholders() {
+        let query = Query::new("SELECT id FROM t WHERE a = $1 AND b = '$1' AND c = $2")
+            .bind(1)
+            .bind(2);
+        let mut params = vec![SqlValue::I32(0)];
+        assert_eq!(
+            query.embed(Dialect::Postgres, &mut params),
+            "SELECT id FROM t WHERE a = $2 AND b = '$1' AND c = $3"
+        );
+        assert_eq!(
+            params,
+            vec![SqlValue::I32(0), SqlValue::I32(1), SqlValue::I32(2)]
+        );
+
+        let query = Query::new("SELECT id FROM t WHERE a = @p1").bind(1);
+        assert_eq!(
+            query.embed(Dialect::SqlServer, &mut params),
+            "SELECT id FROM t WHERE a = @p4"
+        );
+
+        let query = Query::new("SELECT id FROM t WHERE a = ?").bind(1);
+        assert_eq!(
+            query.embed(Dialect::Sqlite, &mut params),
+            "SELECT id FROM t WHERE a = ?"
+        );
+        assert_eq!(params.len(), 5);
+    }
+}
@@ -0,0 +1,148 @@
+use super::{dialect::Dialect, error::QueryError, select::Field};
+
+pub trait Returning {
+    fn get_returning(&self) -> Vec<(&Field, &str)>;
+    fn set_returning(&mut self, f: impl FnOnce(&mut Vec<(Field, String)>));
+
+    fn returning(&mut self, columns: &[&str]) -> &mut Self {
+        self.set_returning(|r| {
+            r.extend(
+                columns
+                    .iter()
+                    .map(|&c| (Field::Column(c.to_owned()), String::new())),
+            )
+        });
+        self
+    }
+
+    fn returning_alias(&mut self, column: &str, alias: &str) -> &mut Self {
+        self.set_returning(|r| r.push((Field::Column(column.to_owned()), alias.to_owned())));
+        self
+    }
+
+    fn returning_all(&mut self) -> &mut Self {
+        self.returning(&["*"])
+    }
+
+    // output column names for Query::fields, the same rule Select::build_query follows
+    fn returning_fields(&self) -> Vec<String> {
+        self.get_returning()
+            .iter()
+            .take_while(|(field, _)| !matches!(field, Field::Column(c) if c == "*"))
+            .map(|(field, alias)| match field {
+                Field::Column(column) if alias.is_empty() => column.clone(),
+                _ => alias.to_string(),
+            })
+            .collect()
+    }
+
+    // pseudo_table is INSERTED or DELETED, only sql server uses it (OUTPUT INSERTED.id)
+    fn build_returning(
+        &self,
+        dialect: Dialect,
+        pseudo_table: &str,
+    ) -> Result<Option<String>, QueryError> {
+        let returning = self.get_returning();
+        if returning.is_empty() {
+            return Ok(None);
+        }
+
+        let keyword = match dialect {
+            Dialect::MySql => return Err(QueryError::Unsupported("RETURNING", dialect)),
+            Dialect::SqlServer => "OUTPUT",
+            _ => "RETURNING",
+        };
+
+        let fields = returning
+            .iter()
+            .map(|(field, alias)| {
+                let field = match (dialect, field) {
+                    (Dialect::SqlServer, Field::Column(column)) => {
+                        format!("{}.{}", pseudo_table, column)
+                    }
+                    _ => field.to_sql(dialect)?,
+                };
+                match alias.is_empty() {
+                    true => Ok(field),
+                    false => Ok(format!("{} AS {}", field, alias)),
+                }
+            })
+            .collect::<Result<Vec<String>, QueryError>>()?
+            .join(", ");
+
+        Ok(Some(format!("{} {}", keyword, fields)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct ReturningTest {
+        returning: Vec<(Field, String)>,
+    }
+
+    impl Returning for ReturningTest {
+        fn get_returning(&self) -> Vec<(&Field, &str)> {
+            self.returning
+                .iter()
+                .map(|(f, a)| (f, a.as_str()))
+                .collect()
+        }
+
+        fn set_returning(&mut self, f: impl FnOnce(&mut Vec<(Field, String)>)) {
+            f(&mut self.returning)
+        }
+    }
+
+    #[test]
+    fn test_no_returning() {
+        let returning = ReturningTest::default();
+        assert_eq!(
+            returning.build_returning(Dialect::MySql, "INSERTED"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_returning_per_dialect() {
+        let mut returning = ReturningTest::default();
+        returning
+            .returning(&["id"])
+            .returning_alias("created_at", "created");
+        assert_eq!(
+            returning
+                .build_returning(Dialect::Postgres, "INSERTED")
+                .unwrap()
+                .unwrap(),
+            "RETURNING id, created_at AS created"
+        );
+        assert_eq!(
+            returning
+                .build_returning(Dialect::SqlServer, "DELETED")
+                .unwrap()
+                .unwrap(),
+            "OUTPUT DELETED.id, DELETED.created_at AS created"
+        );
+        assert_eq!(
+            returning.build_returning(Dialect::MySql, "INSERTED"),
+            Err(QueryError::Unsupported("RETURNING", Dialect::MySql))
+        );
+        assert_eq!(returning.returning_fields(), vec!["id", "created"]);
+    }
+
+    #[test]
+    fn test_returning_all() {
+        let mut returning = ReturningTest::default();
+        returning.returning_all();
+        assert_eq!(
+            returning
+                .build_returning(Dialect::SqlServer, "INSERTED")
+                .unwrap()
+                .unwrap(),
+            "OUTPUT INSERTED.*"
+        );
+        assert!(returning.returning_fields().is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+use std::{any, collections::HashMap};
+
+use super::{error::QueryError, value::SqlValue};
+
+pub trait FromSql: Sized {
+    fn from_sql(value: SqlValue) -> Option<Self>;
+}
+
+macro_rules! impl_from_sql_integer {
+    ($($t: ty),+) => {
+        $(
+            impl FromSql for $t {
+                fn from_sql(value: SqlValue) -> Option<Self> {
+                    match value {
+                        SqlValue::I128(val) => val.try_into().ok(),
+                        SqlValue::I64(val) => val.try_into().ok(),
+                        SqlValue::I32(val) | SqlValue::Int(val) => val.try_into().ok(),
+                        SqlValue::U128(val) => val.try_into().ok(),
+                        SqlValue::U64(val) => val.try_into().ok(),
+                        SqlValue::U32(val) => val.try_into().ok(),
+                        SqlValue::Numeric(val) => val.parse().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_from_sql_integer!(i128, i64, i32, u128, u64, u32);
+
+macro_rules! impl_from_sql_float {
+    ($($t: ty),+) => {
+        $(
+            impl FromSql for $t {
+                fn from_sql(value: SqlValue) -> Option<Self> {
+                    match value {
+                        SqlValue::F64(val) => Some(val as $t),
+                        SqlValue::F32(val) | SqlValue::Float(val) => Some(val as $t),
+                        SqlValue::I64(val) => Some(val as $t),
+                        SqlValue::I32(val) | SqlValue::Int(val) => Some(val as $t),
+                        SqlValue::Numeric(val) => val.parse().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_from_sql_float!(f64, f32);
+
+impl FromSql for bool {
+    fn from_sql(value: SqlValue) -> Option<Self> {
+        // sqlite has no boolean type and hands back 0 and 1
+        match value {
+            SqlValue::Bool(val) => Some(val),
+            SqlValue::I64(val) => Some(val != 0),
+            SqlValue::I32(val) | SqlValue::Int(val) => Some(val != 0),
+            _ => None,
+        }
+    }
+}
+
+impl FromSql for String {
+    fn from_sql(value: SqlValue) -> Option<Self> {
+        match value {
+            // exact numerics read as text keep every digit
+            SqlValue::Text(val) | SqlValue::StringDate(val) | SqlValue::Numeric(val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+impl FromSql for Vec<u8> {
+    fn from_sql(value: SqlValue) -> Option<Self> {
+        match value {
+            SqlValue::Bytes(val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+impl FromSql for SqlValue {
+    fn from_sql(value: SqlValue) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl<T: FromSql> FromSql for Option<T> {
+    fn from_sql(value: SqlValue) -> Option<Self> {
+        match value {
+            SqlValue::Null => Some(None),
+            value => T::from_sql(value).map(Some),
+        }
+    }
+}
+
+// a result row looked up by output column name, i.e. the alias given in the select
+pub trait Row {
+    fn get_value(&self, column: &str) -> Result<SqlValue, QueryError>;
+
+    fn try_get<T: FromSql>(&self, column: &str) -> Result<T, QueryError> {
+        T::from_sql(self.get_value(column)?)
+            .ok_or_else(|| QueryError::InvalidType(column.to_owned(), any::type_name::<T>()))
+    }
+}
+
+impl Row for HashMap<String, SqlValue> {
+    fn get_value(&self, column: &str) -> Result<SqlValue, QueryError> {
+        self.get(column)
+            .cloned()
+            .ok_or_else(|| QueryError::MissingColumn(column.to_owned()))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Row for rusqlite::Row<'_> {
+    fn get_value(&self, column: &str) -> Result<SqlValue, QueryError> {
+        use rusqlite::types::ValueRef;
+
+        let value = self
+            .get_ref(column)
+            .map_err(|_| QueryError::MissingColumn(column.to_owned()))?;
+
+        Ok(match value {
+            ValueRef::Null => SqlValue::Null,
+            ValueRef::Integer(val) => SqlValue::I64(val),
+            ValueRef::Real(val) => SqlValue::F64(val),
+            ValueRef::Text(val) => SqlValue::Text(String::from_utf8_lossy(val).into_owned()),
+            ValueRef::Blob(val) => SqlValue::Bytes(val.to_vec()),
+        })
+    }
+}
+
+// the text postgres prints for a date or a timestamp, years before 1 AD are counted back
+// from 1 and marked BC after everything else
+#[cfg(any(feature = "tokio-postgres", feature = "sqlx"))]
+fn postgres_text(date: chrono::NaiveDate, time: Option<(chrono::NaiveTime, &str)>) -> String {
+    use chrono::Datelike;
+
+    let (year, era) = match date.year() {
+        year if year > 0 => (year, ""),
+        year => (1 - year, " BC"),
+    };
+    let date = format!("{:04}-{:02}-{:02}", year, date.month(), date.day());
+
+    match time {
+        None => format!("{}{}", date, era),
+        Some((time, zone)) => format!("{} {}{}{}", date, time_text(time), zone, era),
+    }
+}
+
+// postgres prints the microseconds without trailing zeros
+#[cfg(any(feature = "tokio-postgres", feature = "sqlx"))]
+fn time_text(time: chrono::NaiveTime) -> String {
+    use chrono::Timelike;
+
+    let text = format!(
+        "{:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second()
+    );
+    match time.nanosecond() / 1000 {
+        0 => text,
+        micros => format!(
+            "{}.{}",
+            text,
+            format!("{:06}", micros).trim_end_matches('0')
+        ),
+    }
+}
+
+// each postgres type is read through the driver decoder for it, exact numerics as their text
+#[cfg(feature = "tokio-postgres")]
+impl Row for tokio_postgres::Row {
+    fn get_value(&self, column: &str) -> Result<SqlValue, QueryError> {
+        use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+        use rust_decimal::Decimal;
+        use serde_json::value::RawValue;
+        use tokio_postgres::types::{Date, FromSql, Json, Timestamp, Type};
+        use uuid::Uuid;
+
+        fn get<'a, T: FromSql<'a>>(
+            row: &'a tokio_postgres::Row,
+            index: usize,
+        ) -> Result<Option<T>, QueryError> {
+            Ok(row.try_get::<_, Option<T>>(index)?)
+        }
+
+        fn infinite<T>(value: Timestamp<T>, text: impl Fn(T) -> String) -> String {
+            match value {
+                Timestamp::PosInfinity => "infinity".to_owned(),
+                Timestamp::NegInfinity => "-infinity".to_owned(),
+                Timestamp::Value(value) => text(value),
+            }
+        }
+
+        let index = self
+            .columns()
+            .iter()
+            .position(|c| c.name() == column)
+            .ok_or_else(|| QueryError::MissingColumn(column.to_owned()))?;
+
+        let value = match self.columns()[index].type_() {
+            &Type::BOOL => get(self, index)?.map(SqlValue::Bool),
+            &Type::INT2 => get::<i16>(self, index)?.map(|v| SqlValue::I32(v.into())),
+            &Type::INT4 => get(self, index)?.map(SqlValue::I32),
+            &Type::INT8 => get(self, index)?.map(SqlValue::I64),
+            &Type::FLOAT4 => get(self, index)?.map(SqlValue::F32),
+            &Type::FLOAT8 => get(self, index)?.map(SqlValue::F64),
+            &Type::NUMERIC => {
+                get::<Decimal>(self, index)?.map(|v| SqlValue::Numeric(v.to_string()))
+            }
+            &Type::BYTEA => get(self, index)?.map(SqlValue::Bytes),
+            // "char" is a single byte
+            &Type::CHAR => {
+                get::<i8>(self, index)?.map(|v| SqlValue::Text(char::from(v as u8).to_string()))
+            }
+            &Type::JSON | &Type::JSONB => get::<Json<Box<RawValue>>>(self, index)?
+                .map(|v| SqlValue::Text(v.0.get().to_owned())),
+            &Type::UUID => get::<Uuid>(self, index)?.map(|v| SqlValue::Text(v.to_string())),
+            &Type::DATE => get::<Date<NaiveDate>>(self, index)?.map(|v| {
+                SqlValue::StringDate(match v {
+                    Date::PosInfinity => "infinity".to_owned(),
+                    Date::NegInfinity => "-infinity".to_owned(),
+                    Date::Value(date) => postgres_text(date, None),
+                })
+            }),
+            &Type::TIME => {
+                get::<NaiveTime>(self, index)?.map(|v| SqlValue::StringDate(time_text(v)))
+            }
+            &Type::TIMESTAMP => get::<Timestamp<NaiveDateTime>>(self, index)?.map(|v| {
+                SqlValue::StringDate(infinite(v, |v| {
+                    postgres_text(v.date(), Some((v.time(), "")))
+                }))
+            }),
+            // timestamptz is sent in utc
+            &Type::TIMESTAMPTZ => get::<Timestamp<DateTime<Utc>>>(self, index)?.map(|v| {
+                SqlValue::StringDate(infinite(v, |v| {
+                    let v = v.naive_utc();
+                    postgres_text(v.date(), Some((v.time(), "+00")))
+                }))
+            }),
+            ty if <String as FromSql>::accepts(ty) => get(self, index)?.map(SqlValue::Text),
+            ty => {
+                return Err(QueryError::UnknownSqlType(
+                    column.to_owned(),
+                    ty.name().to_owned(),
+                ))
+            }
+        };
+
+        Ok(value.unwrap_or(SqlValue::Null))
+    }
+}
+
+// each postgres type is read through the sqlx decoder for it, exact numerics as their text
+#[cfg(feature = "sqlx")]
+impl Row for sqlx::postgres::PgRow {
+    fn get_value(&self, column: &str) -> Result<SqlValue, QueryError> {
+        use chrono::TimeDelta;
+        use sqlx::{
+            postgres::PgRow,
+            types::{
+                chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc},
+                Decimal, Json, JsonRawValue, Uuid,
+            },
+            Decode, Postgres, Type, TypeInfo, ValueRef,
+        };
+
+        fn get<'r, T: Decode<'r, Postgres> + Type<Postgres>>(
+            row: &'r PgRow,
+            column: &str,
+        ) -> Result<T, QueryError> {
+            Ok(sqlx::Row::try_get(row, column)?)
+        }
+
+        // sqlx panics on the infinities and on values past the years chrono holds, so the
+        // raw count since 2000-01-01 is checked before decoding
+        fn timestamp(
+            row: &PgRow,
+            column: &str,
+            zone: &str,
+            decode: impl FnOnce() -> Result<NaiveDateTime, QueryError>,
+        ) -> Result<String, QueryError> {
+            let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0));
+            match sqlx::Row::try_get_unchecked::<i64, _>(row, column)? {
+                i64::MAX => Ok("infinity".to_owned()),
+                i64::MIN => Ok("-infinity".to_owned()),
+                micros
+                    if epoch
+                        .and_then(|e| e.checked_add_signed(TimeDelta::microseconds(micros)))
+                        .is_none() =>
+                {
+                    Err(QueryError::Driver(format!("{} is out of range", column)))
+                }
+                _ => {
+                    let value = decode()?;
+                    Ok(postgres_text(value.date(), Some((value.time(), zone))))
+                }
+            }
+        }
+
+        let raw = sqlx::Row::try_get_raw(self, column)
+            .map_err(|_| QueryError::MissingColumn(column.to_owned()))?;
+        if raw.is_null() {
+            return Ok(SqlValue::Null);
+        }
+        let type_info = raw.type_info().into_owned();
+
+        // sqlx reports type names in upper case
+        Ok(match type_info.name() {
+            "BOOL" => SqlValue::Bool(get(self, column)?),
+            "INT2" => SqlValue::I32(get::<i16>(self, column)?.into()),
+            "INT4" => SqlValue::I32(get(self, column)?),
+            "INT8" => SqlValue::I64(get(self, column)?),
+            "FLOAT4" => SqlValue::F32(get(self, column)?),
+            "FLOAT8" => SqlValue::F64(get(self, column)?),
+            "NUMERIC" => SqlValue::Numeric(get::<Decimal>(self, column)?.to_string()),
+            "BYTEA" => SqlValue::Bytes(get(self, column)?),
+            // "char" is a single byte
+            "\"CHAR\"" => SqlValue::Text(char::from(get::<i8>(self, column)? as u8).to_string()),
+            "JSON" | "JSONB" => SqlValue::Text(
+                get::<Json<Box<JsonRawValue>>>(self, column)?
+                    .0
+                    .get()
+                    .to_owned(),
+            ),
+            "UUID" => SqlValue::Text(get::<Uuid>(self, column)?.to_string()),
+            "DATE" => SqlValue::StringDate(
+                match sqlx::Row::try_get_unchecked::<i32, _>(self, column)? {
+                    i32::MAX => "infinity".to_owned(),
+                    i32::MIN => "-infinity".to_owned(),
+                    days => match NaiveDate::from_ymd_opt(2000, 1, 1)
+                        .and_then(|e| e.checked_add_signed(TimeDelta::days(days.into())))
+                    {
+                        Some(_) => postgres_text(get(self, column)?, None),
+                        None => {
+                            return Err(QueryError::Driver(format!("{} is out of range", column)))
+                        }
+                    },
+                },
+            ),
+            "TIME" => SqlValue::StringDate(time_text(get::<NaiveTime>(self, column)?)),
+            "TIMESTAMP" => SqlValue::StringDate(timestamp(self, column, "", || get(self, column))?),
+            // timestamptz is sent in utc
+            "TIMESTAMPTZ" => SqlValue::StringDate(timestamp(self, column, "+00", || {
+                Ok(get::<DateTime<Utc>>(self, column)?.naive_utc())
+            })?),
+            _ if <String as Type<Postgres>>::compatible(&type_info) => {
+                SqlValue::Text(get(self, column)?)
+            }
+            name => {
+                return Err(QueryError::UnknownSqlType(
+                    column.to_owned(),
+                    name.to_ascii_lowercase(),
+                ))
+            }
+        })
+    }
+}
+
+pub trait FromRow: Sized {
+    fn from_row(row: &impl Row) -> Result<Self, QueryError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: Vec<(&str, SqlValue)>) -> HashMap<String, SqlValue> {
+        values
+            .into_iter()
+            .map(|(column, value)| (column.to_owned(), value))
+            .collect()
+    }
+
+    #[test]
+    fn test_row_get() {
+        let row = row(vec![
+            ("id", SqlValue::I64(7)),
+            ("score", SqlValue::I32(3)),
+            ("active", SqlValue::I64(1)),
+            ("email", SqlValue::Null),
+            ("price", SqlValue::Numeric("12.50".to_owned())),
+            ("stock", SqlValue::Numeric("40".to_owned())),
+        ]);
+        assert_eq!(row.try_get::<i32>("id"), Ok(7));
+        assert_eq!(row.try_get::<f64>("price"), Ok(12.5));
+        assert_eq!(row.try_get::<String>("price"), Ok("12.50".to_owned()));
+        assert_eq!(row.try_get::<u32>("stock"), Ok(40));
+        assert!(row.try_get::<i64>("price").is_err());
+        assert_eq!(row.try_get::<f64>("score"), Ok(3.0));
+        assert_eq!(row.try_get::<bool>("active"), Ok(true));
+        assert_eq!(row.try_get::<Option<String>>("email"), Ok(None));
+    }
+
+    #[test]
+    fn test_row_get_errors() {
+        let row = row(vec![
+            ("id", SqlValue::I64(i64::MAX)),
+            ("name", SqlValue::Null),
+        ]);
+        assert_eq!(
+            row.try_get::<i32>("id"),
+            Err(QueryError::InvalidType("id".to_owned(), "i32"))
+        );
+        assert!(matches!(
+            row.try_get::<String>("name"),
+            Err(QueryError::InvalidType(column, _)) if column == "name"
+        ));
+        assert_eq!(
+            row.try_get::<i64>("age"),
+            Err(QueryError::MissingColumn("age".to_owned()))
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, crate::FromRow)]
+    struct Account {
+        id: i64,
+        #[qrex(rename = "user_name")]
+        name: String,
+        email: Option<String>,
+        #[qrex(skip)]
+        cached: bool,
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_from_row() {
+        // keyed by field name, the alias select_struct gives each column
+        let row = row(vec![
+            ("id", SqlValue::I64(1)),
+            ("name", SqlValue::Text("bob".to_owned())),
+            ("email", SqlValue::Null),
+        ]);
+        assert_eq!(
+            Account::from_row(&row),
+            Ok(Account {
+                id: 1,
+                name: "bob".to_owned(),
+                email: None,
+                cached: false,
+            })
+        );
+    }
+
+    #[cfg(all(feature = "sqlite", feature = "derive"))]
+    #[test]
+    fn test_rusqlite_row() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        let account = connection
+            .query_row("SELECT 1 as id, 'bob' as name, NULL as email", [], |row| {
+                Ok(Account::from_row(row))
+            })
+            .unwrap();
+        assert_eq!(
+            account,
+            Ok(Account {
+                id: 1,
+                name: "bob".to_owned(),
+                email: None,
+                cached: false,
+            })
+        );
+
+        let values = connection
+            .query_row("SELECT 2.5 as score, x'00ff' as avatar", [], |row| {
+                Ok((
+                    Row::try_get::<f64>(row, "score"),
+                    Row::try_get::<Vec<u8>>(row, "avatar"),
+                    Row::try_get::<i64>(row, "missing"),
+                ))
+            })
+            .unwrap();
+        assert_eq!(
+            values,
+            (
+                Ok(2.5),
+                Ok(vec![0x00, 0xff]),
+                Err(QueryError::MissingColumn("missing".to_owned()))
+            )
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use std::{fmt, marker::PhantomData};
+
+use super::{condition::Condition, dialect::Dialect, value::SqlValue};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SqlType {
+    Boolean,
+    SmallInt,
+    Integer,
+    BigInt,
+    Real,
+    Double,
+    Numeric(u32, u32), // Takes precision and scale
+    UnboundedNumeric,  // Numeric without a precision, exact at any size
+    Varchar(u32),
+    Text,
+    Blob,
+    Date,
+    Timestamp,
+}
+
+impl SqlType {
+    pub fn to_sql(&self, dialect: Dialect) -> String {
+        match (self, dialect) {
+            (SqlType::Boolean, Dialect::SqlServer) => "BIT".to_owned(),
+            (SqlType::Boolean, _) => "BOOLEAN".to_owned(),
+            (SqlType::SmallInt, _) => "SMALLINT".to_owned(),
+            (SqlType::Integer, Dialect::SqlServer) => "INT".to_owned(),
+            (SqlType::Integer, _) => "INTEGER".to_owned(),
+            (SqlType::BigInt, _) => "BIGINT".to_owned(),
+            (SqlType::Real, Dialect::MySql) => "FLOAT".to_owned(),
+            (SqlType::Real, _) => "REAL".to_owned(),
+            (SqlType::Double, Dialect::Postgres) => "DOUBLE PRECISION".to_owned(),
+            // REAL affinity on sqlite like REAL, the name tells them apart when introspected
+            (SqlType::Double, Dialect::MySql | Dialect::Sqlite) => "DOUBLE".to_owned(),
+            (SqlType::Double, Dialect::SqlServer) => "FLOAT".to_owned(),
+            (SqlType::Numeric(precision, scale), Dialect::MySql) => {
+                format!("DECIMAL({}, {})", precision, scale)
+            }
+            (SqlType::Numeric(precision, scale), _) => {
+                format!("NUMERIC({}, {})", precision, scale)
+            }
+            // mysql and sql server need a precision, take their widest
+            (SqlType::UnboundedNumeric, Dialect::MySql) => "DECIMAL(65, 30)".to_owned(),
+            (SqlType::UnboundedNumeric, Dialect::SqlServer) => "NUMERIC(38, 18)".to_owned(),
+            (SqlType::UnboundedNumeric, _) => "NUMERIC".to_owned(),
+            (SqlType::Varchar(length), Dialect::SqlServer) => format!("NVARCHAR({})", length),
+            (SqlType::Varchar(length), _) => format!("VARCHAR({})", length),
+            (SqlType::Text, Dialect::SqlServer) => "NVARCHAR(MAX)".to_owned(),
+            (SqlType::Text, _) => "TEXT".to_owned(),
+            (SqlType::Blob, Dialect::Postgres) => "BYTEA".to_owned(),
+            (SqlType::Blob, Dialect::SqlServer) => "VARBINARY(MAX)".to_owned(),
+            (SqlType::Blob, _) => "BLOB".to_owned(),
+            (SqlType::Date, _) => "DATE".to_owned(),
+            (SqlType::Timestamp, Dialect::MySql) => "DATETIME".to_owned(),
+            (SqlType::Timestamp, Dialect::SqlServer) => "DATETIME2".to_owned(),
+            (SqlType::Timestamp, _) => "TIMESTAMP".to_owned(),
+        }
+    }
+}
+
+pub trait ColumnType {
+    type Value: Into<SqlValue>;
+    const SQL_TYPE: SqlType;
+    const NULLABLE: bool = false;
+}
+
+macro_rules! impl_column_type {
+    ($($t: ty => $sql_type: expr),+) => {
+        $(
+            impl ColumnType for $t {
+                type Value = $t;
+                const SQL_TYPE: SqlType = $sql_type;
+            }
+        )+
+    };
+}
+
+impl_column_type!(
+    bool => SqlType::Boolean,
+    i32 => SqlType::Integer,
+    i64 => SqlType::BigInt,
+    f32 => SqlType::Real,
+    f64 => SqlType::Double,
+    String => SqlType::Text,
+    Vec<u8> => SqlType::Blob
+);
+
+impl<T: ColumnType> ColumnType for Option<T> {
+    type Value = T::Value;
+    const SQL_TYPE: SqlType = T::SQL_TYPE;
+    const NULLABLE: bool = true;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnDef {
+    name: String,
+    sql_type: SqlType,
+    nullable: bool,
+    default: Option<SqlValue>,
+}
+
+impl ColumnDef {
+    pub fn new(name: &str, sql_type: SqlType) -> Self {
+        Self {
+            name: name.to_owned(),
+            sql_type,
+            nullable: false,
+            default: None,
+        }
+    }
+
+    pub fn nullable(mut self, nullable: bool) -> Self {
+        self.nullable = nullable;
+        self
+    }
+
+    pub fn default(mut self, value: impl Into<SqlValue>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_sql_type(&self) -> SqlType {
+        self.sql_type
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn get_default(&self) -> Option<&SqlValue> {
+        self.default.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableDef {
+    name: String,
+    columns: Vec<ColumnDef>,
+    primary_key: Vec<String>,
+}
+
+impl TableDef {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            columns: vec![],
+            primary_key: vec![],
+        }
+    }
+
+    pub fn column(mut self, column: ColumnDef) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    pub fn primary_key(mut self, columns: &[&str]) -> Self {
+        self.primary_key = columns.iter().map(|&c| c.to_owned()).collect();
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_columns(&self) -> &[ColumnDef] {
+        &self.columns
+    }
+
+    pub fn get_column(&self, name: &str) -> Option<&ColumnDef> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    pub fn get_primary_key(&self) -> Vec<&str> {
+        self.primary_key.iter().map(|c| c.as_str()).collect()
+    }
+}
+
+// implemented by #[derive(Table)], columns() is the insert column list in field order
+pub trait Table {
+    const TABLE_NAME: &'static str;
+
+    fn columns() -> Vec<&'static str>;
+
+    fn primary_key() -> Vec<&'static str>;
+
+    fn table() -> TableDef;
+}
+
+// implemented by #[derive(Selectable)], pairs each column with the field it is read into
+pub trait Selectable {
+    fn select_fields() -> Vec<(&'static str, &'static str)>;
+}
+
+// the rust type parameter is what makes `AGE.gt("thirty")` a compile error
+pub struct Column<T> {
+    table: &'static str,
+    name: &'static str,
+    alias: Option<&'static str>, // Qualifies the column in place of the table name
+    column_type: PhantomData<T>,
+}
+
+impl<T> Clone for Column<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Column<T> {}
+
+impl<T> fmt::Display for Column<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.alias.unwrap_or(self.table), self.name)
+    }
+}
+
+impl<T: ColumnType> Column<T> {
+    pub const fn new(table: &'static str, name: &'static str) -> Self {
+        Self {
+            table,
+            name,
+            alias: None,
+            column_type: PhantomData,
+        }
+    }
+
+    // for queries that alias the table with table_as
+    pub const fn with_alias(self, alias: &'static str) -> Self {
+        Self {
+            alias: Some(alias),
+            ..self
+        }
+    }
+
+    pub fn get_table(&self) -> &'static str {
+        self.table
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn def(&self) -> ColumnDef {
+        ColumnDef::new(self.name, T::SQL_TYPE).nullable(T::NULLABLE)
+    }
+
+    pub fn eq(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Eq(self.to_string(), value.into().into())
+    }
+
+    pub fn neq(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Neq(self.to_string(), value.into().into())
+    }
+
+    pub fn gt(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Gt(self.to_string(), value.into().into())
+    }
+
+    pub fn gte(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Gte(self.to_string(), value.into().into())
+    }
+
+    pub fn lt(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Lt(self.to_string(), value.into().into())
+    }
+
+    pub fn lte(&self, value: impl Into<T::Value>) -> Condition {
+        Condition::Lte(self.to_string(), value.into().into())
+    }
+
+    pub fn between(&self, low: impl Into<T::Value>, high: impl Into<T::Value>) -> Condition {
+        Condition::Between(self.to_string(), low.into().into(), high.into().into())
+    }
+
+    pub fn in_list<V: Into<T::Value>>(&self, values: Vec<V>) -> Condition {
+        Condition::In(
+            self.to_string(),
+            values.into_iter().map(|v| v.into().into()).collect(),
+        )
+    }
+
+    pub fn not_in<V: Into<T::Value>>(&self, values: Vec<V>) -> Condition {
+        Condition::NotIn(
+            self.to_string(),
+            values.into_iter().map(|v| v.into().into()).collect(),
+        )
+    }
+}
+
+impl<T: ColumnType<Value = String>> Column<T> {
+    pub fn like(&self, pattern: &str) -> Condition {
+        Condition::Like(self.to_string(), SqlValue::from_string_slice(pattern))
+    }
+}
+
+impl<T: ColumnType> Column<Option<T>> {
+    pub fn is_null(&self) -> Condition {
+        Condition::Null(self.to_string())
+    }
+
+    pub fn is_not_null(&self) -> Condition {
+        Condition::NotNull(self.to_string())
+    }
+}
+
+/**
+ * declares a module holding one typed constant per column and the table definition
+ *
+ * ```
+ * qrex::table! {
+ *     users {
+ *         id as ID: i64,
+ *         age as AGE: Option<i32> = 18,
+ *     }
+ * }
+ *
+ * users::AGE.gt(30);
+ * ```
+ *
+ * comparing with a value of another type does not compile
+ *
+ * ```compile_fail
+ * qrex::table! {
+ *     users {
+ *         age as AGE: Option<i32>,
+ *     }
+ * }
+ *
+ * users::AGE.gt("thirty");
+ * ```
+ * */
+#[macro_export]
+macro_rules! table {
+    ($table: ident { $($column: ident as $constant: ident : $t: ty $(= $default: expr)?),* $(,)? }) => {
+        pub mod $table {
+            use super::*;
+            use $crate::query_builder::schema::{Column, ColumnType, TableDef};
+
+            pub const TABLE_NAME: &str = stringify!($table);
+
+            $(
+                pub const $constant: Column<$t> = Column::new(TABLE_NAME, stringify!($column));
+            )*
+
+            pub fn table() -> TableDef {
+                TableDef::new(TABLE_NAME)
+                    $(
+                        .column($constant.def()$(.default(<$t as ColumnType>::Value::from($default)))?)
+                    )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::select::{Select, SelectQuery};
+
+    table! {
+        users {
+            id as ID: i64,
+            name as NAME: String,
+            age as AGE: Option<i32> = 18,
+            score as SCORE: f64,
+        }
+    }
+
+    #[test]
+    fn test_typed_comparisons() {
+        assert_eq!(
+            users::AGE.gt(30).build_conditions().unwrap(),
+            "users.age > 30"
+        );
+        assert_eq!(
+            users::NAME.eq("O'Brien").build_conditions().unwrap(),
+            "users.name = 'O''Brien'"
+        );
+        assert_eq!(
+            users::SCORE.between(1.5, 3.0).build_conditions().unwrap(),
+            "users.score BETWEEN 1.5 AND 3"
+        );
+        assert_eq!(
+            users::ID.in_list(vec![1, 2, 3]).build_conditions().unwrap(),
+            "users.id IN (1, 2, 3)"
+        );
+    }
+
+    #[test]
+    fn test_aliased_table_columns() {
+        let mut select = SelectQuery::new();
+        select
+            .table_as(users::TABLE_NAME, "u")
+            .select_fields(&[&users::NAME.with_alias("u").to_string()])
+            .and_where(users::AGE.with_alias("u").gt(30));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT u.name as name FROM users AS u WHERE u.age > 30"
+        );
+        assert_eq!(users::AGE.with_alias("u").get_table(), "users");
+    }
+
+    #[test]
+    fn test_text_and_nullable_only_operators() {
+        let condition = users::NAME.like("J%").and(users::AGE.is_not_null());
+        assert_eq!(
+            condition.build_conditions().unwrap(),
+            "(users.name Like 'J%' AND users.age IS NOT NULL)"
+        );
+    }
+
+    #[test]
+    fn test_table_definition() {
+        let table = users::table();
+        assert_eq!(table.get_name(), "users");
+        assert_eq!(
+            table.get_columns(),
+            &[
+                ColumnDef::new("id", SqlType::BigInt),
+                ColumnDef::new("name", SqlType::Text),
+                ColumnDef::new("age", SqlType::Integer)
+                    .nullable(true)
+                    .default(18),
+                ColumnDef::new("score", SqlType::Double),
+            ]
+        );
+        assert_eq!(users::ID.to_string(), "users.id");
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(crate::Table, crate::Selectable)]
+    #[qrex(table = "accounts")]
+    struct Account {
+        #[qrex(primary_key)]
+        id: i64,
+        #[qrex(rename = "user_name")]
+        name: String,
+        email: Option<String>,
+        #[qrex(skip)]
+        cached: bool,
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(crate::Table)]
+    struct OrderLine {
+        #[qrex(primary_key)]
+        order_id: i64,
+        #[qrex(primary_key)]
+        r#line: i32,
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_table() {
+        assert_eq!(Account::TABLE_NAME, "accounts");
+        assert_eq!(Account::columns(), vec!["id", "user_name", "email"]);
+        assert_eq!(Account::primary_key(), vec!["id"]);
+        assert_eq!(
+            Account::table(),
+            TableDef::new("accounts")
+                .column(ColumnDef::new("id", SqlType::BigInt))
+                .column(ColumnDef::new("user_name", SqlType::Text))
+                .column(ColumnDef::new("email", SqlType::Text).nullable(true))
+                .primary_key(&["id"])
+        );
+        assert_eq!(
+            Account::NAME.eq("bob").build_conditions().unwrap(),
+            "accounts.user_name = 'bob'"
+        );
+        assert_eq!(
+            Account::EMAIL.is_null().build_conditions().unwrap(),
+            "accounts.email IS NULL"
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_table_defaults() {
+        assert_eq!(OrderLine::TABLE_NAME, "order_line");
+        assert_eq!(OrderLine::LINE.to_string(), "order_line.line");
+        assert_eq!(
+            OrderLine::table().get_primary_key(),
+            vec!["order_id", "line"]
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_selectable() {
+        assert_eq!(
+            Account::select_fields(),
+            vec![("id", "id"), ("user_name", "name"), ("email", "email")]
+        );
+    }
+}
@@ -18,15 +18,24 @@ use dyn_clone::DynClone;
 
 use super::{
     condition::{self, Condition},
-    group_by::GroupBy,
-    join::Join,
-    order_by::OrderBy,
+    dialect::Dialect,
+    error::QueryError,
+    group_by::{GroupBy, Grouping},
+    join::{Join, JoinEntry},
+    lock::{Lock, RowLock},
+    order_by::{Order, OrderBy, OrderEntry},
+    query::{BuildQuery, Query},
+    schema::Selectable,
+    value::SqlValue,
+    window::{NamedWindow, Window, WindowExpr},
+    with::{CteEntry, With},
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Agregate {
     Sum(String),
     Count(String),
+    CountAll,
     Max(String),
     Min(String),
     Avg(String),
@@ -35,35 +44,257 @@ pub enum Agregate {
     StdDev(String),
     Var(String),
     StringAgg(String, String), // Takes column and separator as arguments
+    ArrayAgg(String, Vec<(String, Order)>), // Takes column and the inner ordering
+    JsonAgg(String, Vec<(String, Order)>), // Takes column and the inner ordering
+    PercentileCont(f64, String, Order), // Takes fraction, column and ordering
+    PercentileDisc(f64, String, Order), // Takes fraction, column and ordering
+    Distinct(Box<Self>),
+    Filter(Box<Self>, Condition),
+    Grouping(Vec<String>), // Tells which columns a ROLLUP/CUBE row was aggregated over
 }
 
+impl Agregate {
+    pub fn distinct(self) -> Self {
+        Self::Distinct(Box::new(self))
+    }
+
+    pub fn filter(self, condition: Condition) -> Self {
+        Self::Filter(Box::new(self), condition)
+    }
+
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        self.check(dialect, false)?;
+        Ok(self.build(dialect, false, None))
+    }
+
+    // comparisons for HAVING, the aggregate is rendered for the dialect of the query
+    fn compare(&self, condition: Condition) -> Condition {
+        Condition::Agregate(Box::new(self.clone()), Box::new(condition))
+    }
+
+    pub fn eq(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Eq(String::new(), value))
+    }
+
+    pub fn neq(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Neq(String::new(), value))
+    }
+
+    pub fn gt(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Gt(String::new(), value))
+    }
+
+    pub fn gte(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Gte(String::new(), value))
+    }
+
+    pub fn lt(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Lt(String::new(), value))
+    }
+
+    pub fn lte(&self, value: SqlValue) -> Condition {
+        self.compare(Condition::Lte(String::new(), value))
+    }
+
+    pub fn between(&self, low: SqlValue, high: SqlValue) -> Condition {
+        self.compare(Condition::Between(String::new(), low, high))
+    }
+
+    // what the dialect lacks, build renders without looking
+    fn check(&self, dialect: Dialect, distinct: bool) -> Result<(), QueryError> {
+        match (self, dialect) {
+            (Agregate::Distinct(agregate), _) => agregate.check(dialect, true),
+            (Agregate::Filter(agregate, condition), _) => {
+                condition.for_dialect(dialect)?;
+                agregate.check(dialect, distinct)
+            }
+            (Agregate::PercentileCont(..) | Agregate::PercentileDisc(..), Dialect::Postgres) => {
+                Ok(())
+            }
+            (Agregate::PercentileCont(..) | Agregate::PercentileDisc(..), _) => {
+                Err(QueryError::Unsupported("ordered-set aggregate", dialect))
+            }
+            (Agregate::Grouping(_), Dialect::Sqlite) => {
+                Err(QueryError::Unsupported("GROUPING", dialect))
+            }
+            // sqlite's group_concat takes no separator along with DISTINCT
+            (Agregate::StringAgg(..), Dialect::Sqlite) if distinct => Err(QueryError::Unsupported(
+                "GROUP_CONCAT DISTINCT with a separator",
+                dialect,
+            )),
+            (Agregate::ArrayAgg(..), Dialect::Postgres) => Ok(()),
+            (Agregate::ArrayAgg(..), _) => Err(QueryError::Unsupported("ARRAY_AGG", dialect)),
+            (Agregate::JsonAgg(..), Dialect::SqlServer) => {
+                Err(QueryError::Unsupported("JSON_AGG", dialect))
+            }
+            (Agregate::JsonAgg(_, order), Dialect::MySql | Dialect::Sqlite)
+                if !order.is_empty() =>
+            {
+                Err(QueryError::Unsupported("ordered JSON_AGG", dialect))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn build(&self, dialect: Dialect, distinct: bool, filter: Option<&Condition>) -> String {
+        fn order_list(order: &[(String, Order)]) -> String {
+            order
+                .iter()
+                .map(|(field, order)| format!("{} {}", field, order))
+                .collect::<Vec<String>>()
+                .join(", ")
+        }
+
+        // aggregates nested in a filter are rejected by the database anyway
+        fn filter_sql(filter: Option<&Condition>) -> Option<String> {
+            filter.and_then(|condition| condition.build_conditions())
+        }
+
+        let (func, column, tail) = match self {
+            Agregate::Distinct(agregate) => return agregate.build(dialect, true, filter),
+            Agregate::Filter(agregate, condition) => {
+                let condition = match filter {
+                    Some(outer) => outer.clone().and(condition.clone()),
+                    None => condition.clone(),
+                };
+                return agregate.build(dialect, distinct, Some(&condition));
+            }
+            Agregate::PercentileCont(fraction, column, order)
+            | Agregate::PercentileDisc(fraction, column, order) => {
+                let func = match self {
+                    Agregate::PercentileCont(..) => "PERCENTILE_CONT",
+                    _ => "PERCENTILE_DISC",
+                };
+                let mut sql = format!(
+                    "{}({}) WITHIN GROUP (ORDER BY {} {})",
+                    func, fraction, column, order
+                );
+                if let Some(condition) = filter_sql(filter) {
+                    sql.push_str(&format!(" FILTER (WHERE {})", condition));
+                }
+                return sql;
+            }
+            Agregate::Grouping(columns) => {
+                return match dialect {
+                    // sql server's GROUPING takes a single column, GROUPING_ID builds the same mask
+                    Dialect::SqlServer if columns.len() > 1 => {
+                        format!("GROUPING_ID({})", columns.join(", "))
+                    }
+                    _ => format!("GROUPING({})", columns.join(", ")),
+                };
+            }
+            Agregate::Sum(column) => ("SUM", column.as_str(), String::new()),
+            Agregate::Count(column) => ("COUNT", column.as_str(), String::new()),
+            Agregate::CountAll => ("COUNT", "*", String::new()),
+            Agregate::Max(column) => ("MAX", column.as_str(), String::new()),
+            Agregate::Min(column) => ("MIN", column.as_str(), String::new()),
+            Agregate::Avg(column) => ("AVG", column.as_str(), String::new()),
+            Agregate::First(column) => ("FIRST", column.as_str(), String::new()),
+            Agregate::Last(column) => ("LAST", column.as_str(), String::new()),
+            Agregate::StdDev(column) => ("STDDEV", column.as_str(), String::new()),
+            Agregate::Var(column) => ("VAR", column.as_str(), String::new()),
+            Agregate::StringAgg(column, separator) => {
+                let separator = SqlValue::from_string_slice(separator).to_literal(dialect);
+                match dialect {
+                    Dialect::MySql => (
+                        "GROUP_CONCAT",
+                        column.as_str(),
+                        format!(" SEPARATOR {}", separator),
+                    ),
+                    Dialect::Sqlite => {
+                        ("GROUP_CONCAT", column.as_str(), format!(", {}", separator))
+                    }
+                    _ => ("STRING_AGG", column.as_str(), format!(", {}", separator)),
+                }
+            }
+            Agregate::ArrayAgg(column, order) => {
+                let tail = if order.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ORDER BY {}", order_list(order))
+                };
+                ("ARRAY_AGG", column.as_str(), tail)
+            }
+            Agregate::JsonAgg(column, order) => {
+                let func = match dialect {
+                    Dialect::MySql => "JSON_ARRAYAGG",
+                    Dialect::Sqlite => "JSON_GROUP_ARRAY",
+                    _ => "JSON_AGG",
+                };
+                let tail = if order.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ORDER BY {}", order_list(order))
+                };
+                (func, column.as_str(), tail)
+            }
+        };
+
+        // dialects without FILTER get the condition folded into the argument
+        let native_filter = matches!(dialect, Dialect::Postgres | Dialect::Sqlite);
+
+        let filter = filter_sql(filter);
+
+        let argument = match &filter {
+            Some(condition) if !native_filter => {
+                let value = if column == "*" { "1" } else { column };
+                format!("CASE WHEN {} THEN {} END", condition, value)
+            }
+            _ => column.to_owned(),
+        };
+
+        let mut sql = if distinct {
+            format!("{}(DISTINCT {}{})", func, argument, tail)
+        } else {
+            format!("{}({}{})", func, argument, tail)
+        };
+
+        if native_filter {
+            if let Some(condition) = filter {
+                sql.push_str(&format!(" FILTER (WHERE {})", condition));
+            }
+        }
+
+        sql
+    }
+}
+
+// renders for postgres, which takes every aggregate shape, to_sql checks the other dialects
 impl fmt::Display for Agregate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.build(Dialect::Postgres, false, None))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Field {
+    Column(String),
+    Agregate(Agregate),
+    Window(WindowExpr),
+}
+
+impl Field {
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
         match self {
-            Agregate::Sum(column) => write!(f, "SUM({})", column),
-            Agregate::Count(column) => write!(f, "COUNT({})", column),
-            Agregate::Max(column) => write!(f, "MAX({})", column),
-            Agregate::Min(column) => write!(f, "MIN({})", column),
-            Agregate::Avg(column) => write!(f, "AVG({})", column),
-            Agregate::First(column) => write!(f, "FIRST({})", column),
-            Agregate::Last(column) => write!(f, "LAST({})", column),
-            Agregate::StdDev(column) => write!(f, "STDDEV({})", column),
-            Agregate::Var(column) => write!(f, "VAR({})", column),
-            Agregate::StringAgg(column, separator) => {
-                write!(f, "STRING_AGG({}, '{}')", column, separator)
-            }
+            Field::Column(column) => Ok(column.clone()),
+            Field::Agregate(agregate) => agregate.to_sql(dialect),
+            Field::Window(window) => window.to_sql(dialect),
         }
     }
+
+    fn is_column(&self, name: &str) -> bool {
+        matches!(self, Field::Column(column) if column == name)
+    }
 }
 
 pub trait ClonableString: DynClone + ToString {}
 
 impl<T> ClonableString for T where T: Clone + ToString {}
 
-pub trait Select: GroupBy + OrderBy + Join {
-    fn set_fields(&mut self, fields: impl Fn(&mut Vec<(String, String)>));
+pub trait Select: With + GroupBy + NamedWindow + OrderBy + Join + Lock {
+    fn set_fields(&mut self, fields: impl Fn(&mut Vec<(Field, String)>));
 
-    fn get_fields(&self) -> Vec<(&str, &str)>;
+    fn get_fields(&self) -> Vec<(&Field, &str)>;
 
     fn get_condition(&self) -> Option<Condition>;
 
@@ -73,72 +304,143 @@ pub trait Select: GroupBy + OrderBy + Join {
 
     fn get_table(&self) -> String;
 
+    fn set_table_alias(&mut self, alias: &str);
+
+    fn get_table_alias(&self) -> Option<String>;
+
+    fn set_dialect(&mut self, dialect: Dialect);
+
+    fn get_dialect(&self) -> Dialect;
+
+    fn set_functional_dependence(&mut self, allowed: bool);
+
+    fn get_functional_dependence(&self) -> bool;
+
     fn table(&mut self, table: &str) -> &mut Self {
         self.set_table(table);
         self
     }
 
-    fn build_select(&self) -> Option<String> {
-        let fields_vec = self.get_fields();
+    fn table_as(&mut self, table: &str, alias: &str) -> &mut Self {
+        self.set_table(table);
+        self.set_table_alias(alias);
+        self
+    }
+
+    fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.set_dialect(dialect);
+        self
+    }
+
+    // postgres and mysql accept columns functionally dependent on the grouped key
+    fn allow_functional_dependence(&mut self) -> &mut Self {
+        self.set_functional_dependence(true);
+        self
+    }
 
-        if fields_vec.is_empty() {
-            return None;
+    fn check_grouping(&self) -> Result<(), QueryError> {
+        let fields = self.get_fields();
+        let mut group = self.get_group();
+        if let Some(grouping) = self.get_grouping() {
+            group.extend(grouping.fields());
         }
 
-        let fields = if fields_vec[0].0 == "*" {
-            String::from("*")
-        } else {
-            fields_vec
-                .iter()
-                .map(|f| format!("{} as {}", f.0, f.1,))
-                .collect::<Vec<String>>()
-                .join(", ")
-        };
+        let agregated = fields.iter().any(|f| matches!(f.0, Field::Agregate(_)));
+
+        if self.get_functional_dependence() || (group.is_empty() && !agregated) {
+            return Ok(());
+        }
+
+        for (field, alias) in fields.iter() {
+            if let Field::Column(column) = field {
+                if !group.contains(&column.as_str()) && !group.contains(alias) {
+                    return Err(QueryError::UngroupedColumn(column.clone()));
+                }
+            }
+        }
+
+        Ok(())
+    }
 
-        let conditions = ""; //self.build_conditions()?;
+    // the sql alone, build_query keeps the params of ctes and derived tables
+    fn build_select(&self) -> Result<String, QueryError> {
+        Ok(self.build_query()?.sql().to_owned())
+    }
+
+    fn build_query(&self) -> Result<Query, QueryError> {
+        let mut params = vec![];
+        let sql = render_select(self, &mut params, false)?;
+        Ok(Query::new(sql)
+            .with_fields(output_fields(self))
+            .with_params(params))
+    }
 
-        Some(format!(
-            "SELECT {} FROM {} WHERE {}",
-            fields,
-            self.get_table(),
-            conditions
-        ))
+    // like build_query but the WHERE values are bound as params instead of inlined
+    fn build_prepared(&self) -> Result<Query, QueryError> {
+        let mut params = vec![];
+        let sql = render_select(self, &mut params, true)?;
+        Ok(Query::new(sql)
+            .with_fields(output_fields(self))
+            .with_params(params))
     }
 
     fn r#where(&mut self, condition: Condition) {
         self.set_condition(condition);
     }
 
+    // ANDs the condition onto the current one, e.g. a tenant filter on a parsed query
+    fn and_where(&mut self, condition: Condition) -> &mut Self {
+        let condition = match self.get_condition() {
+            Some(current) => current.and(condition),
+            None => condition,
+        };
+        self.set_condition(condition);
+        self
+    }
+
     fn select_all_fields(&mut self) -> &Self {
         let fields = self.get_fields();
         let mut exist = false;
-        if !fields.is_empty() && fields[0].0 == "#**#" {
+        if !fields.is_empty() && fields[0].0.is_column("#**#") {
             exist = true;
         }
 
         self.set_fields(|f| {
             if exist {
                 let item = f.get_mut(0).unwrap();
-                item.0 = "*".to_string();
+                item.0 = Field::Column("*".to_string());
             } else {
-                f.insert(0, (String::from("*"), "".to_string()))
+                f.insert(0, (Field::Column(String::from("*")), "".to_string()))
             }
         });
-        self.set_fields(|f| *f = vec![("*".to_string(), String::new())]);
+        self.set_fields(|f| *f = vec![(Field::Column("*".to_string()), String::new())]);
         self
     }
 
     fn select_alias_field(&mut self, field: impl ClonableString, alias: &str) -> &Self {
         self.set_fields(|f| {
-            (*f).push((dyn_clone::clone_box(&field).to_string(), alias.to_owned()))
+            (*f).push((
+                Field::Column(dyn_clone::clone_box(&field).to_string()),
+                alias.to_owned(),
+            ))
         });
         self
     }
 
+    fn select_agregate(&mut self, agregate: Agregate, alias: &str) -> &mut Self {
+        self.set_fields(|f| (*f).push((Field::Agregate(agregate.clone()), alias.to_owned())));
+        self
+    }
+
+    fn select_window(&mut self, window: WindowExpr, alias: &str) -> &mut Self {
+        self.set_fields(|f| (*f).push((Field::Window(window.clone()), alias.to_owned())));
+        self
+    }
+
     fn alias(&mut self, alias: &str) -> &Self {
         let fields = self.get_fields();
         let mut exist = false;
-        if !fields.is_empty() && fields[0].0 == "*" {
+        if !fields.is_empty() && fields[0].0.is_column("*") {
             exist = true;
         }
 
@@ -147,15 +449,24 @@ pub trait Select: GroupBy + OrderBy + Join {
                 let item = f.get_mut(0).unwrap();
                 item.1 = alias.to_string();
             } else {
-                f.insert(0, (String::from("#**#"), alias.to_string()))
+                f.insert(0, (Field::Column(String::from("#**#")), alias.to_string()))
             }
         });
         self
     }
 
+    // a qualified column is aliased to its column name, u.name as name
     fn select_fields(&mut self, fields: &[&str]) -> &mut Self {
         for &f in fields.iter() {
-            self.select_alias_field(f, f);
+            let alias = f.rsplit('.').next().unwrap_or(f);
+            self.select_alias_field(f, alias);
+        }
+        self
+    }
+
+    fn select_struct<T: Selectable>(&mut self) -> &mut Self {
+        for (column, alias) in T::select_fields() {
+            self.select_alias_field(column, alias);
         }
         self
     }
@@ -171,3 +482,801 @@ pub trait Select: GroupBy + OrderBy + Join {
     //     //fields.push("");
     // }
 }
+
+// the Select every mixin is implemented for, what parser::parse_select returns
+#[derive(Default)]
+pub struct SelectQuery {
+    fields: Vec<(Field, String)>,
+    condition: Option<Condition>,
+    table: String,
+    table_alias: Option<String>,
+    dialect: Dialect,
+    functional_dependence: bool,
+    joins: Vec<JoinEntry>,
+    ctes: Vec<CteEntry>,
+    windows: Vec<(String, Window)>,
+    groups: Vec<String>,
+    grouping: Option<Grouping>,
+    having: Option<Condition>,
+    order: Vec<OrderEntry>,
+    lock: Option<RowLock>,
+}
+
+impl SelectQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Select for SelectQuery {
+    fn set_fields(&mut self, fields: impl Fn(&mut Vec<(Field, String)>)) {
+        fields(&mut self.fields)
+    }
+
+    fn get_fields(&self) -> Vec<(&Field, &str)> {
+        self.fields.iter().map(|(f, a)| (f, a.as_str())).collect()
+    }
+
+    fn get_condition(&self) -> Option<Condition> {
+        self.condition.clone()
+    }
+
+    fn set_condition(&mut self, condition: Condition) {
+        self.condition = Some(condition)
+    }
+
+    fn set_table(&mut self, table: &str) {
+        self.table = table.to_owned()
+    }
+
+    fn get_table(&self) -> String {
+        self.table.clone()
+    }
+
+    fn set_table_alias(&mut self, alias: &str) {
+        self.table_alias = Some(alias.to_owned())
+    }
+
+    fn get_table_alias(&self) -> Option<String> {
+        self.table_alias.clone()
+    }
+
+    fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect
+    }
+
+    fn get_dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    fn set_functional_dependence(&mut self, allowed: bool) {
+        self.functional_dependence = allowed
+    }
+
+    fn get_functional_dependence(&self) -> bool {
+        self.functional_dependence
+    }
+}
+
+impl With for SelectQuery {
+    fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>)) {
+        f(&mut self.ctes)
+    }
+
+    fn get_with(&self) -> Vec<&CteEntry> {
+        self.ctes.iter().collect()
+    }
+}
+
+impl GroupBy for SelectQuery {
+    fn get_group(&self) -> Vec<&str> {
+        self.groups.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn set_group(&mut self, group: Vec<String>) {
+        self.groups = group
+    }
+
+    fn get_grouping(&self) -> Option<&Grouping> {
+        self.grouping.as_ref()
+    }
+
+    fn set_grouping(&mut self, grouping: Grouping) {
+        self.grouping = Some(grouping)
+    }
+
+    fn get_having_condition(&self) -> Option<&Condition> {
+        self.having.as_ref()
+    }
+
+    fn set_having_condition(&mut self, condition: Condition) {
+        self.having = Some(condition)
+    }
+}
+
+impl NamedWindow for SelectQuery {
+    fn set_window(&mut self, f: impl FnOnce(&mut Vec<(String, Window)>)) {
+        f(&mut self.windows)
+    }
+
+    fn get_window(&self) -> Vec<(&str, &Window)> {
+        self.windows.iter().map(|(n, w)| (n.as_str(), w)).collect()
+    }
+}
+
+impl OrderBy for SelectQuery {
+    fn get_order(&self) -> Vec<&OrderEntry> {
+        self.order.iter().collect()
+    }
+
+    fn set_order(&mut self, f: impl FnOnce(&mut Vec<OrderEntry>)) {
+        f(&mut self.order)
+    }
+}
+
+impl Join for SelectQuery {
+    fn set_join(&mut self, f: impl FnOnce(&mut Vec<JoinEntry>)) {
+        f(&mut self.joins)
+    }
+
+    fn get_join(&self) -> Vec<&JoinEntry> {
+        self.joins.iter().collect()
+    }
+}
+
+impl Lock for SelectQuery {
+    fn get_lock(&self) -> Option<&RowLock> {
+        self.lock.as_ref()
+    }
+
+    fn set_lock(&mut self, f: impl FnOnce(&mut Option<RowLock>)) {
+        f(&mut self.lock)
+    }
+}
+
+impl BuildQuery for SelectQuery {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_query()
+    }
+}
+
+fn output_fields<S: Select + ?Sized>(select: &S) -> Vec<String> {
+    select
+        .get_fields()
+        .iter()
+        .take_while(|f| !f.0.is_column("*"))
+        .map(|f| match f.0 {
+            Field::Column(column) if f.1.is_empty() => column.clone(),
+            _ => f.1.to_owned(),
+        })
+        .collect()
+}
+
+// params collects the values of nested queries in sql order, and the WHERE ones when bound
+fn render_select<S: Select + ?Sized>(
+    select: &S,
+    params: &mut Vec<SqlValue>,
+    bind_where: bool,
+) -> Result<String, QueryError> {
+    let fields_vec = select.get_fields();
+
+    if fields_vec.is_empty() {
+        return Err(QueryError::NoFields);
+    }
+
+    let table = select.get_table();
+
+    if table.is_empty() {
+        return Err(QueryError::NoTable);
+    }
+
+    select.check_grouping()?;
+
+    let fields = if fields_vec[0].0.is_column("*") {
+        String::from("*")
+    } else {
+        fields_vec
+            .iter()
+            .map(|f| match f.0.to_sql(select.get_dialect())? {
+                field if f.1.is_empty() => Ok(field),
+                field => Ok(format!("{} as {}", field, f.1)),
+            })
+            .collect::<Result<Vec<String>, QueryError>>()?
+            .join(", ")
+    };
+
+    let mut query = match select.build_with(select.get_dialect(), params) {
+        Some(ctes) => format!("{} SELECT {} FROM {}", ctes, fields, table),
+        None => format!("SELECT {} FROM {}", fields, table),
+    };
+
+    if let Some(alias) = select.get_table_alias() {
+        query.push_str(&format!(" AS {}", alias));
+    }
+
+    let lock = select.build_lock(select.get_dialect())?;
+
+    if let (Some(hints), Dialect::SqlServer) = (&lock, select.get_dialect()) {
+        query.push_str(&format!(" {}", hints));
+    }
+
+    if let Some(joins) = select.build_join(select.get_dialect(), params)? {
+        query.push_str(&format!(" {}", joins));
+    }
+
+    let conditions = match select.get_condition() {
+        Some(c) => {
+            let c = c.for_dialect(select.get_dialect())?;
+            match bind_where {
+                true => Some(c.build_parameterized(select.get_dialect(), params)),
+                false => c.build_conditions(),
+            }
+        }
+        None => None,
+    };
+
+    if let Some(conditions) = conditions {
+        query.push_str(&format!(" WHERE {}", conditions));
+    }
+
+    if let Some(group) = select.build_group_by(select.get_dialect())? {
+        query.push_str(&format!(" {}", group));
+    }
+
+    if let Some(windows) = select.build_window(select.get_dialect())? {
+        query.push_str(&format!(" {}", windows));
+    }
+
+    if let Some(order) = select.build_order_by(select.get_dialect()) {
+        query.push_str(&format!(" {}", order));
+    }
+
+    if let (Some(lock), false) = (&lock, select.get_dialect() == Dialect::SqlServer) {
+        query.push_str(&format!(" {}", lock));
+    }
+
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::{join::JoinType, window::WindowFunction};
+
+    #[test]
+    fn test_select_without_fields() {
+        let mut select = SelectQuery::new();
+        select.table("users");
+        assert_eq!(select.build_select(), Err(QueryError::NoFields));
+    }
+
+    #[test]
+    fn test_select_with_where() {
+        let mut select = SelectQuery::new();
+        select.table("users").select_fields(&["id", "name"]);
+        select.r#where(Condition::Gt("age".to_owned(), SqlValue::Int(30)));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT id as id, name as name FROM users WHERE age > 30"
+        );
+    }
+
+    #[test]
+    fn test_select_self_join_with_aliases() {
+        let mut select = SelectQuery::new();
+        select
+            .table_as("users", "u")
+            .select_fields(&["u.name"])
+            .select_alias_field("m.name", "manager");
+        select.join_as(
+            JoinType::Left,
+            "users",
+            "m",
+            Some(Condition::Native("m.id = u.manager_id".to_owned())),
+        );
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT u.name as name, m.name as manager FROM users AS u LEFT JOIN users AS m ON m.id = u.manager_id"
+        );
+    }
+
+    #[test]
+    fn test_select_top_n_per_group_with_lateral() {
+        let mut select = SelectQuery::new();
+        select
+            .table_as("users", "u")
+            .select_alias_field("u.id", "user_id");
+        select.select_alias_field("recent.total", "total");
+        select.join_lateral(
+            JoinType::Inner,
+            Query::new("SELECT total FROM orders WHERE orders.user_id = u.id ORDER BY created_at DESC LIMIT 3"),
+            "recent",
+            None,
+        );
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT u.id as user_id, recent.total as total FROM users AS u INNER JOIN LATERAL (SELECT total FROM orders WHERE orders.user_id = u.id ORDER BY created_at DESC LIMIT 3) AS recent ON TRUE"
+        );
+
+        select.dialect(Dialect::Sqlite);
+        assert_eq!(
+            select.build_select(),
+            Err(QueryError::Unsupported("LATERAL join", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_select_from_recursive_cte() {
+        let mut select = SelectQuery::new();
+        select
+            .with_recursive(
+                "tree",
+                &["id", "parent_id"],
+                Query::new("SELECT id, parent_id FROM categories WHERE parent_id IS NULL"),
+                Query::new("SELECT c.id, c.parent_id FROM categories AS c INNER JOIN tree ON c.parent_id = tree.id"),
+            )
+            .table("tree")
+            .select_all_fields();
+        assert_eq!(
+            select.build_select().unwrap(),
+            "WITH RECURSIVE tree (id, parent_id) AS (SELECT id, parent_id FROM categories WHERE parent_id IS NULL UNION ALL SELECT c.id, c.parent_id FROM categories AS c INNER JOIN tree ON c.parent_id = tree.id) SELECT * FROM tree"
+        );
+    }
+
+    #[test]
+    fn test_build_query_keeps_aliases() {
+        let mut select = SelectQuery::new();
+        select.table("users").select_alias_field("id", "user_id");
+        select.select_alias_field("name", "");
+        let query = select.build_query().unwrap();
+        assert_eq!(query.fields(), &["user_id".to_owned(), "name".to_owned()]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_select_struct() {
+        #[derive(crate::Selectable)]
+        struct Account {
+            id: i64,
+            #[qrex(rename = "user_name")]
+            name: String,
+            #[qrex(skip)]
+            cached: bool,
+        }
+
+        let mut select = SelectQuery::new();
+        select.table("accounts").select_struct::<Account>();
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT id as id, user_name as name FROM accounts"
+        );
+        assert_eq!(
+            select.build_query().unwrap().fields(),
+            &["id".to_owned(), "name".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_build_prepared_binds_where_values() {
+        let mut select = SelectQuery::new();
+        select
+            .table("users")
+            .select_fields(&["id"])
+            .dialect(Dialect::Postgres)
+            .r#where(
+                Condition::Eq("name".to_owned(), SqlValue::from_string_slice("bob"))
+                    .and(Condition::Gt("age".to_owned(), SqlValue::Int(18))),
+            );
+        let query = select.build_prepared().unwrap();
+        assert_eq!(
+            query.sql(),
+            "SELECT id as id FROM users WHERE (name = $1 AND age > $2)"
+        );
+        assert_eq!(
+            query.params(),
+            &[SqlValue::from_string_slice("bob"), SqlValue::Int(18)]
+        );
+        assert_eq!(query.fields(), &["id".to_owned()]);
+        assert!(select.build_query().unwrap().params().is_empty());
+    }
+
+    #[test]
+    fn test_build_prepared_numbers_cte_and_derived_table_params() {
+        let mut active = SelectQuery::new();
+        active
+            .table("users")
+            .select_fields(&["id"])
+            .r#where(Condition::Eq("status".to_owned(), SqlValue::from("active")));
+
+        let mut totals = SelectQuery::new();
+        totals
+            .table("orders")
+            .select_fields(&["user_id"])
+            .select_agregate(Agregate::Sum("total".to_owned()), "total")
+            .r#where(Condition::Gt("total".to_owned(), SqlValue::I32(10)));
+        totals.group_by_field("user_id");
+
+        let mut select = SelectQuery::new();
+        select
+            .with("active", active.build_prepared().unwrap())
+            .table("active")
+            .select_fields(&["active.id", "t.total"])
+            .join_subquery(
+                JoinType::Inner,
+                totals.build_prepared().unwrap(),
+                "t",
+                Some(Condition::Native("t.user_id = active.id".to_owned())),
+            );
+        select.r#where(Condition::Lt("t.total".to_owned(), SqlValue::I32(500)));
+
+        let query = select.build_prepared().unwrap();
+        assert_eq!(
+            query.sql(),
+            "WITH active AS (SELECT id as id FROM users WHERE status = $1) SELECT active.id as id, t.total as total FROM active INNER JOIN (SELECT user_id as user_id, SUM(total) as total FROM orders WHERE total > $2 GROUP BY user_id) AS t ON t.user_id = active.id WHERE t.total < $3"
+        );
+        assert_eq!(
+            query.params(),
+            &[
+                SqlValue::from("active"),
+                SqlValue::I32(10),
+                SqlValue::I32(500)
+            ]
+        );
+        assert_eq!(
+            select.build_query().unwrap().params(),
+            &[SqlValue::from("active"), SqlValue::I32(10)]
+        );
+    }
+
+    #[test]
+    fn test_select_job_queue_skip_locked() {
+        let mut select = SelectQuery::new();
+        select
+            .table_as("jobs", "j")
+            .select_alias_field("j.id", "id");
+        select.r#where(Condition::Eq(
+            "j.status".to_owned(),
+            SqlValue::from_string_slice("queued"),
+        ));
+        select.order_by_field_asc("j.id").for_update().skip_locked();
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT j.id as id FROM jobs AS j WHERE j.status = 'queued' ORDER BY j.id ASC FOR UPDATE SKIP LOCKED"
+        );
+        select.dialect(Dialect::SqlServer);
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT j.id as id FROM jobs AS j WITH (UPDLOCK, ROWLOCK, READPAST) WHERE j.status = 'queued' ORDER BY j.id ASC"
+        );
+        select.dialect(Dialect::Sqlite);
+        assert_eq!(
+            select.build_select(),
+            Err(QueryError::Unsupported("row locking", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_select_ranking_with_named_window() {
+        let mut select = SelectQuery::new();
+        select
+            .table("scores")
+            .window(
+                "w",
+                Window::new()
+                    .partition_by(&["team"])
+                    .order_by("points", Order::Desc),
+            )
+            .select_fields(&["player"])
+            .select_window(WindowFunction::Rank.over_named("w"), "position");
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT player as player, RANK() OVER w as position FROM scores WINDOW w AS (PARTITION BY team ORDER BY points DESC)"
+        );
+    }
+
+    #[test]
+    fn test_agregate_plain() {
+        assert_eq!(
+            Agregate::Sum("amount".to_owned()).to_string(),
+            "SUM(amount)"
+        );
+        assert_eq!(Agregate::CountAll.to_string(), "COUNT(*)");
+    }
+
+    #[test]
+    fn test_agregate_distinct() {
+        let agregate = Agregate::Count("user_id".to_owned()).distinct();
+        assert_eq!(agregate.to_string(), "COUNT(DISTINCT user_id)");
+        assert_eq!(
+            agregate.to_sql(Dialect::SqlServer).unwrap(),
+            "COUNT(DISTINCT user_id)"
+        );
+    }
+
+    #[test]
+    fn test_agregate_filter() {
+        let agregate = Agregate::CountAll.filter(Condition::Eq(
+            "status".to_owned(),
+            SqlValue::from_string_slice("paid"),
+        ));
+        assert_eq!(
+            agregate.to_sql(Dialect::Postgres).unwrap(),
+            "COUNT(*) FILTER (WHERE status = 'paid')"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::Sqlite).unwrap(),
+            "COUNT(*) FILTER (WHERE status = 'paid')"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::MySql).unwrap(),
+            "COUNT(CASE WHEN status = 'paid' THEN 1 END)"
+        );
+    }
+
+    #[test]
+    fn test_agregate_distinct_filter_fallback() {
+        let agregate = Agregate::Sum("amount".to_owned())
+            .distinct()
+            .filter(Condition::Gt("amount".to_owned(), SqlValue::Int(0)));
+        assert_eq!(
+            agregate.to_sql(Dialect::SqlServer).unwrap(),
+            "SUM(DISTINCT CASE WHEN amount > 0 THEN amount END)"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::Postgres).unwrap(),
+            "SUM(DISTINCT amount) FILTER (WHERE amount > 0)"
+        );
+    }
+
+    #[test]
+    fn test_string_agg_per_dialect() {
+        let agregate = Agregate::StringAgg("name".to_owned(), "', '".to_owned());
+        assert_eq!(
+            agregate.to_sql(Dialect::Postgres).unwrap(),
+            "STRING_AGG(name, ''', ''')"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::MySql).unwrap(),
+            "GROUP_CONCAT(name SEPARATOR ''', ''')"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::Sqlite).unwrap(),
+            "GROUP_CONCAT(name, ''', ''')"
+        );
+
+        let agregate = Agregate::StringAgg("path".to_owned(), "\\".to_owned());
+        assert_eq!(
+            agregate.to_sql(Dialect::MySql).unwrap(),
+            "GROUP_CONCAT(path SEPARATOR '\\\\')"
+        );
+        assert_eq!(
+            agregate.to_sql(Dialect::Postgres).unwrap(),
+            "STRING_AGG(path, '\\')"
+        );
+        assert_eq!(
+            agregate.clone().distinct().to_sql(Dialect::Sqlite),
+            Err(QueryError::Unsupported(
+                "GROUP_CONCAT DISTINCT with a separator",
+                Dialect::Sqlite
+            ))
+        );
+        assert_eq!(
+            agregate.distinct().to_string(),
+            "STRING_AGG(DISTINCT path, '\\')"
+        );
+    }
+
+    #[test]
+    fn test_array_and_json_agg_with_order() {
+        let order = vec![("created_at".to_owned(), Order::Desc)];
+        assert_eq!(
+            Agregate::ArrayAgg("id".to_owned(), order.clone()).to_string(),
+            "ARRAY_AGG(id ORDER BY created_at DESC)"
+        );
+        assert_eq!(
+            Agregate::JsonAgg("tag".to_owned(), order.clone()).to_string(),
+            "JSON_AGG(tag ORDER BY created_at DESC)"
+        );
+        assert_eq!(
+            Agregate::JsonAgg("tag".to_owned(), vec![])
+                .to_sql(Dialect::MySql)
+                .unwrap(),
+            "JSON_ARRAYAGG(tag)"
+        );
+        assert_eq!(
+            Agregate::JsonAgg("tag".to_owned(), order).to_sql(Dialect::MySql),
+            Err(QueryError::Unsupported("ordered JSON_AGG", Dialect::MySql))
+        );
+        assert_eq!(
+            Agregate::ArrayAgg("id".to_owned(), vec![]).to_sql(Dialect::Sqlite),
+            Err(QueryError::Unsupported("ARRAY_AGG", Dialect::Sqlite))
+        );
+    }
+
+    #[test]
+    fn test_percentile_within_group() {
+        assert_eq!(
+            Agregate::PercentileCont(0.5, "latency".to_owned(), Order::Asc).to_string(),
+            "PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY latency ASC)"
+        );
+        assert_eq!(
+            Agregate::PercentileDisc(0.9, "latency".to_owned(), Order::Desc).to_sql(Dialect::MySql),
+            Err(QueryError::Unsupported(
+                "ordered-set aggregate",
+                Dialect::MySql
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_agregates_with_group() {
+        let mut select = SelectQuery::new();
+        select
+            .table("employees")
+            .select_fields(&["department"])
+            .select_agregate(Agregate::CountAll, "total")
+            .group_by_field("department")
+            .having(Condition::Native("COUNT(*) > 5".to_owned()));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT department as department, COUNT(*) as total FROM employees GROUP BY department HAVING COUNT(*) > 5"
+        );
+    }
+
+    #[test]
+    fn test_select_agregate_per_dialect() {
+        let mut select = SelectQuery::new();
+        select
+            .table("tags")
+            .dialect(Dialect::MySql)
+            .select_agregate(
+                Agregate::StringAgg("name".to_owned(), ",".to_owned()),
+                "names",
+            );
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT GROUP_CONCAT(name SEPARATOR ',') as names FROM tags"
+        );
+        assert_eq!(
+            select.build_query().unwrap().fields(),
+            &["names".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_select_ungrouped_column() {
+        let mut select = SelectQuery::new();
+        select
+            .table("employees")
+            .select_fields(&["department", "name"])
+            .select_agregate(Agregate::Sum("salary".to_owned()), "payroll")
+            .group_by_field("department");
+        assert_eq!(
+            select.build_select(),
+            Err(QueryError::UngroupedColumn("name".to_owned()))
+        );
+
+        select.allow_functional_dependence();
+        assert!(select.build_select().is_ok());
+    }
+
+    #[test]
+    fn test_select_agregate_without_group() {
+        let mut select = SelectQuery::new();
+        select
+            .table("orders")
+            .select_fields(&["customer_id"])
+            .select_agregate(Agregate::Max("total".to_owned()), "biggest");
+        assert_eq!(
+            select.build_select(),
+            Err(QueryError::UngroupedColumn("customer_id".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_select_group_by_alias() {
+        let mut select = SelectQuery::new();
+        select
+            .table("orders")
+            .select_alias_field("DATE(created_at)", "day");
+        select
+            .select_agregate(Agregate::CountAll, "orders")
+            .group_by_field("day");
+        assert!(select.build_select().is_ok());
+    }
+
+    #[test]
+    fn test_select_subtotals_with_grouping() {
+        let mut select = SelectQuery::new();
+        select
+            .table("ledger")
+            .select_fields(&["account", "month"])
+            .select_agregate(Agregate::Sum("amount".to_owned()), "total")
+            .select_agregate(
+                Agregate::Grouping(vec!["account".to_owned(), "month".to_owned()]),
+                "level",
+            )
+            .rollup(&["account", "month"])
+            .having(Condition::Native("SUM(amount) <> 0".to_owned()));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT account as account, month as month, SUM(amount) as total, GROUPING(account, month) as level FROM ledger GROUP BY ROLLUP (account, month) HAVING SUM(amount) <> 0"
+        );
+
+        select.dialect(Dialect::SqlServer);
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT account as account, month as month, SUM(amount) as total, GROUPING_ID(account, month) as level FROM ledger GROUP BY ROLLUP (account, month) HAVING SUM(amount) <> 0"
+        );
+    }
+
+    #[test]
+    fn test_select_group_by_and_having_independently() {
+        let mut select = SelectQuery::new();
+        select
+            .table("orders")
+            .select_fields(&["customer_id"])
+            .select_agregate(Agregate::CountAll, "orders")
+            .group_by_field("customer_id");
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT customer_id as customer_id, COUNT(*) as orders FROM orders GROUP BY customer_id"
+        );
+
+        select.having(Agregate::CountAll.gte(SqlValue::Int(3)));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT customer_id as customer_id, COUNT(*) as orders FROM orders GROUP BY customer_id HAVING COUNT(*) >= 3"
+        );
+    }
+
+    #[test]
+    fn test_select_having_agregate_per_dialect() {
+        let paid =
+            Agregate::CountAll.filter(Condition::Eq("paid".to_owned(), SqlValue::Bool(true)));
+        let mut select = SelectQuery::new();
+        select
+            .table("orders")
+            .dialect(Dialect::MySql)
+            .select_fields(&["customer_id"])
+            .group_by_field("customer_id")
+            .having(paid.gt(SqlValue::Int(1)));
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT customer_id as customer_id FROM orders GROUP BY customer_id HAVING COUNT(CASE WHEN paid = true THEN 1 END) > 1"
+        );
+
+        select.dialect(Dialect::Postgres);
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT customer_id as customer_id FROM orders GROUP BY customer_id HAVING COUNT(*) FILTER (WHERE paid = true) > 1"
+        );
+        assert!(matches!(
+            Agregate::PercentileCont(0.5, "total".to_owned(), Order::Asc)
+                .gt(SqlValue::Int(10))
+                .for_dialect(Dialect::Sqlite),
+            Err(QueryError::Unsupported(
+                "ordered-set aggregate",
+                Dialect::Sqlite
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_select_order_by_after_group() {
+        let mut select = SelectQuery::new();
+        select
+            .table("orders")
+            .dialect(Dialect::MySql)
+            .select_fields(&["customer_id"])
+            .select_agregate(Agregate::Max("shipped_at".to_owned()), "last_shipped")
+            .group_by_field("customer_id")
+            .order_by_expression("MAX(shipped_at)", Order::Desc)
+            .nulls_last()
+            .order_by_field_asc("customer_id");
+        assert_eq!(
+            select.build_select().unwrap(),
+            "SELECT customer_id as customer_id, MAX(shipped_at) as last_shipped FROM orders GROUP BY customer_id ORDER BY MAX(shipped_at) IS NULL ASC, MAX(shipped_at) DESC, customer_id ASC"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    future::{ready, Future},
+    sync::{Mutex, PoisonError},
+};
+
+use rusqlite::{
+    params_from_iter,
+    types::{ToSqlOutput, Value},
+    Connection, ToSql,
+};
+
+use super::{
+    error::QueryError,
+    executor::{Executor, SingleConnection},
+    query::Query,
+    row::FromRow,
+    value::SqlValue,
+};
+
+impl From<rusqlite::Error> for QueryError {
+    fn from(error: rusqlite::Error) -> Self {
+        QueryError::Driver(error.to_string())
+    }
+}
+
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        fn integer<T: TryInto<i64>>(val: T) -> rusqlite::Result<Value>
+        where
+            T::Error: std::error::Error + Send + Sync + 'static,
+        {
+            val.try_into()
+                .map(Value::Integer)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+        }
+
+        // sqlite stores every integer as i64, bools included
+        let value = match self {
+            SqlValue::I128(val) => integer(*val)?,
+            SqlValue::U128(val) => integer(*val)?,
+            SqlValue::U64(val) => integer(*val)?,
+            SqlValue::I64(val) => Value::Integer(*val),
+            SqlValue::I32(val) | SqlValue::Int(val) => Value::Integer(*val as i64),
+            SqlValue::U32(val) => Value::Integer(*val as i64),
+            SqlValue::Bool(val) => Value::Integer(*val as i64),
+            SqlValue::F64(val) => Value::Real(*val),
+            SqlValue::F32(val) | SqlValue::Float(val) => Value::Real(*val as f64),
+            // numeric affinity turns the text back into a number
+            SqlValue::Text(val) | SqlValue::StringDate(val) | SqlValue::Numeric(val) => {
+                Value::Text(val.clone())
+            }
+            SqlValue::Bytes(val) => Value::Blob(val.clone()),
+            SqlValue::Null => Value::Null,
+        };
+
+        Ok(ToSqlOutput::Owned(value))
+    }
+}
+
+// runs a built Query on a connection, binding Query::params in order
+pub trait SqliteExecutor {
+    fn execute_query(&self, query: &Query) -> Result<usize, QueryError>;
+
+    fn fetch_all<T: FromRow>(&self, query: &Query) -> Result<Vec<T>, QueryError>;
+
+    fn fetch_optional<T: FromRow>(&self, query: &Query) -> Result<Option<T>, QueryError>;
+
+    fn fetch_one<T: FromRow>(&self, query: &Query) -> Result<T, QueryError> {
+        self.fetch_optional(query)?.ok_or(QueryError::RowNotFound)
+    }
+}
+
+impl SqliteExecutor for Connection {
+    fn execute_query(&self, query: &Query) -> Result<usize, QueryError> {
+        Ok(self.execute(query.sql(), params_from_iter(query.params()))?)
+    }
+
+    fn fetch_all<T: FromRow>(&self, query: &Query) -> Result<Vec<T>, QueryError> {
+        let mut statement = self.prepare(query.sql())?;
+        let mut rows = statement.query(params_from_iter(query.params()))?;
+
+        let mut result = vec![];
+        while let Some(row) = rows.next()? {
+            result.push(T::from_row(row)?);
+        }
+
+        Ok(result)
+    }
+
+    fn fetch_optional<T: FromRow>(&self, query: &Query) -> Result<Option<T>, QueryError> {
+        let mut statement = self.prepare(query.sql())?;
+        let mut rows = statement.query(params_from_iter(query.params()))?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(T::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+// the async Executor over a blocking connection, each call runs to completion before returning
+impl Executor for Mutex<Connection> {
+    fn execute(&self, query: &Query) -> impl Future<Output = Result<u64, QueryError>> + Send {
+        let connection = self.lock().unwrap_or_else(PoisonError::into_inner);
+        ready(connection.execute_query(query).map(|count| count as u64))
+    }
+
+    fn fetch_all<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Vec<T>, QueryError>> + Send {
+        let connection = self.lock().unwrap_or_else(PoisonError::into_inner);
+        ready(SqliteExecutor::fetch_all(&*connection, query))
+    }
+
+    fn fetch_optional<T: FromRow + Send>(
+        &self,
+        query: &Query,
+    ) -> impl Future<Output = Result<Option<T>, QueryError>> + Send {
+        let connection = self.lock().unwrap_or_else(PoisonError::into_inner);
+        ready(SqliteExecutor::fetch_optional(&*connection, query))
+    }
+}
+
+impl SingleConnection for Mutex<Connection> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::{
+        condition::Condition,
+        ddl::{CreateIndex, CreateTable},
+        dialect::Dialect,
+        executor::{transaction, Connection as Scoped},
+        insert::Insert,
+        returning::Returning,
+        row::Row,
+        schema::{ColumnDef, SqlType},
+        transaction::Begin,
+    };
+
+    struct User {
+        id: i64,
+        name: String,
+        active: bool,
+        avatar: Option<Vec<u8>>,
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                active: row.try_get("active")?,
+                avatar: row.try_get("avatar")?,
+            })
+        }
+    }
+
+    fn connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_query(&Query::new(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, active INTEGER NOT NULL, avatar BLOB)",
+            ))
+            .unwrap();
+
+        let insert = "INSERT INTO users (id, name, active, avatar) VALUES (?, ?, ?, ?)";
+        let rows = [
+            Query::new(insert)
+                .bind(1)
+                .bind("O'Brien")
+                .bind(true)
+                .bind(vec![0x89u8, 0x50]),
+            Query::new(insert)
+                .bind(2)
+                .bind("bob")
+                .bind(false)
+                .bind(SqlValue::Null),
+        ];
+        for row in rows.iter() {
+            assert_eq!(connection.execute_query(row), Ok(1));
+        }
+
+        connection
+    }
+
+    #[test]
+    fn test_bound_values_round_trip() {
+        let connection = connection();
+        let users: Vec<User> = connection
+            .fetch_all(&Query::new(
+                "SELECT id, name, active, avatar FROM users ORDER BY id",
+            ))
+            .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!((users[0].id, users[0].name.as_str()), (1, "O'Brien"));
+        assert!(users[0].active);
+        assert_eq!(users[0].avatar, Some(vec![0x89, 0x50]));
+        assert!(!users[1].active);
+        assert_eq!(users[1].avatar, None);
+    }
+
+    #[test]
+    fn test_parameterized_condition() {
+        let connection = connection();
+        let mut params = vec![];
+        let condition = Condition::In(
+            "name".to_owned(),
+            vec![SqlValue::from_string_slice("bob"), SqlValue::Null],
+        )
+        .build_parameterized(Dialect::Sqlite, &mut params);
+        let query = Query::new(format!(
+            "SELECT id, name, active, avatar FROM users WHERE {}",
+            condition
+        ))
+        .with_params(params);
+
+        let user: User = connection.fetch_one(&query).unwrap();
+        assert_eq!(user.id, 2);
+    }
+
+    #[test]
+    fn test_insert_returning() {
+        let connection = connection();
+        let mut insert = Insert::new("users");
+        insert
+            .dialect(Dialect::Sqlite)
+            .columns(&["name", "active"])
+            .values(vec!["carol".into(), true.into()])
+            .returning(&["id", "name", "active", "avatar"]);
+        let user: User = connection
+            .fetch_one(&insert.build_insert().unwrap())
+            .unwrap();
+        assert_eq!((user.id, user.name.as_str()), (3, "carol"));
+    }
+
+    #[test]
+    fn test_fetch_errors() {
+        let connection = connection();
+        let query = Query::new("SELECT id, name, active, avatar FROM users WHERE id = ?").bind(3);
+        assert!(matches!(
+            connection.fetch_optional::<User>(&query),
+            Ok(None)
+        ));
+        assert!(matches!(
+            connection.fetch_one::<User>(&query),
+            Err(QueryError::RowNotFound)
+        ));
+        assert!(matches!(
+            connection.execute_query(&Query::new("SELECT * FROM missing")),
+            Err(QueryError::Driver(_))
+        ));
+        assert!(matches!(
+            connection.execute_query(
+                &Query::new("INSERT INTO users (id, name, active) VALUES (?, 'max', 1)")
+                    .bind(u64::MAX)
+            ),
+            Err(QueryError::Driver(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_unique_index_ignores_soft_deleted_rows() {
+        let connection = Connection::open_in_memory().unwrap();
+        let mut create = CreateTable::new("accounts");
+        create
+            .dialect(Dialect::Sqlite)
+            .column(ColumnDef::new("email", SqlType::Text))
+            .column(ColumnDef::new("deleted_at", SqlType::Timestamp).nullable(true));
+        connection
+            .execute_query(&create.build_create().unwrap())
+            .unwrap();
+        let mut index = CreateIndex::new("accounts_email_key", "accounts");
+        index
+            .dialect(Dialect::Sqlite)
+            .unique()
+            .expression("lower(email)")
+            .r#where(Condition::Null("deleted_at".to_owned()));
+        connection
+            .execute_query(&index.build_create_index().unwrap())
+            .unwrap();
+
+        let insert = |email: &str, deleted_at: Option<&str>| {
+            connection.execute_query(
+                &Query::new("INSERT INTO accounts (email, deleted_at) VALUES (?, ?)")
+                    .bind(email)
+                    .bind(deleted_at.map(str::to_owned)),
+            )
+        };
+        insert("ann@example.com", Some("2024-01-01 00:00:00")).unwrap();
+        insert("Ann@example.com", None).unwrap();
+        assert!(matches!(
+            insert("ann@example.com", None),
+            Err(QueryError::Driver(_))
+        ));
+    }
+
+    struct Count {
+        n: i64,
+    }
+
+    impl FromRow for Count {
+        fn from_row(row: &impl Row) -> Result<Self, QueryError> {
+            Ok(Self {
+                n: row.try_get("n")?,
+            })
+        }
+    }
+
+    fn scoped() -> Scoped<Mutex<Connection>> {
+        Scoped::new(Mutex::new(connection()), Dialect::Sqlite)
+    }
+
+    async fn count(connection: &Scoped<Mutex<Connection>>) -> Result<i64, QueryError> {
+        connection
+            .fetch_one::<Count>(&Query::new("SELECT COUNT(*) AS n FROM users"))
+            .await
+            .map(|c| c.n)
+    }
+
+    fn insert(id: i64) -> Query {
+        Query::new("INSERT INTO users (id, name, active) VALUES (?, 'x', 1)").bind(id)
+    }
+
+    #[tokio::test]
+    async fn test_nested_transactions() {
+        let connection = scoped();
+        let result = transaction(&connection, Begin::default(), async |c| {
+            c.execute(&insert(3)).await?;
+            let failed = transaction(c, Begin::default(), async |c| {
+                c.execute(&insert(4)).await?;
+                c.execute(&insert(4)).await
+            })
+            .await;
+            assert!(matches!(failed, Err(QueryError::Driver(_))));
+            transaction(c, Begin::default(), async |c| c.execute(&insert(5)).await).await
+        })
+        .await;
+        assert_eq!(result, Ok(1));
+        // 3 and 5 commit with the outer transaction, 4 went with its savepoint
+        assert_eq!(count(&connection).await, Ok(4));
+
+        let failed = transaction(&connection, Begin::default(), async |c| {
+            transaction(c, Begin::default(), async |c| c.execute(&insert(6)).await).await?;
+            Err::<(), _>(QueryError::RowNotFound)
+        })
+        .await;
+        assert_eq!(failed, Err(QueryError::RowNotFound));
+        assert_eq!(count(&connection).await, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_transaction_poisons_the_connection() {
+        let connection = scoped();
+        tokio::select! {
+            biased;
+            _ = transaction(&connection, Begin::default(), async |c| {
+                c.execute(&insert(3)).await?;
+                transaction(c, Begin::default(), async |c| {
+                    c.execute(&insert(4)).await?;
+                    std::future::pending::<Result<(), QueryError>>().await
+                })
+                .await
+            }) => unreachable!(),
+            _ = std::future::ready(()) => {}
+        }
+
+        assert!(connection.is_poisoned());
+        assert_eq!(
+            count(&connection).await,
+            Err(QueryError::AbandonedTransaction)
+        );
+        assert_eq!(connection.recover().await, Ok(()));
+        assert_eq!(count(&connection).await, Ok(2));
+
+        // the depth is back to zero, so this begins a transaction of its own
+        let result = transaction(&connection, Begin::default(), async |c| {
+            c.execute(&insert(3)).await
+        })
+        .await;
+        assert_eq!(result, Ok(1));
+        assert_eq!(count(&connection).await, Ok(3));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+
+use super::{dialect::Dialect, error::QueryError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationLevel::ReadUncommitted => write!(f, "READ UNCOMMITTED"),
+            IsolationLevel::ReadCommitted => write!(f, "READ COMMITTED"),
+            IsolationLevel::RepeatableRead => write!(f, "REPEATABLE READ"),
+            IsolationLevel::Serializable => write!(f, "SERIALIZABLE"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Begin {
+    isolation: Option<IsolationLevel>,
+    read_only: bool,
+    deferrable: bool,
+}
+
+impl Begin {
+    pub fn isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = Some(isolation);
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    // postgres only, meaningful for SERIALIZABLE READ ONLY transactions
+    pub fn deferrable(mut self) -> Self {
+        self.deferrable = true;
+        self
+    }
+
+    // mysql and sql server set the isolation level in a statement of its own before starting,
+    // run them in order on the same connection
+    pub fn to_sql(&self, dialect: Dialect) -> Result<Vec<String>, QueryError> {
+        if self.deferrable && dialect != Dialect::Postgres {
+            return Err(QueryError::Unsupported("DEFERRABLE", dialect));
+        }
+
+        match dialect {
+            Dialect::Postgres => {
+                let mut modes = vec![];
+                if let Some(isolation) = self.isolation {
+                    modes.push(format!("ISOLATION LEVEL {}", isolation));
+                }
+                if self.read_only {
+                    modes.push("READ ONLY".to_owned());
+                }
+                if self.deferrable {
+                    modes.push("DEFERRABLE".to_owned());
+                }
+                match modes.is_empty() {
+                    true => Ok(vec!["BEGIN".to_owned()]),
+                    false => Ok(vec![format!("BEGIN {}", modes.join(", "))]),
+                }
+            }
+            Dialect::MySql => {
+                let begin = match self.read_only {
+                    true => "START TRANSACTION READ ONLY",
+                    false => "START TRANSACTION",
+                };
+                match self.isolation {
+                    Some(isolation) => Ok(vec![
+                        format!("SET TRANSACTION ISOLATION LEVEL {}", isolation),
+                        begin.to_owned(),
+                    ]),
+                    None => Ok(vec![begin.to_owned()]),
+                }
+            }
+            Dialect::Sqlite if self.isolation.is_some() => {
+                Err(QueryError::Unsupported("isolation level", dialect))
+            }
+            Dialect::Sqlite if self.read_only => {
+                Err(QueryError::Unsupported("READ ONLY transaction", dialect))
+            }
+            Dialect::Sqlite => Ok(vec!["BEGIN".to_owned()]),
+            Dialect::SqlServer if self.read_only => {
+                Err(QueryError::Unsupported("READ ONLY transaction", dialect))
+            }
+            Dialect::SqlServer => match self.isolation {
+                Some(isolation) => Ok(vec![
+                    format!("SET TRANSACTION ISOLATION LEVEL {}", isolation),
+                    "BEGIN TRANSACTION".to_owned(),
+                ]),
+                None => Ok(vec!["BEGIN TRANSACTION".to_owned()]),
+            },
+        }
+    }
+}
+
+// statements that end a transaction or work inside it, Begin starts one
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transaction {
+    Commit,
+    Rollback,
+    Savepoint(String),
+    Release(String), // Takes the savepoint name
+    RollbackTo(String),
+}
+
+impl Transaction {
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        let sql = match (self, dialect) {
+            (Transaction::Commit, Dialect::SqlServer) => "COMMIT TRANSACTION".to_owned(),
+            (Transaction::Commit, _) => "COMMIT".to_owned(),
+            (Transaction::Rollback, Dialect::SqlServer) => "ROLLBACK TRANSACTION".to_owned(),
+            (Transaction::Rollback, _) => "ROLLBACK".to_owned(),
+            (Transaction::Savepoint(name), Dialect::SqlServer) => {
+                format!("SAVE TRANSACTION {}", name)
+            }
+            (Transaction::Savepoint(name), _) => format!("SAVEPOINT {}", name),
+            // sql server savepoints are only dropped by the outer commit
+            (Transaction::Release(_), Dialect::SqlServer) => {
+                return Err(QueryError::Unsupported("RELEASE SAVEPOINT", dialect))
+            }
+            (Transaction::Release(name), _) => format!("RELEASE SAVEPOINT {}", name),
+            (Transaction::RollbackTo(name), Dialect::SqlServer) => {
+                format!("ROLLBACK TRANSACTION {}", name)
+            }
+            (Transaction::RollbackTo(name), _) => format!("ROLLBACK TO SAVEPOINT {}", name),
+        };
+
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_begin_postgres() {
+        assert_eq!(
+            Begin::default().to_sql(Dialect::Postgres).unwrap(),
+            vec!["BEGIN"]
+        );
+        assert_eq!(
+            Begin::default()
+                .isolation(IsolationLevel::Serializable)
+                .read_only()
+                .deferrable()
+                .to_sql(Dialect::Postgres)
+                .unwrap(),
+            vec!["BEGIN ISOLATION LEVEL SERIALIZABLE, READ ONLY, DEFERRABLE"]
+        );
+    }
+
+    #[test]
+    fn test_begin_per_dialect() {
+        let begin = Begin::default().isolation(IsolationLevel::RepeatableRead);
+        assert_eq!(
+            begin.to_sql(Dialect::MySql).unwrap(),
+            vec![
+                "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ",
+                "START TRANSACTION"
+            ]
+        );
+        assert_eq!(
+            begin.to_sql(Dialect::SqlServer).unwrap(),
+            vec![
+                "SET TRANSACTION ISOLATION LEVEL REPEATABLE READ",
+                "BEGIN TRANSACTION"
+            ]
+        );
+        assert_eq!(
+            begin.to_sql(Dialect::Sqlite),
+            Err(QueryError::Unsupported("isolation level", Dialect::Sqlite))
+        );
+        assert_eq!(
+            Begin::default().read_only().to_sql(Dialect::MySql).unwrap(),
+            vec!["START TRANSACTION READ ONLY"]
+        );
+        assert_eq!(
+            Begin::default().deferrable().to_sql(Dialect::MySql),
+            Err(QueryError::Unsupported("DEFERRABLE", Dialect::MySql))
+        );
+    }
+
+    #[test]
+    fn test_savepoints() {
+        let statements = [
+            Transaction::Savepoint("before_update".to_owned()),
+            Transaction::RollbackTo("before_update".to_owned()),
+            Transaction::Release("before_update".to_owned()),
+            Transaction::Commit,
+        ];
+        assert_eq!(
+            statements
+                .iter()
+                .map(|s| s.to_sql(Dialect::Sqlite).unwrap())
+                .collect::<Vec<String>>(),
+            vec![
+                "SAVEPOINT before_update",
+                "ROLLBACK TO SAVEPOINT before_update",
+                "RELEASE SAVEPOINT before_update",
+                "COMMIT"
+            ]
+        );
+        assert_eq!(
+            statements[0].to_sql(Dialect::SqlServer).unwrap(),
+            "SAVE TRANSACTION before_update"
+        );
+        assert_eq!(
+            statements[1].to_sql(Dialect::SqlServer).unwrap(),
+            "ROLLBACK TRANSACTION before_update"
+        );
+        assert_eq!(
+            statements[2].to_sql(Dialect::SqlServer),
+            Err(QueryError::Unsupported(
+                "RELEASE SAVEPOINT",
+                Dialect::SqlServer
+            ))
+        );
+    }
+}
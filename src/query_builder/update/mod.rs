@@ -0,0 +1,210 @@
+use super::{
+    condition::Condition,
+    dialect::Dialect,
+    error::QueryError,
+    query::{BuildQuery, Query},
+    returning::Returning,
+    select::Field,
+    value::SqlValue,
+    with::{CteEntry, With},
+};
+
+pub struct Update {
+    table: String,
+    set: Vec<(String, SqlValue)>,
+    condition: Option<Condition>,
+    returning: Vec<(Field, String)>,
+    ctes: Vec<CteEntry>,
+    dialect: Dialect,
+}
+
+impl Update {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_owned(),
+            set: vec![],
+            condition: None,
+            returning: vec![],
+            ctes: vec![],
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn set(&mut self, column: &str, value: impl Into<SqlValue>) -> &mut Self {
+        self.set.push((column.to_owned(), value.into()));
+        self
+    }
+
+    pub fn r#where(&mut self, condition: Condition) -> &mut Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    // values, the WHERE ones included, are bound as params after the params of the ctes
+    pub fn build_update(&self) -> Result<Query, QueryError> {
+        if self.set.is_empty() {
+            return Err(QueryError::NoFields);
+        }
+
+        let mut params = vec![];
+        let with = self.build_with(self.dialect, &mut params);
+
+        let set = self
+            .set
+            .iter()
+            .map(|(column, value)| {
+                params.push(value.clone());
+                format!("{} = {}", column, self.dialect.placeholder(params.len()))
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let mut sql = match with {
+            Some(ctes) => format!("{} UPDATE {} SET {}", ctes, self.table, set),
+            None => format!("UPDATE {} SET {}", self.table, set),
+        };
+
+        let returning = self.build_returning(self.dialect, "INSERTED")?;
+
+        if let (Some(output), Dialect::SqlServer) = (&returning, self.dialect) {
+            sql.push_str(&format!(" {}", output));
+        }
+
+        if let Some(condition) = &self.condition {
+            let condition = condition
+                .for_dialect(self.dialect)?
+                .build_parameterized(self.dialect, &mut params);
+            sql.push_str(&format!(" WHERE {}", condition));
+        }
+
+        if let (Some(returning), false) = (&returning, self.dialect == Dialect::SqlServer) {
+            sql.push_str(&format!(" {}", returning));
+        }
+
+        Ok(Query::new(sql)
+            .with_fields(self.returning_fields())
+            .with_params(params))
+    }
+}
+
+impl Returning for Update {
+    fn get_returning(&self) -> Vec<(&Field, &str)> {
+        self.returning
+            .iter()
+            .map(|(f, a)| (f, a.as_str()))
+            .collect()
+    }
+
+    fn set_returning(&mut self, f: impl FnOnce(&mut Vec<(Field, String)>)) {
+        f(&mut self.returning)
+    }
+}
+
+impl With for Update {
+    fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>)) {
+        f(&mut self.ctes)
+    }
+
+    fn get_with(&self) -> Vec<&CteEntry> {
+        self.ctes.iter().collect()
+    }
+}
+
+impl BuildQuery for Update {
+    fn build(&mut self) -> Result<Query, QueryError> {
+        self.build_update()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::{order_by::Order, select::Agregate};
+
+    #[test]
+    fn test_update_returning() {
+        let mut update = Update::new("jobs");
+        update
+            .set("status", "running")
+            .set("attempts", 2)
+            .r#where(Condition::Eq("id".to_owned(), SqlValue::I64(7)))
+            .returning(&["id", "status"]);
+        let query = update.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "UPDATE jobs SET status = $1, attempts = $2 WHERE id = $3 RETURNING id, status"
+        );
+        assert_eq!(
+            query.params(),
+            &[
+                SqlValue::Text("running".to_owned()),
+                SqlValue::I32(2),
+                SqlValue::I64(7)
+            ]
+        );
+
+        update.dialect(Dialect::SqlServer);
+        assert_eq!(
+            update.build().unwrap().sql(),
+            "UPDATE jobs SET status = @p1, attempts = @p2 OUTPUT INSERTED.id, INSERTED.status WHERE id = @p3"
+        );
+    }
+
+    #[test]
+    fn test_update_without_set() {
+        let mut update = Update::new("jobs");
+        assert_eq!(update.build(), Err(QueryError::NoFields));
+    }
+
+    #[test]
+    fn test_update_with_cte() {
+        let mut update = Update::new("jobs");
+        update
+            .with(
+                "stale",
+                Query::new("SELECT id FROM jobs WHERE heartbeat < $1").bind("2024-01-01"),
+            )
+            .set("status", "queued")
+            .r#where(
+                Condition::Native("id IN (SELECT id FROM stale)".to_owned())
+                    .and(Condition::Lt("attempts".to_owned(), SqlValue::I32(3))),
+            );
+        let query = update.build().unwrap();
+        assert_eq!(
+            query.sql(),
+            "WITH stale AS (SELECT id FROM jobs WHERE heartbeat < $1) UPDATE jobs SET status = $2 WHERE (id IN (SELECT id FROM stale) AND attempts < $3)"
+        );
+        assert_eq!(
+            query.params(),
+            &[
+                SqlValue::Text("2024-01-01".to_owned()),
+                SqlValue::Text("queued".to_owned()),
+                SqlValue::I32(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_condition_per_dialect() {
+        let mut update = Update::new("jobs");
+        update
+            .dialect(Dialect::Sqlite)
+            .set("status", "queued")
+            .r#where(
+                Agregate::PercentileCont(0.5, "attempts".to_owned(), Order::Asc)
+                    .gt(SqlValue::Int(3)),
+            );
+        assert_eq!(
+            update.build(),
+            Err(QueryError::Unsupported(
+                "ordered-set aggregate",
+                Dialect::Sqlite
+            ))
+        );
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Clone, Debug)]
+use super::dialect::Dialect;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum SqlValue {
     I128(i128),
     I64(i64),
@@ -14,6 +16,7 @@ pub enum SqlValue {
     Float(f32),
     Bytes(Vec<u8>),
     StringDate(String), // For date values
+    Numeric(String),    // Exact decimal, kept as its text
     Null,
 }
 
@@ -34,13 +37,45 @@ impl SqlValue {
             Self::Int(val) => val.parse_sql_value(),
             Self::Float(val) => val.parse_sql_value(),
             Self::StringDate(val) => val.parse_sql_value(),
+            // quoted unless it is a plain number, so the text cannot leak into the statement
+            Self::Numeric(val) if is_decimal(val) => val.clone(),
+            Self::Numeric(val) => val.parse_sql_value(),
             _ => "".to_owned(),
         }
     }
     pub fn from_string_slice(val: &str) -> Self {
         Self::Text(val.to_string())
     }
+
+    // to_sql for ddl, which cannot bind params, sql server has no boolean literals
+    // and mysql reads backslashes in strings as escapes
+    pub fn to_literal(&self, dialect: Dialect) -> String {
+        match (self, dialect) {
+            (Self::Bool(val), Dialect::SqlServer) => (*val as u8).to_string(),
+            (Self::Text(val) | Self::StringDate(val), Dialect::MySql) => {
+                format!("'{}'", val.replace('\\', "\\\\").replace('\'', "''"))
+            }
+            (Self::Bytes(val), _) => {
+                let hex = val.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+                match dialect {
+                    Dialect::Postgres => format!("'\\x{}'", hex),
+                    Dialect::SqlServer => format!("0x{}", hex),
+                    Dialect::MySql | Dialect::Sqlite => format!("X'{}'", hex),
+                }
+            }
+            (val, _) => val.to_sql(),
+        }
+    }
+}
+
+fn is_decimal(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    !whole.is_empty()
+        && whole.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit())
 }
+
 trait SqlValueParser {
     fn parse_sql_value(&self) -> String;
 }
@@ -80,6 +115,44 @@ impl_sql_parser!(true, String, &str, char);
 
 impl_sql_parser!(u8, u32, u16, u64, u128, i8, i16, i32, i64, i128, f32, f64, bool);
 
+macro_rules! impl_from_for_sql_value {
+    ($($t: ty => $v: ident),+) => {
+        $(
+            impl From<$t> for SqlValue {
+                fn from(val: $t) -> Self {
+                    Self::$v(val)
+                }
+            }
+        )+
+    };
+}
+
+impl_from_for_sql_value!(
+    i128 => I128,
+    i64 => I64,
+    i32 => I32,
+    u128 => U128,
+    u64 => U64,
+    u32 => U32,
+    f64 => F64,
+    f32 => F32,
+    bool => Bool,
+    String => Text,
+    Vec<u8> => Bytes
+);
+
+impl From<&str> for SqlValue {
+    fn from(val: &str) -> Self {
+        Self::from_string_slice(val)
+    }
+}
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(val: Option<T>) -> Self {
+        val.map_or(Self::Null, |v| v.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,6 +217,19 @@ mod tests {
         assert_eq!(val.to_sql(), "true");
     }
 
+    #[test]
+    fn test_sql_value_literal() {
+        let bytes = SqlValue::Bytes(vec![0xde, 0xad, 0x01]);
+        assert_eq!(bytes.to_literal(Dialect::Postgres), "'\\xdead01'");
+        assert_eq!(bytes.to_literal(Dialect::MySql), "X'dead01'");
+        assert_eq!(bytes.to_literal(Dialect::SqlServer), "0xdead01");
+        assert_eq!(SqlValue::Bool(true).to_literal(Dialect::SqlServer), "1");
+        assert_eq!(SqlValue::Bool(false).to_literal(Dialect::Sqlite), "false");
+        let text = SqlValue::from_string_slice("it's C:\\");
+        assert_eq!(text.to_literal(Dialect::MySql), "'it''s C:\\\\'");
+        assert_eq!(text.to_literal(Dialect::Postgres), "'it''s C:\\'");
+    }
+
     #[test]
     fn test_sql_value_text() {
         let val = SqlValue::Text("Hello, World!".to_string());
@@ -167,6 +253,14 @@ mod tests {
         let val = SqlValue::StringDate("2024-06-12".to_string());
         assert_eq!(val.to_sql(), "'2024-06-12'");
     }
+
+    #[test]
+    fn test_sql_value_numeric() {
+        let val = SqlValue::Numeric("-12345.678000000000000001".to_string());
+        assert_eq!(val.to_sql(), "-12345.678000000000000001");
+        let val = SqlValue::Numeric("1; DROP TABLE users".to_string());
+        assert_eq!(val.to_sql(), "'1; DROP TABLE users'");
+    }
     #[test]
     fn test_sql_value_char() {
         let val = SqlValue::from_string_slice("A");
@@ -220,4 +314,12 @@ mod tests {
         let val = SqlValue::from_string_slice(&String::from("It's raining"));
         assert_eq!(val.to_sql(), "'It''s raining'");
     }
+
+    #[test]
+    fn test_sql_value_from() {
+        assert_eq!(SqlValue::from(42i64), SqlValue::I64(42));
+        assert_eq!(SqlValue::from("it's"), SqlValue::Text("it's".to_string()));
+        assert_eq!(SqlValue::from(Some(true)), SqlValue::Bool(true));
+        assert_eq!(SqlValue::from(None::<i32>), SqlValue::Null);
+    }
 }
@@ -0,0 +1,460 @@
+use std::fmt;
+
+use super::{
+    dialect::Dialect,
+    error::QueryError,
+    order_by::{Order, OrderBy, OrderEntry},
+    select::Agregate,
+    value::SqlValue,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum FrameUnit {
+    Rows,
+    Range,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(u64),
+    CurrentRow,
+    Following(u64),
+    UnboundedFollowing,
+}
+
+impl FrameBound {
+    // position along the partition, UNBOUNDED PRECEDING first
+    fn position(&self) -> i128 {
+        match self {
+            FrameBound::UnboundedPreceding => i128::MIN,
+            FrameBound::Preceding(n) => -(*n as i128),
+            FrameBound::CurrentRow => 0,
+            FrameBound::Following(n) => *n as i128,
+            FrameBound::UnboundedFollowing => i128::MAX,
+        }
+    }
+
+    fn is_offset(&self) -> bool {
+        matches!(self, FrameBound::Preceding(_) | FrameBound::Following(_))
+    }
+}
+
+impl fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameBound::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            FrameBound::Preceding(n) => write!(f, "{} PRECEDING", n),
+            FrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            FrameBound::Following(n) => write!(f, "{} FOLLOWING", n),
+            FrameBound::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Window {
+    partition_by: Vec<String>,
+    order_by: Vec<OrderEntry>,
+    frame: Option<(FrameUnit, FrameBound, FrameBound)>,
+}
+
+impl Window {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn partition_by(mut self, fields: &[&str]) -> Self {
+        self.partition_by
+            .extend(fields.iter().map(|&f| f.to_owned()));
+        self
+    }
+
+    pub fn order_by(mut self, field: &str, order: Order) -> Self {
+        self.order_by_field(field, order);
+        self
+    }
+
+    pub fn rows_between(mut self, start: FrameBound, end: FrameBound) -> Self {
+        self.frame = Some((FrameUnit::Rows, start, end));
+        self
+    }
+
+    pub fn range_between(mut self, start: FrameBound, end: FrameBound) -> Self {
+        self.frame = Some((FrameUnit::Range, start, end));
+        self
+    }
+
+    fn check_frame(&self) -> Result<(), QueryError> {
+        let (unit, start, end) = match &self.frame {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+
+        if matches!(start, FrameBound::UnboundedFollowing) {
+            return Err(QueryError::InvalidFrame(
+                "frame cannot start at UNBOUNDED FOLLOWING",
+            ));
+        }
+
+        if matches!(end, FrameBound::UnboundedPreceding) {
+            return Err(QueryError::InvalidFrame(
+                "frame cannot end at UNBOUNDED PRECEDING",
+            ));
+        }
+
+        if start.position() > end.position() {
+            return Err(QueryError::InvalidFrame("frame starts after its end"));
+        }
+
+        // the offset is a distance on the sort key, there has to be exactly one
+        if matches!(unit, FrameUnit::Range)
+            && (start.is_offset() || end.is_offset())
+            && self.order_by.len() != 1
+        {
+            return Err(QueryError::InvalidFrame(
+                "RANGE with an offset needs a single ORDER BY key",
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        self.check_frame()?;
+
+        let mut parts = vec![];
+
+        if !self.partition_by.is_empty() {
+            parts.push(format!("PARTITION BY {}", self.partition_by.join(", ")));
+        }
+
+        if let Some(order) = self.build_order_by(dialect) {
+            parts.push(order);
+        }
+
+        if let Some((unit, start, end)) = &self.frame {
+            let unit = match unit {
+                FrameUnit::Rows => "ROWS",
+                FrameUnit::Range => "RANGE",
+            };
+            parts.push(format!("{} BETWEEN {} AND {}", unit, start, end));
+        }
+
+        Ok(parts.join(" "))
+    }
+}
+
+// keys, nulls placement and collation come from the same builders as the query's ORDER BY
+impl OrderBy for Window {
+    fn get_order(&self) -> Vec<&OrderEntry> {
+        self.order_by.iter().collect()
+    }
+
+    fn set_order(&mut self, f: impl FnOnce(&mut Vec<OrderEntry>)) {
+        f(&mut self.order_by)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Ntile(u32),
+    Lag(String, Option<u32>, Option<SqlValue>), // Takes column, offset and default value
+    Lead(String, Option<u32>, Option<SqlValue>), // Takes column, offset and default value
+    Agregate(Agregate),
+}
+
+impl WindowFunction {
+    pub fn over(self, window: Window) -> WindowExpr {
+        WindowExpr {
+            function: self,
+            over: Over::Window(window),
+        }
+    }
+
+    pub fn over_named(self, name: &str) -> WindowExpr {
+        WindowExpr {
+            function: self,
+            over: Over::Named(name.to_owned()),
+        }
+    }
+}
+
+impl WindowFunction {
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        match self {
+            WindowFunction::Agregate(agregate) => agregate.to_sql(dialect),
+            function => Ok(function.to_string()),
+        }
+    }
+}
+
+impl From<Agregate> for WindowFunction {
+    fn from(agregate: Agregate) -> Self {
+        WindowFunction::Agregate(agregate)
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn offset_args(column: &str, offset: &Option<u32>, default: &Option<SqlValue>) -> String {
+            match (offset, default) {
+                (None, None) => column.to_owned(),
+                (Some(offset), None) => format!("{}, {}", column, offset),
+                (offset, Some(default)) => {
+                    format!("{}, {}, {}", column, offset.unwrap_or(1), default.to_sql())
+                }
+            }
+        }
+
+        match self {
+            WindowFunction::RowNumber => write!(f, "ROW_NUMBER()"),
+            WindowFunction::Rank => write!(f, "RANK()"),
+            WindowFunction::DenseRank => write!(f, "DENSE_RANK()"),
+            WindowFunction::Ntile(buckets) => write!(f, "NTILE({})", buckets),
+            WindowFunction::Lag(column, offset, default) => {
+                write!(f, "LAG({})", offset_args(column, offset, default))
+            }
+            WindowFunction::Lead(column, offset, default) => {
+                write!(f, "LEAD({})", offset_args(column, offset, default))
+            }
+            WindowFunction::Agregate(agregate) => write!(f, "{}", agregate),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Over {
+    Window(Window),
+    Named(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct WindowExpr {
+    function: WindowFunction,
+    over: Over,
+}
+
+impl WindowExpr {
+    pub fn to_sql(&self, dialect: Dialect) -> Result<String, QueryError> {
+        let function = self.function.to_sql(dialect)?;
+        Ok(match &self.over {
+            Over::Window(window) => format!("{} OVER ({})", function, window.to_sql(dialect)?),
+            Over::Named(name) => format!("{} OVER {}", function, name),
+        })
+    }
+}
+
+pub trait NamedWindow {
+    fn set_window(&mut self, f: impl FnOnce(&mut Vec<(String, Window)>));
+    fn get_window(&self) -> Vec<(&str, &Window)>;
+
+    fn window(&mut self, name: &str, window: Window) -> &mut Self {
+        self.set_window(|f| f.push((name.to_owned(), window)));
+        self
+    }
+
+    fn build_window(&self, dialect: Dialect) -> Result<Option<String>, QueryError> {
+        let windows = self.get_window();
+        if windows.is_empty() {
+            return Ok(None);
+        }
+
+        let window_strings = windows
+            .iter()
+            .map(|(name, window)| Ok(format!("{} AS ({})", name, window.to_sql(dialect)?)))
+            .collect::<Result<Vec<String>, QueryError>>()?
+            .join(", ");
+
+        Ok(Some(format!("WINDOW {}", window_strings)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct WindowTest {
+        windows: Vec<(String, Window)>,
+    }
+
+    impl NamedWindow for WindowTest {
+        fn set_window(&mut self, f: impl FnOnce(&mut Vec<(String, Window)>)) {
+            f(&mut self.windows)
+        }
+
+        fn get_window(&self) -> Vec<(&str, &Window)> {
+            self.windows.iter().map(|(n, w)| (n.as_str(), w)).collect()
+        }
+    }
+
+    #[test]
+    fn test_row_number() {
+        let expr = WindowFunction::RowNumber.over(
+            Window::new()
+                .partition_by(&["department"])
+                .order_by("salary", Order::Desc),
+        );
+        assert_eq!(
+            expr.to_sql(Dialect::Postgres).unwrap(),
+            "ROW_NUMBER() OVER (PARTITION BY department ORDER BY salary DESC)"
+        );
+    }
+
+    #[test]
+    fn test_running_total() {
+        let expr = WindowFunction::from(Agregate::Sum("amount".to_owned())).over(
+            Window::new()
+                .partition_by(&["account_id"])
+                .order_by("created_at", Order::Asc)
+                .rows_between(FrameBound::UnboundedPreceding, FrameBound::CurrentRow),
+        );
+        assert_eq!(
+            expr.to_sql(Dialect::Postgres).unwrap(),
+            "SUM(amount) OVER (PARTITION BY account_id ORDER BY created_at ASC ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW)"
+        );
+    }
+
+    #[test]
+    fn test_moving_average_range() {
+        let expr = WindowFunction::from(Agregate::Avg("price".to_owned())).over(
+            Window::new()
+                .order_by("day", Order::Asc)
+                .range_between(FrameBound::Preceding(6), FrameBound::Following(0)),
+        );
+        assert_eq!(
+            expr.to_sql(Dialect::Postgres).unwrap(),
+            "AVG(price) OVER (ORDER BY day ASC RANGE BETWEEN 6 PRECEDING AND 0 FOLLOWING)"
+        );
+    }
+
+    #[test]
+    fn test_lag_lead_and_ntile() {
+        let lag = WindowFunction::Lag("price".to_owned(), None, None);
+        let lead = WindowFunction::Lead("price".to_owned(), Some(2), None);
+        let lag_default = WindowFunction::Lag("price".to_owned(), None, Some(SqlValue::Int(0)));
+        assert_eq!(
+            lag.over(Window::new()).to_sql(Dialect::Postgres).unwrap(),
+            "LAG(price) OVER ()"
+        );
+        assert_eq!(
+            lead.over_named("w").to_sql(Dialect::Postgres).unwrap(),
+            "LEAD(price, 2) OVER w"
+        );
+        assert_eq!(
+            lag_default
+                .over_named("w")
+                .to_sql(Dialect::Postgres)
+                .unwrap(),
+            "LAG(price, 1, 0) OVER w"
+        );
+        assert_eq!(
+            WindowFunction::Ntile(4)
+                .over_named("w")
+                .to_sql(Dialect::Postgres)
+                .unwrap(),
+            "NTILE(4) OVER w"
+        );
+    }
+
+    #[test]
+    fn test_named_windows() {
+        let mut windows = WindowTest::default();
+        assert_eq!(windows.build_window(Dialect::Postgres), Ok(None));
+        windows
+            .window(
+                "w",
+                Window::new()
+                    .partition_by(&["team"])
+                    .order_by("score", Order::Desc),
+            )
+            .window("all_rows", Window::new());
+        assert_eq!(
+            windows.build_window(Dialect::Postgres).unwrap().unwrap(),
+            "WINDOW w AS (PARTITION BY team ORDER BY score DESC), all_rows AS ()"
+        );
+    }
+
+    #[test]
+    fn test_window_order_nulls_and_collation() {
+        let mut window = Window::new().partition_by(&["team"]);
+        window
+            .order_by_field_desc("score")
+            .nulls_last()
+            .order_by_field_asc("name")
+            .collate("\"C\"");
+        let expr = WindowFunction::Rank.over(window.clone());
+        assert_eq!(
+            expr.to_sql(Dialect::Postgres).unwrap(),
+            "RANK() OVER (PARTITION BY team ORDER BY score DESC NULLS LAST, name COLLATE \"C\" ASC)"
+        );
+        assert_eq!(
+            expr.to_sql(Dialect::MySql).unwrap(),
+            "RANK() OVER (PARTITION BY team ORDER BY score IS NULL ASC, score DESC, name COLLATE \"C\" ASC)"
+        );
+
+        let mut windows = WindowTest::default();
+        windows.window("w", window);
+        assert_eq!(
+            windows.build_window(Dialect::SqlServer).unwrap().unwrap(),
+            "WINDOW w AS (PARTITION BY team ORDER BY CASE WHEN score IS NULL THEN 1 ELSE 0 END, score DESC, name COLLATE \"C\" ASC)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let frame = |window: Window| {
+            WindowFunction::RowNumber
+                .over(window)
+                .to_sql(Dialect::Postgres)
+        };
+        assert_eq!(
+            frame(Window::new().rows_between(
+                FrameBound::UnboundedFollowing,
+                FrameBound::UnboundedFollowing
+            )),
+            Err(QueryError::InvalidFrame(
+                "frame cannot start at UNBOUNDED FOLLOWING"
+            ))
+        );
+        assert_eq!(
+            frame(
+                Window::new().rows_between(FrameBound::CurrentRow, FrameBound::UnboundedPreceding)
+            ),
+            Err(QueryError::InvalidFrame(
+                "frame cannot end at UNBOUNDED PRECEDING"
+            ))
+        );
+        assert_eq!(
+            frame(Window::new().rows_between(FrameBound::Following(1), FrameBound::Preceding(1))),
+            Err(QueryError::InvalidFrame("frame starts after its end"))
+        );
+        assert_eq!(
+            frame(Window::new().rows_between(FrameBound::Preceding(1), FrameBound::Preceding(3))),
+            Err(QueryError::InvalidFrame("frame starts after its end"))
+        );
+        assert_eq!(
+            frame(
+                Window::new()
+                    .order_by("day", Order::Asc)
+                    .order_by("id", Order::Asc)
+                    .range_between(FrameBound::Preceding(6), FrameBound::CurrentRow)
+            ),
+            Err(QueryError::InvalidFrame(
+                "RANGE with an offset needs a single ORDER BY key"
+            ))
+        );
+        assert!(frame(
+            Window::new().range_between(FrameBound::UnboundedPreceding, FrameBound::CurrentRow)
+        )
+        .is_ok());
+        assert!(frame(
+            Window::new().rows_between(FrameBound::Preceding(3), FrameBound::Preceding(1))
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,242 @@
+use super::{dialect::Dialect, query::Query, value::SqlValue};
+
+pub enum Materialization {
+    Materialized,
+    NotMaterialized,
+}
+
+pub struct CteEntry {
+    name: String,
+    columns: Vec<String>,
+    query: Query,
+    recursive_part: Option<Query>,
+    materialization: Option<Materialization>,
+}
+
+pub trait With {
+    fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>));
+    fn get_with(&self) -> Vec<&CteEntry>;
+
+    fn with(&mut self, name: &str, query: Query) -> &mut Self {
+        self.set_with(|f| {
+            f.push(CteEntry {
+                name: name.to_owned(),
+                columns: vec![],
+                query,
+                recursive_part: None,
+                materialization: None,
+            })
+        });
+        self
+    }
+
+    // the hint only reaches postgres, other dialects decide on their own
+    fn with_materialization(
+        &mut self,
+        name: &str,
+        query: Query,
+        materialization: Materialization,
+    ) -> &mut Self {
+        self.set_with(|f| {
+            f.push(CteEntry {
+                name: name.to_owned(),
+                columns: vec![],
+                query,
+                recursive_part: None,
+                materialization: Some(materialization),
+            })
+        });
+        self
+    }
+
+    fn with_recursive(
+        &mut self,
+        name: &str,
+        columns: &[&str],
+        anchor: Query,
+        recursive_part: Query,
+    ) -> &mut Self {
+        self.set_with(|f| {
+            f.push(CteEntry {
+                name: name.to_owned(),
+                columns: columns.iter().map(|&c| c.to_owned()).collect(),
+                query: anchor,
+                recursive_part: Some(recursive_part),
+                materialization: None,
+            })
+        });
+        self
+    }
+
+    // the params of the ctes are appended to params in the order they are rendered
+    fn build_with(&self, dialect: Dialect, params: &mut Vec<SqlValue>) -> Option<String> {
+        let ctes = self.get_with();
+        if ctes.is_empty() {
+            return None;
+        }
+
+        let recursive = ctes.iter().any(|c| c.recursive_part.is_some());
+
+        let keyword = if recursive && dialect != Dialect::SqlServer {
+            "WITH RECURSIVE"
+        } else {
+            "WITH"
+        };
+
+        let cte_strings = ctes
+            .iter()
+            .map(|c| c.build(dialect, params))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Some(format!("{} {}", keyword, cte_strings))
+    }
+}
+
+impl CteEntry {
+    fn build(&self, dialect: Dialect, params: &mut Vec<SqlValue>) -> String {
+        let mut cte = self.name.clone();
+
+        if !self.columns.is_empty() {
+            cte.push_str(&format!(" ({})", self.columns.join(", ")));
+        }
+
+        cte.push_str(" AS ");
+
+        match (dialect, &self.materialization) {
+            (Dialect::Postgres, Some(Materialization::Materialized)) => {
+                cte.push_str("MATERIALIZED ")
+            }
+            (Dialect::Postgres, Some(Materialization::NotMaterialized)) => {
+                cte.push_str("NOT MATERIALIZED ")
+            }
+            _ => {}
+        }
+
+        let query = self.query.embed(dialect, params);
+
+        match &self.recursive_part {
+            Some(recursive_part) => {
+                let recursive_part = recursive_part.embed(dialect, params);
+                cte.push_str(&format!("({} UNION ALL {})", query, recursive_part))
+            }
+            None => cte.push_str(&format!("({})", query)),
+        }
+
+        cte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct WithTest {
+        ctes: Vec<CteEntry>,
+    }
+
+    impl With for WithTest {
+        fn set_with(&mut self, f: impl FnOnce(&mut Vec<CteEntry>)) {
+            f(&mut self.ctes)
+        }
+
+        fn get_with(&self) -> Vec<&CteEntry> {
+            self.ctes.iter().collect()
+        }
+    }
+
+    #[test]
+    fn test_no_cte() {
+        let with = WithTest::default();
+        assert_eq!(with.build_with(Dialect::Postgres, &mut vec![]), None);
+    }
+
+    #[test]
+    fn test_multiple_ctes() {
+        let mut with = WithTest::default();
+        with.with(
+            "active",
+            Query::new("SELECT id FROM users WHERE active = true"),
+        )
+        .with("paid", Query::new("SELECT user_id FROM invoices"));
+        assert_eq!(
+            with.build_with(Dialect::MySql, &mut vec![]).unwrap(),
+            "WITH active AS (SELECT id FROM users WHERE active = true), paid AS (SELECT user_id FROM invoices)"
+        );
+    }
+
+    #[test]
+    fn test_materialization_hint() {
+        let mut with = WithTest::default();
+        with.with_materialization(
+            "totals",
+            Query::new("SELECT SUM(amount) FROM orders"),
+            Materialization::NotMaterialized,
+        );
+        assert_eq!(
+            with.build_with(Dialect::Postgres, &mut vec![]).unwrap(),
+            "WITH totals AS NOT MATERIALIZED (SELECT SUM(amount) FROM orders)"
+        );
+        assert_eq!(
+            with.build_with(Dialect::Sqlite, &mut vec![]).unwrap(),
+            "WITH totals AS (SELECT SUM(amount) FROM orders)"
+        );
+    }
+
+    #[test]
+    fn test_recursive_cte() {
+        let mut with = WithTest::default();
+        with.with_recursive(
+            "chart",
+            &["id", "manager_id", "depth"],
+            Query::new("SELECT id, manager_id, 0 FROM employees WHERE manager_id IS NULL"),
+            Query::new(
+                "SELECT e.id, e.manager_id, chart.depth + 1 FROM employees AS e INNER JOIN chart ON e.manager_id = chart.id",
+            ),
+        );
+        assert_eq!(
+            with.build_with(Dialect::Postgres, &mut vec![]).unwrap(),
+            "WITH RECURSIVE chart (id, manager_id, depth) AS (SELECT id, manager_id, 0 FROM employees WHERE manager_id IS NULL UNION ALL SELECT e.id, e.manager_id, chart.depth + 1 FROM employees AS e INNER JOIN chart ON e.manager_id = chart.id)"
+        );
+    }
+
+    #[test]
+    fn test_recursive_cte_on_sql_server() {
+        let mut with = WithTest::default();
+        with.with_recursive(
+            "n",
+            &["x"],
+            Query::new("SELECT 1"),
+            Query::new("SELECT x + 1 FROM n WHERE x < 10"),
+        );
+        assert_eq!(
+            with.build_with(Dialect::SqlServer, &mut vec![]).unwrap(),
+            "WITH n (x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 10)"
+        );
+    }
+
+    #[test]
+    fn test_cte_params() {
+        let mut with = WithTest::default();
+        with.with(
+            "recent",
+            Query::new("SELECT id FROM orders WHERE total > $1").bind(100),
+        )
+        .with_recursive(
+            "n",
+            &["x"],
+            Query::new("SELECT $1").bind(1),
+            Query::new("SELECT x + 1 FROM n WHERE x < $1").bind(10),
+        );
+        let mut params = vec![];
+        assert_eq!(
+            with.build_with(Dialect::Postgres, &mut params).unwrap(),
+            "WITH RECURSIVE recent AS (SELECT id FROM orders WHERE total > $1), n (x) AS (SELECT $2 UNION ALL SELECT x + 1 FROM n WHERE x < $3)"
+        );
+        assert_eq!(
+            params,
+            vec![SqlValue::I32(100), SqlValue::I32(1), SqlValue::I32(10)]
+        );
+    }
+}
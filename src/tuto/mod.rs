@@ -73,6 +73,7 @@ mod macors {
         }
 
         #[test]
+        #[allow(non_local_definitions)]
         fn test_rep0() {
             impl_for_one_or_more!(Nono, u32,);
             assert_eq!(5, u32::foo());